@@ -0,0 +1,205 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that reads the settings of a workspace from its nuhound.toml file
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// The name of the file holding the settings, which is found by walking up from the directory of the
+// manifest of the crate being compiled so that one file at the root of a workspace serves all of its
+// crates. The settings take the form 'key = value' and the rules of the message policy follow a
+// [policy] header, such as:
+//
+// disclose = "line"
+// path_prefix = "/home/build/"
+// location_format = "{file}:{line}: {msg}"
+// require = ["code"]
+// sink = "crate::telemetry::record"
+// no_std = true
+// message_capacity = 128
+//
+// [policy]
+// max_length = 80
+const FILE: &str = "nuhound.toml";
+
+// The settings that may be given, each with the environment variable that takes precedence over it
+const SETTINGS: [(&str, Option<&str>); 12] = [
+    ("disclose", None),
+    ("path_prefix", Some("NUHOUND_PATH_PREFIX")),
+    ("location_format", Some("NUHOUND_LOCATION_FORMAT")),
+    ("require", None),
+    ("sink", None),
+    ("max_message_length", Some("NUHOUND_MAX_MESSAGE_LENGTH")),
+    ("log_threshold", None),
+    ("metrics_threshold", None),
+    ("backtrace_threshold", None),
+    ("log", None),
+    ("no_std", None),
+    ("message_capacity", Some("NUHOUND_MESSAGE_CAPACITY")),
+];
+
+// The settings file found for each crate compiled so far, keyed by the directory of its manifest,
+// so that the file is read once however many macros consult it
+static FILES: Mutex<Vec<File>> = Mutex::new(Vec::new());
+
+// The settings file of a crate, if it has one, and whether it has been registered as a dependency
+struct File {
+    directory: OsString,
+    path: Option<PathBuf>,
+    text: Option<String>,
+    registered: bool,
+}
+
+// Return the text of the settings file of the crate being compiled, or None when there isn't one
+fn read() -> Option<String> {
+    read_from(&env::var_os("CARGO_MANIFEST_DIR")?)
+}
+
+// Return the text of the settings file found by walking up from the given manifest directory,
+// reading it only the first time that the directory is seen
+fn read_from(directory: &OsStr) -> Option<String> {
+    let mut files = FILES.lock().unwrap();
+    if let Some(file) = files.iter().find(|file| file.directory == directory) {
+        return file.text.clone();
+    }
+    let path = Path::new(directory).ancestors()
+        .map(|directory| directory.join(FILE))
+        .find(|path| path.is_file());
+    let text = path.as_ref().map(|path| fs::read_to_string(path)
+        .unwrap_or_else(|error| panic!("Unable to read the settings '{}': {}", path.display(), error)));
+    files.push(File { directory: directory.to_os_string(), path, text: text.clone(), registered: false });
+    text
+}
+
+// Return the items that make the settings file, and the environment variables that take precedence
+// over its settings, dependencies of the crate being compiled, so that the crate is rebuilt when
+// they change. The items are returned only once for each crate.
+pub(crate) fn registration() -> Option<String> {
+    registration_from(&env::var_os("CARGO_MANIFEST_DIR")?)
+}
+
+// Return the items that register the settings file of the given manifest directory, if it has one,
+// and the environment variables of the settings, unless they have been returned before
+pub(crate) fn registration_from(directory: &OsStr) -> Option<String> {
+    read_from(directory);
+    let mut files = FILES.lock().unwrap();
+    let file = files.iter_mut().find(|file| file.directory == directory)?;
+    if file.registered {
+        return None;
+    }
+    file.registered = true;
+    let path = file.path.iter()
+        .map(|path| format!("const _: &str = ::core::include_str!({:?});", path.display().to_string()));
+    let variables = SETTINGS.iter().filter_map(|(_, variable)| *variable)
+        .map(|variable| format!("const _: ::core::option::Option<&str> = ::core::option_env!(\"{variable}\");"));
+    Some(path.chain(variables).collect::<Vec<_>>().join(" "))
+}
+
+// Return the lines of the given section of the settings, where the settings that precede any header
+// form the section with an empty name
+pub(crate) fn section(text: &str, name: &str) -> Vec<String> {
+    let mut current = String::new();
+    let mut lines = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        match line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            Some(header) => current = header.trim().to_string(),
+            None if current == name => lines.push(line.to_string()),
+            None => {}
+        }
+    }
+    lines
+}
+
+// Return the value of the given setting, taken from its environment variable when that is set and
+// otherwise from the settings file. The quotes of a string value are removed.
+pub(crate) fn setting(key: &str) -> Option<String> {
+    let (_, variable) = SETTINGS.iter().find(|(setting, _)| *setting == key)
+        .unwrap_or_else(|| panic!("Unknown setting '{key}'"));
+    if let Some(value) = variable.and_then(|variable| env::var(variable).ok()).filter(|value| !value.is_empty()) {
+        return Some(value);
+    }
+    lookup(&read()?, key)
+}
+
+// Return the value of the given setting from the text of a settings file
+pub(crate) fn lookup(text: &str, key: &str) -> Option<String> {
+    section(text, "").iter().find_map(|line| {
+        let (name, value) = crate::split_assignment(line)
+            .unwrap_or_else(|| panic!("The setting '{line}' in {FILE} must take the form 'key = value'"));
+        if !SETTINGS.iter().any(|(setting, _)| *setting == name) {
+            panic!("Unknown setting '{name}' in {FILE}. Expected 'disclose', 'path_prefix', 'location_format', \
+                'require', 'sink', 'max_message_length', 'log_threshold', 'metrics_threshold', \
+                'backtrace_threshold', 'log', 'no_std' or 'message_capacity'");
+        }
+        (name == key).then(|| unquote(&value))
+    })
+}
+
+// Return the string literals of a setting that takes a list, such as require = ["code"]
+pub(crate) fn list(key: &str) -> Vec<String> {
+    let Some(value) = setting(key) else {
+        return Vec::new();
+    };
+    let list = value.strip_prefix('[').and_then(|value| value.strip_suffix(']'))
+        .unwrap_or_else(|| panic!("The setting '{key}' in {FILE} must be a list of strings, such as [\"code\"]"));
+    crate::analyse(list.chars()).into_iter()
+        .filter(|item| !item.is_empty())
+        .map(|item| unquote(&item))
+        .collect()
+}
+
+// Return the text of the rules of the message policy given in the settings file, if any
+pub(crate) fn policy() -> Option<String> {
+    let lines = section(&read()?, "policy");
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+// Remove the quotes of a string value, resolving the escaped quotes and backslashes within it
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+        Some(value) => value.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
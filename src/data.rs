@@ -0,0 +1,324 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that splits the input of a derive macro into its component parts
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+// The data of a struct or enum given to a derive macro. A struct is treated as an enum with a single
+// variant that has the same name and attributes as the struct.
+pub(crate) struct Data {
+    // The attributes of the struct or enum itself
+    pub(crate) attributes: Vec<String>,
+    pub(crate) is_enum: bool,
+    pub(crate) name: String,
+    // Generic parameters including the angle brackets, such as <'a, T: Clone>
+    pub(crate) generics: String,
+    // The where clause including the where keyword
+    pub(crate) where_clause: String,
+    pub(crate) variants: Vec<Variant>,
+}
+
+pub(crate) struct Variant {
+    pub(crate) attributes: Vec<String>,
+    pub(crate) name: String,
+    pub(crate) fields: Fields,
+}
+
+pub(crate) enum Fields {
+    Unit,
+    // The types of the fields of a tuple struct or variant
+    Tuple(Vec<Field>),
+    // The names and types of the fields of a struct or struct variant
+    Named(Vec<Field>),
+}
+
+pub(crate) struct Field {
+    pub(crate) attributes: Vec<String>,
+    // The name of the field, or its index for a tuple field
+    pub(crate) name: String,
+    pub(crate) ty: String,
+}
+
+impl Data {
+    // Split the input of a derive macro into its component parts
+    pub(crate) fn parse(item: TokenStream) -> Self {
+        let trees: Vec<TokenTree> = item.into_iter().collect();
+        let (attributes, mut index) = attributes(&trees, 0);
+        while !matches!(&trees[index], TokenTree::Ident(ident) if ["struct", "enum", "union"].contains(&ident.to_string().as_str())) {
+            index += 1;
+        }
+        let keyword = trees[index].to_string();
+        if keyword == "union" {
+            panic!("Unions are not supported");
+        }
+        let name = trees[index + 1].to_string();
+        index += 2;
+
+        let mut generics = String::new();
+        if is_punct(trees.get(index), '<') {
+            let close = closing_angle(&trees, index);
+            generics = text(&trees[index..=close]);
+            index = close + 1;
+        }
+        // The where clause ends at the braces of the body. The parentheses of a tuple struct come
+        // before any where clause but a where clause may itself contain parentheses.
+        let start = index;
+        let mut within_where = false;
+        while index < trees.len() && !is_punct(trees.get(index), ';') {
+            match &trees[index] {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => break,
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Parenthesis && !within_where => break,
+                TokenTree::Ident(ident) if ident.to_string() == "where" => within_where = true,
+                _ => (),
+            }
+            index += 1;
+        }
+        let mut where_clause = text(&trees[start..index]);
+
+        let variants = match trees.get(index) {
+            Some(TokenTree::Group(group)) if keyword == "enum" => {
+                split_commas(group.stream()).into_iter().map(|variant| parse_variant(&variant)).collect()
+            }
+            Some(TokenTree::Group(group)) => {
+                let fields = parse_fields(group);
+                // The where clause of a tuple struct follows its fields
+                if group.delimiter() == Delimiter::Parenthesis {
+                    let end = trees.iter().rposition(|tree| !is_punct(Some(tree), ';')).unwrap_or(index);
+                    where_clause = text(&trees[index + 1..=end.max(index)]);
+                }
+                vec![Variant { attributes: attributes.clone(), name: name.clone(), fields }]
+            }
+            _ => vec![Variant { attributes: attributes.clone(), name: name.clone(), fields: Fields::Unit }],
+        };
+
+        Self { attributes, is_enum: keyword == "enum", name, generics, where_clause, variants }
+    }
+
+    // Return the generic parameters without their bounds, such as <'a, T>, for use after the name of
+    // the type in an impl block
+    pub(crate) fn type_generics(&self) -> String {
+        if self.generics.is_empty() {
+            return String::new();
+        }
+        let inner: TokenStream = self.generics.trim()[1..self.generics.trim().len() - 1].parse().unwrap();
+        let parameters: Vec<String> = split_commas(inner).iter()
+            .map(|parameter| {
+                let start = usize::from(matches!(parameter.first(), Some(TokenTree::Ident(ident)) if ident.to_string() == "const"));
+                match &parameter[start..] {
+                    [TokenTree::Punct(quote), TokenTree::Ident(lifetime), ..] if quote.as_char() == '\'' => format!("'{lifetime}"),
+                    [name, ..] => name.to_string(),
+                    [] => String::new(),
+                }
+            })
+            .collect();
+        format!("<{}>", parameters.join(", "))
+    }
+
+    // Return the path of a variant for use in a pattern, such as MyError::Io or MyError for a struct
+    pub(crate) fn path(&self, variant: &Variant) -> String {
+        if self.is_enum {
+            format!("{}::{}", self.name, variant.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+impl Variant {
+    // Return a pattern that matches the variant whilst binding each of its fields, such as
+    // MyError::Io(field0) or MyError::Parse { line, column }
+    pub(crate) fn pattern(&self, path: &str) -> String {
+        match &self.fields {
+            Fields::Unit => path.to_string(),
+            Fields::Tuple(fields) => {
+                let bindings: Vec<String> = fields.iter().map(|field| format!("field{}", field.name)).collect();
+                format!("{path}({})", bindings.join(", "))
+            }
+            Fields::Named(fields) => {
+                let bindings: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+                format!("{path} {{ {} }}", bindings.join(", "))
+            }
+        }
+    }
+
+    // Return a pattern that matches the variant without binding any of its fields
+    pub(crate) fn wildcard(&self, path: &str) -> String {
+        match &self.fields {
+            Fields::Unit => path.to_string(),
+            Fields::Tuple(_) => format!("{path}(..)"),
+            Fields::Named(_) => format!("{path} {{ .. }}"),
+        }
+    }
+
+    // Return the fields of the variant
+    pub(crate) fn fields(&self) -> &[Field] {
+        match &self.fields {
+            Fields::Unit => &[],
+            Fields::Tuple(fields) | Fields::Named(fields) => fields,
+        }
+    }
+}
+
+impl Field {
+    // Return the name by which the field is bound in the patterns created by Variant::pattern
+    pub(crate) fn binding(&self) -> String {
+        if self.name.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("field{}", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+// Return the options of the attributes with the given name, such as the code of
+// #[nuhound(code = "E100")], as the text of the attributes within the parentheses
+pub(crate) fn find_attributes(attributes: &[String], name: &str) -> Vec<String> {
+    attributes.iter()
+        .filter_map(|attribute| {
+            let inner = attribute.strip_prefix('#')?.trim().strip_prefix('[')?.strip_suffix(']')?.trim();
+            let rest = inner.strip_prefix(name)?.trim_start();
+            if rest.is_empty() {
+                Some(String::new())
+            } else if let Some(rest) = rest.strip_prefix('=') {
+                Some(rest.trim().to_string())
+            } else {
+                Some(rest.strip_prefix('(')?.strip_suffix(')')?.trim().to_string())
+            }
+        })
+        .collect()
+}
+
+// Collect the outer attributes starting at the given index, returning them together with the index
+// of the first token that follows them
+fn attributes(trees: &[TokenTree], mut index: usize) -> (Vec<String>, usize) {
+    let mut attributes = Vec::new();
+    while is_punct(trees.get(index), '#') {
+        attributes.push(format!("#{}", trees[index + 1]));
+        index += 2;
+    }
+    (attributes, index)
+}
+
+fn parse_variant(trees: &[TokenTree]) -> Variant {
+    let (attributes, index) = attributes(trees, 0);
+    let name = trees[index].to_string();
+    let fields = match trees.get(index + 1) {
+        Some(TokenTree::Group(group)) => parse_fields(group),
+        _ => Fields::Unit,
+    };
+    Variant { attributes, name, fields }
+}
+
+fn parse_fields(group: &proc_macro::Group) -> Fields {
+    let fields = split_commas(group.stream());
+    match group.delimiter() {
+        Delimiter::Parenthesis => Fields::Tuple(fields.iter().enumerate().map(|(position, field)| {
+            let (attributes, index) = attributes(field, 0);
+            let index = skip_visibility(field, index);
+            Field { attributes, name: position.to_string(), ty: text(&field[index..]) }
+        }).collect()),
+        Delimiter::Brace => Fields::Named(fields.iter().map(|field| {
+            let (attributes, index) = attributes(field, 0);
+            let index = skip_visibility(field, index);
+            Field { attributes, name: field[index].to_string(), ty: text(&field[index + 2..]) }
+        }).collect()),
+        _ => Fields::Unit,
+    }
+}
+
+// Return the index of the first token after any visibility such as pub or pub(crate)
+fn skip_visibility(trees: &[TokenTree], mut index: usize) -> usize {
+    if matches!(trees.get(index), Some(TokenTree::Ident(ident)) if ident.to_string() == "pub") {
+        index += 1;
+        if matches!(trees.get(index), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis) {
+            index += 1;
+        }
+    }
+    index
+}
+
+// Split the tokens at each comma that is not enclosed by angle brackets, leaving out empty parts
+fn split_commas(stream: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0;
+    let mut previous: Option<TokenTree> = None;
+    for tree in stream {
+        match &tree {
+            TokenTree::Punct(punct) if punct.as_char() == ',' && depth == 0 => {
+                parts.push(Vec::new());
+                previous = None;
+                continue;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '<' => depth += 1,
+            TokenTree::Punct(punct) if punct.as_char() == '>' && !is_punct(previous.as_ref(), '-') => depth -= 1,
+            _ => (),
+        }
+        previous = Some(tree.clone());
+        parts.last_mut().unwrap().push(tree);
+    }
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+// Return the index of the angle bracket that closes the one at the given index
+fn closing_angle(trees: &[TokenTree], open: usize) -> usize {
+    let mut depth = 0;
+    for index in open..trees.len() {
+        if is_punct(trees.get(index), '<') {
+            depth += 1;
+        } else if is_punct(trees.get(index), '>') && !is_punct(trees.get(index.wrapping_sub(1)), '-') {
+            depth -= 1;
+            if depth == 0 {
+                return index;
+            }
+        }
+    }
+    panic!("Unbalanced angle brackets");
+}
+
+fn is_punct(tree: Option<&TokenTree>, c: char) -> bool {
+    matches!(tree, Some(TokenTree::Punct(punct)) if punct.as_char() == c)
+}
+
+// Collect the tokens into text
+fn text(trees: &[TokenTree]) -> String {
+    TokenStream::from_iter(trees.iter().cloned()).to_string()
+}
@@ -0,0 +1,350 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that handles the flags that may be given at the start of the input of a macro
+
+use crate::rewrite;
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use std::cell::RefCell;
+
+// The flags that alter the code generated by a single macro. They are written at the start of the
+// input of the macro, before any other arguments, such as `convert!(@quiet text.parse::<u32>(), ...)`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Flags {
+    // Emit plain messages, ignoring the 'disclose' feature and leaving out the text of arguments
+    pub(crate) quiet: bool,
+    // Emit disclosed messages, ignoring the 'disclose' feature, so that a single call always carries
+    // the location of the error
+    pub(crate) disclose: bool,
+    // Report the location of the caller of the enclosing function, captured by the track_caller
+    // attribute, rather than the location of the macro
+    pub(crate) caller: bool,
+    // Capture the location of the caller of the enclosing function where the macro is expanded,
+    // given as '@caller(1)', so that a function marked with the standard track_caller attribute
+    // reports the location of its caller without the nuhound::track_caller attribute
+    pub(crate) shim: bool,
+    // Compose the full message, with its location and backtrace, for only every Nth error at the
+    // site and a cheap static message with a count of the occurrences for the rest
+    pub(crate) sample: Option<u64>,
+    // Mark the error with the number of layers beneath it that should be displayed, so that a top
+    // level handler may print a concise trace
+    pub(crate) depth: Option<u64>,
+    // Record only the type of the cause of the error rather than linking the cause into the chain,
+    // so that data held by the cause is not propagated
+    pub(crate) opaque: bool,
+    // Link the cause of the error into the chain by its Display output, so that a cause that doesn't
+    // implement the Error trait may still be converted
+    pub(crate) display: bool,
+    // The level given by a severity argument, such as `severity = warn`, which is not a flag but is
+    // held with them so that the generators may consult it
+    pub(crate) severity: Option<&'static str>,
+    // The level given by a log argument, such as `log = warn`, or "off", which is held with the flags
+    // likewise
+    pub(crate) log: Option<&'static str>,
+    // The string literals given by a tags argument, such as `tags = ["io", "startup"]`, which are
+    // held with the flags likewise
+    pub(crate) tags: Vec<String>,
+    // The key and value pairs given after `fields:`, such as `fields: request_id = id`, which are
+    // held with the flags likewise
+    pub(crate) fields: Vec<(String, String)>,
+    // The expression given by a help argument, such as `help = "run the setup first"`, which is
+    // held with the flags likewise
+    pub(crate) help: Option<String>,
+    // The expression given by a url argument, such as `url = "https://docs.example.com/E042"`, which
+    // is held with the flags likewise
+    pub(crate) url: Option<String>,
+    // The code given by an exit_code argument, such as `exit_code = 69`, which is held with the flags
+    // likewise
+    pub(crate) exit: Option<i32>,
+    // The classification given by a retriable argument, such as `retriable = true`, which is held
+    // with the flags likewise
+    pub(crate) retriable: Option<bool>,
+    // The expression given by a user argument, such as `user = "The file appears to be corrupted"`,
+    // which is held with the flags likewise
+    pub(crate) user: Option<String>,
+    // The key of a message that is looked up in the catalog, such as `key = "errors.config.missing"`,
+    // which is held with the flags so that it may be recorded with the site
+    pub(crate) key: Option<String>,
+    // The error code given by a code argument, such as `code = "E100"`, which is held with the flags
+    // so that codes shared by several sites may be detected
+    pub(crate) code: Option<String>,
+    // The format string of the message as it is written, before the error code and the fields of the
+    // context are added, which is recorded with the site
+    pub(crate) template: Option<String>,
+    // The format string and arguments of the message as they are given, such as `"unable to load
+    // {}"` and `path`, which are held with the flags so that the message may be logged by defmt
+    // without formatting it
+    pub(crate) format: Vec<String>,
+}
+
+thread_local! {
+    // The flags of the macro currently being expanded, which are consulted by the generators
+    static CURRENT: RefCell<Flags> = RefCell::new(Flags::default());
+    // The file, line and column of the input of the macro currently being expanded, when known
+    static LOCATION: RefCell<Option<(String, usize, usize)>> = const { RefCell::new(None) };
+}
+
+impl Flags {
+    // Split the flags from the start of the text of a macro input, returning them together with
+    // the remaining text
+    pub(crate) fn split(text: &str) -> (Self, &str) {
+        let mut flags = Self::default();
+        let mut scoped = None;
+        let mut rest = text.trim_start();
+        while let Some(after) = rest.strip_prefix('@') {
+            let after = after.trim_start();
+            let end = after.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(after.len());
+            let name = &after[..end];
+            match name {
+                "quiet" => flags.quiet = true,
+                "disclose" => flags.disclose = true,
+                "caller" => {
+                    flags.caller = true;
+                    if is_count(&after[end..]) {
+                        let (count, remainder) = split_count(name, &after[end..]);
+                        if count != 1 {
+                            panic!("The '@caller' flag can only report the immediate caller, as '@caller(1)'. Mark each \
+                                function in between with #[track_caller] to report a more distant caller");
+                        }
+                        flags.shim = true;
+                        rest = remainder;
+                        continue;
+                    }
+                }
+                // Given by an enclosing nuhound macro to the nuhound macros within its input, holding
+                // the location of the input, which is read by located
+                "at" => {
+                    rest = skip_pin(&after[end..]);
+                    continue;
+                }
+                "opaque" => flags.opaque = true,
+                "display" => flags.display = true,
+                "sample" | "depth" => {
+                    let (count, remainder) = split_count(name, &after[end..]);
+                    if name == "sample" {
+                        flags.sample = Some(count);
+                    } else {
+                        flags.depth = Some(count);
+                    }
+                    rest = remainder;
+                    continue;
+                }
+                // Given by the disclose and suppress_disclose attributes to the macros within an
+                // item. The innermost attribute inserts its flag first and so takes precedence,
+                // while a flag given directly to the macro overrides them all.
+                "scoped_quiet" => { scoped.get_or_insert(false); }
+                "scoped_disclose" => { scoped.get_or_insert(true); }
+                _ => panic!("Unknown flag '@{name}'. Expected '@quiet', '@disclose', '@caller', '@opaque', '@display', \
+                    '@sample' or '@depth'"),
+            }
+            rest = after[end..].trim_start();
+        }
+        if flags.quiet && flags.disclose {
+            panic!("The '@quiet' and '@disclose' flags cannot be given together");
+        }
+        if flags.opaque && flags.display {
+            panic!("The '@opaque' and '@display' flags cannot be given together");
+        }
+        if !flags.quiet && !flags.disclose {
+            match scoped {
+                Some(true) => flags.disclose = true,
+                Some(false) => flags.quiet = true,
+                None => (),
+            }
+        }
+        (flags, rest)
+    }
+}
+
+// Determine whether the text that follows the name of a flag starts with a count in parentheses,
+// such as '(1)', rather than with a parenthesised expression
+fn is_count(text: &str) -> bool {
+    text.trim_start().strip_prefix('(')
+        .and_then(|count| count.split_once(')'))
+        .is_some_and(|(count, _)| !count.trim().is_empty() && count.trim().chars().all(|c| c.is_ascii_digit()))
+}
+
+// Skip the location given in parentheses to an '@at' flag, such as '("src/main.rs", 12, 9)', which
+// may hold parentheses within its string, returning the text that follows it
+fn skip_pin(text: &str) -> &str {
+    let text = text.trim_start();
+    let (mut quoted, mut escaped) = (false, false);
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ')' if !quoted => return text[index + 1..].trim_start(),
+            _ => (),
+        }
+    }
+    panic!("The '@at' flag expects a location, such as '@at(\"src/main.rs\", 12, 9)'")
+}
+
+// Split the count given in parentheses to a flag, such as '@sample(100)', from the text that follows
+// the name of the flag, returning it together with the remaining text
+fn split_count<'a>(name: &str, text: &'a str) -> (u64, &'a str) {
+    let (count, remainder) = text.trim_start().strip_prefix('(')
+        .and_then(|count| count.split_once(')'))
+        .unwrap_or_else(|| panic!("The '@{name}' flag expects a count, such as '@{name}(3)'"));
+    let count = count.trim().parse::<u64>().ok().filter(|&count| count > 0)
+        .unwrap_or_else(|| panic!("The '@{name}' flag expects a count greater than zero, not '{}'", count.trim()));
+    (count, remainder.trim_start())
+}
+
+// Return the flags of the macro currently being expanded
+pub(crate) fn current() -> Flags {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+// Update the flags of the macro currently being expanded
+pub(crate) fn annotate(update: impl FnOnce(&mut Flags)) {
+    CURRENT.with(|current| update(&mut current.borrow_mut()));
+}
+
+// Split the flags from the start of the text of a macro input and build the output of the macro
+// from the remaining text with those flags in effect
+pub(crate) fn scoped<T>(text: &str, build: impl FnOnce(&str) -> T) -> T {
+    let (flags, rest) = Flags::split(text);
+    let previous = CURRENT.with(|current| current.replace(flags));
+    let output = build(rest);
+    CURRENT.with(|current| current.replace(previous));
+    output
+}
+
+// Return the file, line and column of the input of the macro currently being expanded, when known
+pub(crate) fn location() -> Option<(String, usize, usize)> {
+    LOCATION.with(|location| location.borrow().clone())
+}
+
+// Build the output of a function like macro as with scoped, with the location of its input in
+// effect. The location is taken from the span of the first token after the flags, looking inside the
+// invisible groups that wrap the fragments passed on by other macros, which is where the user wrote
+// the expression even when the macro is invoked by another macro. The output of a nuhound macro is
+// built from the text of its input, which loses the spans of the nuhound macros nested within it,
+// such as the convert! in `examine!(convert!(fail(2), "inner"), "outer")`. Each of these is given its
+// own location in an '@at' flag before the text is taken, which is then preferred to the span.
+pub(crate) fn located<T>(stream: TokenStream, build: impl FnOnce(&str) -> T) -> T {
+    let mut trees = stream.clone().into_iter().peekable();
+    let mut pinned = None;
+    while let Some(TokenTree::Punct(punct)) = trees.peek() {
+        if punct.as_char() != '@' {
+            break;
+        }
+        trees.next();
+        let name = match trees.next() {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => String::new(),
+        };
+        let count = matches!(trees.peek(), Some(TokenTree::Group(group)) if is_count(&group.to_string()));
+        if name == "at" {
+            if let Some(TokenTree::Group(group)) = trees.next() {
+                pinned = pinned.or_else(|| unpin(&group.stream()));
+            }
+        } else if ["sample", "depth"].contains(&name.as_str()) || (name == "caller" && count) {
+            trees.next();
+        }
+    }
+    let location = pinned.or_else(|| span_location(trees.next()));
+    let stream = rewrite::flag_each_macro(stream, &crate::MACROS, &|input| {
+        let mut trees = input.clone().into_iter();
+        match (trees.next(), trees.next()) {
+            (Some(TokenTree::Punct(punct)), Some(TokenTree::Ident(ident))) if punct.as_char() == '@' && ident.to_string() == "at" => None,
+            _ => skip_flags(input).and_then(|first| span_location(Some(first)))
+                .map(|(file, line, column)| format!("@at({file:?}, {line}, {column})")),
+        }
+    });
+    at(location, &stream.to_string(), build)
+}
+
+// Return the first token of the input of a macro after its flags
+fn skip_flags(input: &TokenStream) -> Option<TokenTree> {
+    let mut trees = input.clone().into_iter().peekable();
+    while let Some(TokenTree::Punct(punct)) = trees.peek() {
+        if punct.as_char() != '@' {
+            break;
+        }
+        trees.next();
+        trees.next();
+        if matches!(trees.peek(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis) {
+            trees.next();
+        }
+    }
+    trees.next()
+}
+
+// Return the file, line and column of the span of a token, looking inside the invisible groups that
+// wrap the fragments passed on by other macros
+fn span_location(mut first: Option<TokenTree>) -> Option<(String, usize, usize)> {
+    while let Some(TokenTree::Group(group)) = &first {
+        if group.delimiter() != Delimiter::None {
+            break;
+        }
+        first = group.stream().into_iter().next();
+    }
+    first
+        .map(|tree| tree.span())
+        .map(|span| (span.file(), span.line(), span.column()))
+        .filter(|(file, line, _)| !file.is_empty() && *line > 0)
+}
+
+// Return the file, line and column given to an '@at' flag, such as '@at("src/main.rs", 12, 9)'
+fn unpin(stream: &TokenStream) -> Option<(String, usize, usize)> {
+    let text = stream.to_string();
+    let (file, rest) = text.trim().strip_prefix('"')?.rsplit_once('"')?;
+    let mut numbers = rest.split(',').map(str::trim).filter(|number| !number.is_empty());
+    let line = numbers.next()?.parse().ok()?;
+    let column = numbers.next()?.parse().ok()?;
+    let mut chars = file.chars();
+    let mut unescaped = String::new();
+    while let Some(c) = chars.next() {
+        unescaped.extend(if c == '\\' { chars.next() } else { Some(c) });
+    }
+    Some((unescaped, line, column))
+}
+
+// Build the output of a function like macro as with scoped, with the given location in effect
+pub(crate) fn at<T>(location: Option<(String, usize, usize)>, text: &str, build: impl FnOnce(&str) -> T) -> T {
+    let previous = LOCATION.with(|current| current.replace(location));
+    let output = scoped(text, build);
+    LOCATION.with(|current| current.replace(previous));
+    output
+}
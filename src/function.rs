@@ -0,0 +1,353 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that splits a function item into its component parts
+
+// The signature of a function is scanned one character at a time. These are the characters that
+// are treated as opening and closing brackets. The angle brackets are only treated as such outside
+// the body of the function where they can only enclose generic parameters.
+const OPENING: [char; 4] = ['(', '[', '{', '<'];
+const CLOSING: [char; 4] = [')', ']', '}', '>'];
+
+// A structure containing the component parts of a function item
+pub(crate) struct Function {
+    // Outer attributes such as #[inline] including doc comments
+    pub(crate) attributes: Vec<String>,
+    // The visibility and qualifiers such as const, async, unsafe and extern "C"
+    pub(crate) qualifiers: String,
+    pub(crate) name: String,
+    // The generic parameters including the enclosing angle brackets
+    pub(crate) generics: String,
+    // The function parameters without the enclosing parentheses
+    pub(crate) inputs: String,
+    // The return type without the arrow
+    pub(crate) output: Option<String>,
+    pub(crate) where_clause: String,
+    // The statements of the function body without the enclosing braces
+    pub(crate) body: String,
+}
+
+impl Function {
+    // Split the text of a function item into its component parts
+    pub(crate) fn parse(item: &str) -> Self {
+        let chars: Vec<char> = item.trim().chars().collect();
+        let mut index = 0;
+        let mut attributes = Vec::new();
+        while chars.get(index) == Some(&'#') {
+            let open = index + chars[index..].iter().position(|&c| c == '[')
+                .unwrap_or_else(|| panic!("Malformed attribute"));
+            let close = matching_bracket(&chars, open);
+            attributes.push(text(&chars[index..=close]));
+            index = skip_whitespace(&chars, close + 1);
+        }
+
+        let keyword = find_keyword(&chars, index, "fn")
+            .unwrap_or_else(|| panic!("This attribute may only be applied to a function"));
+        let qualifiers = text(&chars[index..keyword]);
+        index = skip_whitespace(&chars, keyword + 2);
+        let start = index;
+        while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+            index += 1;
+        }
+        let name = text(&chars[start..index]);
+        index = skip_whitespace(&chars, index);
+
+        let mut generics = String::new();
+        if chars.get(index) == Some(&'<') {
+            let close = matching(&chars, index);
+            generics = text(&chars[index..=close]);
+            index = skip_whitespace(&chars, close + 1);
+        }
+
+        if chars.get(index) != Some(&'(') {
+            panic!("Unable to find the parameters of function '{name}'");
+        }
+        let close = matching(&chars, index);
+        let inputs = text(&chars[index + 1..close]);
+        index = skip_whitespace(&chars, close + 1);
+
+        let open = find_top_level(&chars, index, |c| c == '{' || c == ';')
+            .filter(|&open| chars[open] == '{')
+            .unwrap_or_else(|| panic!("Function '{name}' must have a body"));
+        let signature = &chars[index..open];
+        let (output, where_clause) = match find_keyword(signature, 0, "where") {
+            Some(clause) => (&signature[..clause], text(&signature[clause..])),
+            None => (signature, String::new()),
+        };
+        let output = text(output);
+        let output = output.strip_prefix("->").map(|output| output.trim().to_string());
+        // The body is the last part of the item and may contain comparison operators that would
+        // be mistaken for angle brackets, so it is taken to run to the final closing brace
+        let body = text(&chars[open + 1..chars.len() - 1]);
+
+        Self { attributes, qualifiers, name, generics, inputs, output, where_clause, body }
+    }
+
+    // Determine whether the function is an async function
+    pub(crate) fn is_async(&self) -> bool {
+        self.qualifiers.split_whitespace().any(|qualifier| qualifier == "async")
+    }
+
+    // Return the success type of a function that returns a Report<T> or Result<T, E>
+    pub(crate) fn success_type(&self) -> Option<String> {
+        let output: Vec<char> = self.output.as_deref()?.chars().collect();
+        let open = find_top_level(&output, 0, |c| c == '<')?;
+        let name = text(&output[..open]);
+        let close = matching(&output, open);
+        let inner = &output[open + 1..close];
+        if name == "Report" || name.ends_with("::Report") {
+            Some(text(inner))
+        } else if name == "Result" || name.ends_with("::Result") {
+            find_top_level(inner, 0, |c| c == ',').map(|comma| text(&inner[..comma]))
+        } else {
+            None
+        }
+    }
+
+    // Determine whether the function returns a Report<T> or a Result<T, Nuhound>
+    pub(crate) fn returns_report(&self) -> bool {
+        let Some(output) = self.output.as_deref() else {
+            return false;
+        };
+        let output: Vec<char> = output.chars().collect();
+        let Some(open) = find_top_level(&output, 0, |c| c == '<') else {
+            return false;
+        };
+        let name = text(&output[..open]);
+        let close = matching(&output, open);
+        let inner = &output[open + 1..close];
+        if name == "Report" || name.ends_with("::Report") {
+            true
+        } else if name == "Result" || name.ends_with("::Result") {
+            find_top_level(inner, 0, |c| c == ',').is_some_and(|comma| {
+                let error = text(&inner[comma + 1..]);
+                error == "Nuhound" || error.ends_with("::Nuhound")
+            })
+        } else {
+            false
+        }
+    }
+
+    // Reassemble the function item with a replacement body
+    pub(crate) fn with_body(&self, body: &str) -> String {
+        format!("{} {{\n{}\n}}", self.signature(), body.trim())
+    }
+
+    // Reassemble the signature of the function, including its attributes
+    pub(crate) fn signature(&self) -> String {
+        let mut signature = String::new();
+        for attribute in &self.attributes {
+            signature.push_str(attribute);
+            signature.push('\n');
+        }
+        signature.push_str(&format!("{} fn {}{}({})", self.qualifiers, self.name, self.generics, self.inputs));
+        if let Some(output) = &self.output {
+            signature.push_str(&format!(" -> {output}"));
+        }
+        if !self.where_clause.is_empty() {
+            signature.push_str(&format!(" {}", self.where_clause));
+        }
+        signature.trim_start().to_string()
+    }
+
+    // Create an expression that evaluates the original body of the function. The body is placed in
+    // a closure, or an async block for async functions, so that any return statements or question
+    // mark operators within it yield the value of the expression rather than leaving the function.
+    pub(crate) fn invoke_body(&self) -> String {
+        if self.is_async() {
+            format!("{}.await", self.future_body())
+        } else {
+            format!("(|| -> {0} {{ {1} }})()", self.inferred_output(), self.body)
+        }
+    }
+
+    // Create an async block, for an async function, that evaluates to the value of the original body
+    pub(crate) fn future_body(&self) -> String {
+        format!("async {{\n        let outcome: {0} = {{ {1} }};\n        outcome\n    }}", self.inferred_output(), self.body)
+    }
+
+    // The return type of the function as it may be written for a closure or a binding, where an
+    // `impl Trait` type isn't allowed, so each is left for the compiler to infer
+    fn inferred_output(&self) -> String {
+        let output: Vec<char> = self.output.as_deref().unwrap_or("()").chars().collect();
+        let mut inferred = String::new();
+        let mut index = 0;
+        while index < output.len() {
+            let boundary = index == 0 || !(output[index - 1].is_alphanumeric() || output[index - 1] == '_');
+            let keyword = output[index..].starts_with(&['i', 'm', 'p', 'l'])
+                && output.get(index + 4).is_none_or(|c| !(c.is_alphanumeric() || *c == '_'));
+            if boundary && keyword {
+                inferred.push('_');
+                index = skip_bounds(&output, index + 4);
+            } else {
+                inferred.push(output[index]);
+                index += 1;
+            }
+        }
+        inferred
+    }
+}
+
+// Return the index of the character that ends the bounds of an `impl Trait` type starting at the
+// given index, being the comma or closing bracket of the type that encloses it
+fn skip_bounds(chars: &[char], mut index: usize) -> usize {
+    let mut depth = 0;
+    while index < chars.len() {
+        match chars[index] {
+            '<' | '(' | '[' => depth += 1,
+            '>' if index > 0 && chars[index - 1] == '-' => (),
+            '>' | ')' | ']' | ',' if depth == 0 => break,
+            '>' | ')' | ']' => depth -= 1,
+            _ => (),
+        }
+        index += 1;
+    }
+    index
+}
+
+// Collect a slice of characters into a trimmed string
+fn text(chars: &[char]) -> String {
+    chars.iter().collect::<String>().trim().to_string()
+}
+
+// Return the index of the first character at or after the given index that is not whitespace
+fn skip_whitespace(chars: &[char], mut index: usize) -> usize {
+    while index < chars.len() && chars[index].is_whitespace() {
+        index += 1;
+    }
+    index
+}
+
+// Return the index of the character that closes a string or character literal starting at the
+// given index, or the index itself when the single quote starts a lifetime
+fn skip_literal(chars: &[char], index: usize) -> usize {
+    let quote = chars[index];
+    if quote == '\'' {
+        let lifetime = chars.get(index + 1).is_some_and(|c| c.is_alphanumeric() || *c == '_')
+            && chars.get(index + 2) != Some(&'\'');
+        if lifetime {
+            return index;
+        }
+    }
+    let mut next = index + 1;
+    while next < chars.len() {
+        match chars[next] {
+            '\\' => next += 1,
+            c if c == quote => return next,
+            _ => (),
+        }
+        next += 1;
+    }
+    chars.len() - 1
+}
+
+// Determine whether the characters at the given index form the arrow of a return type or closure
+fn is_arrow(chars: &[char], index: usize) -> bool {
+    chars[index] == '>' && index > 0 && chars[index - 1] == '-'
+}
+
+// Return the index of the bracket that closes the bracket at the given index
+fn matching(chars: &[char], open: usize) -> usize {
+    matching_within(chars, open, &OPENING, &CLOSING)
+}
+
+// Return the index of the bracket that closes the bracket at the given index, ignoring angle
+// brackets, which may be comparison operators within the arguments of an attribute
+fn matching_bracket(chars: &[char], open: usize) -> usize {
+    matching_within(chars, open, &OPENING[..3], &CLOSING[..3])
+}
+
+// Return the index of the bracket that closes the bracket at the given index using the given pairs
+fn matching_within(chars: &[char], open: usize, opening: &[char], closing: &[char]) -> usize {
+    let mut depth = 0;
+    let mut index = open;
+    while index < chars.len() {
+        match chars[index] {
+            '"' | '\'' => index = skip_literal(chars, index),
+            c if is_arrow(chars, index) && c == '>' => (),
+            c if opening.contains(&c) => depth += 1,
+            c if closing.contains(&c) => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => (),
+        }
+        index += 1;
+    }
+    panic!("Unbalanced brackets in '{}'", text(chars));
+}
+
+// Find the first character at or after the given index, that is not enclosed by brackets or
+// quotes, for which the test is true
+fn find_top_level(chars: &[char], mut index: usize, test: impl Fn(char) -> bool) -> Option<usize> {
+    while index < chars.len() {
+        match chars[index] {
+            c if test(c) => return Some(index),
+            '"' | '\'' => index = skip_literal(chars, index),
+            c if is_arrow(chars, index) && c == '>' => (),
+            c if OPENING.contains(&c) => index = matching(chars, index),
+            _ => (),
+        }
+        index += 1;
+    }
+    None
+}
+
+// Find the first occurrence of a keyword at or after the given index that is not enclosed by
+// brackets or quotes and is not part of a longer identifier
+pub(crate) fn find_keyword(chars: &[char], index: usize, keyword: &str) -> Option<usize> {
+    let keyword: Vec<char> = keyword.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let mut start = index;
+    while let Some(found) = find_top_level(chars, start, |c| c == keyword[0]) {
+        let end = found + keyword.len();
+        let matched = chars.get(found..end) == Some(&keyword[..])
+            && (found == 0 || !is_word(&chars[found - 1]))
+            && !chars.get(end).is_some_and(is_word);
+        if matched {
+            return Some(found);
+        }
+        start = found + 1;
+    }
+    None
+}
@@ -51,27 +51,77 @@
 //! convert!, examine! and custom! macros. These macros are designed to help simplify error
 //! handling in a concise and consistent Rust style in line with the nuhound paradigm.
 //!
+//! The features are checked by the code that the macros generate, so they are declared in the
+//! crate that uses the macros, such as `[features] disclose = []`, and enabled when it is
+//! built, such as with `cargo run --features disclose`. Other features give coarser locations,
+//! add context such as the enclosing function, thread, time or span trace, or emit the errors
+//! to `log`, `tracing`, `metrics`, `anyhow`, `eyre`, `miette` or `defmt`. A single call may
+//! override the features with a flag such as `@disclose` or `@quiet`, and may be given metadata
+//! such as a severity or code that is noted on the error. The features, flags and arguments are
+//! described in the README.
+//!
 //! These macros require nuhound v0.2 or later.
 //!
 //! For a fuller explanation of usage please refer to the nuhound crate v0.2 onwards.
 //!
 
+mod config;
+mod data;
+mod flags;
+mod function;
+mod policy;
+mod registry;
+mod rewrite;
 mod scanner;
-use proc_macro::TokenStream;
+use proc_macro::{Delimiter, Group, TokenStream, TokenTree};
 use std::str::Chars;
 use std::collections::HashMap;
+use data::Data;
+use function::Function;
 use scanner::Scanner;
 
 // An array of symmetric character pairs
 const PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
 
+// The placeholder that stands in for the body of a function whilst an attribute macro is expanded
+const BODY: &str = "__nuhound_body";
+
+// The placeholder that stands in for an expression whilst a function body is being rewritten
+const EXPRESSION: &str = "__nuhound_expression";
+
+// The named arguments that are taken by the macros themselves rather than passed on to format!,
+// which are split from the positional arguments of the message by split_named
+const NAMED: [&str; 11] = [
+    "code", "ctx", "severity", "tags", "help", "url", "exit_code", "retriable", "classification", "user", "log",
+];
+
+// The levels of severity, which may be given to a variant of an error or to a single site
+const LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "fatal"];
+// The levels at which a message may be logged by a log argument, or not at all
+const LOG_LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "off"];
+
+// The function like macros that create errors, whose inputs are given flags by the attributes that
+// enclose them, such as `@quiet` by suppress_disclose, and by the macros that enclose them
+const MACROS: [&str; 11] = [
+    "convert", "convert_let", "examine", "custom", "debug_custom", "guard", "dbg_report", "break_custom",
+    "skip_report", "todo_report", "unimplemented_report",
+];
+
+// The attribute macros that take flags, which are given them by the attributes that enclose them in
+// the same way as the function like macros. The attributes that set the disclosure of the macros
+// within their items, such as disclose, are left to take precedence over those enclosing them.
+const ATTRIBUTES: [&str; 17] = [
+    "context", "main", "test", "should_err", "trace_calls", "instrument", "trace_all", "retry", "map_err", "deadline",
+    "catch_panics", "report", "timed", "ensure", "fallback", "track_caller", "async_context",
+];
+
 // Scan through characters enclosed between symmetric character pairs
 fn process_pairs(scanner: &mut Scanner, pairs: &HashMap<char, char>) {
     let exit = pairs[&scanner.get_current().unwrap()];
     loop {
         match scanner.next() {
             // Ignore '<' when in here to allow for less than situations
-            Some(next) if next == '<' => (),
+            Some('<') => (),
             Some(next) if pairs.contains_key(&next) => {
                 process_pairs(scanner, pairs);
             }
@@ -85,12 +135,15 @@ fn process_pairs(scanner: &mut Scanner, pairs: &HashMap<char, char>) {
 }
 
 // Scan through characters placed between double or single quotes remembering
-// to ignore escaped quotes.
+// to ignore escaped characters, including escaped quotes.
 fn process_quotes(scanner: &mut Scanner) {
     let quote = scanner.get_current().unwrap();
     loop {
         match scanner.next() {
-            Some(next) if next == quote && !scanner.is_escaped() => {
+            Some('\\') => {
+                scanner.next();
+            }
+            Some(next) if next == quote => {
                 break;
             }
             Some(_) => (),
@@ -109,20 +162,20 @@ fn analyse(char_string: Chars) -> Vec<String> {
             Some(next) if pairs.contains_key(&next) => {
                 process_pairs(&mut scanner, &pairs);
             }
-            Some(next) if next == '\'' && !scanner.is_escaped() => {
+            Some(next) if next == '\'' && !scanner.is_escaped() && !scanner.is_lifetime() => {
                 process_quotes(&mut scanner);
             }
             Some(next) if next == '"' && !scanner.is_escaped() => {
                 process_quotes(&mut scanner);
             }
-            Some(next) if next == '|' => {
+            Some('|') => {
                 if !scanner.is_pipe_valid() {
                     panic!("The pipe character is misplaced. Perhaps you intended to insert a 'closure' \
                            in which case it must be placed between curly brackets.\n\
                            E.g. {{|n| n + 3}}");
                 }
             }
-            Some(next) if next == ',' => {
+            Some(',') => {
                 scanner.save_attribute(1);
             }
             Some(_) => (),
@@ -133,332 +186,8966 @@ fn analyse(char_string: Chars) -> Vec<String> {
     scanner.get_string_attributes()
 }
 
+// Split an attribute at the first assignment operator that is not enclosed between symmetric
+// character pairs or quotes, returning the trimmed text on either side of it. Comparison operators,
+// match arms and inclusive ranges such as '==', '<=', '=>' and '..=' are not assignments.
+fn split_assignment(attribute: &str) -> Option<(String, String)> {
+    split_top_level(attribute, '=', |scanner| {
+        !matches!(scanner.previous(),
+            Some('=' | '!' | '<' | '>' | '.' | '+' | '-' | '*' | '/' | '%' | '^' | '&' | '|'))
+            && !matches!(scanner.peek(), Some('=' | '>'))
+    })
+}
+
+// Split a function parameter into its pattern and type at the first colon that is not part of a
+// path separator
+fn split_parameter(parameter: &str) -> Option<(String, String)> {
+    split_top_level(parameter, ':', |scanner| {
+        scanner.previous() != Some(':') && scanner.peek() != Some(':')
+    })
+}
+
+// Split an attribute at the first delimiter that is not enclosed between symmetric character pairs
+// or quotes and is accepted by the test, returning the trimmed text on either side of it.
+fn split_top_level(attribute: &str, delimiter: char, accept: impl Fn(&Scanner) -> bool) -> Option<(String, String)> {
+    let pairs = HashMap::from(PAIRS);
+    let chars: Vec<char> = attribute.chars().collect();
+    let mut scanner = Scanner::new(chars.clone());
+    loop {
+        match scanner.next() {
+            Some(next) if next == delimiter && accept(&scanner) => {
+                let position = scanner.position();
+                let left: String = chars[..position - 1].iter().collect();
+                let right: String = chars[position..].iter().collect();
+                return Some((left.trim().to_string(), right.trim().to_string()));
+            }
+            Some(next) if pairs.contains_key(&next) => {
+                process_pairs(&mut scanner, &pairs);
+            }
+            Some('\'') if scanner.is_lifetime() => (),
+            Some(next) if (next == '\'' || next == '"') && !scanner.is_escaped() => {
+                process_quotes(&mut scanner);
+            }
+            Some(_) => (),
+            None => return None,
+        }
+    }
+}
+
+// Separate the options of an attribute macro into their names and values. Options take the form
+// `name = value`, `name(value)` or simply `name`, in which case the value is empty.
+fn analyse_options(attribute: &str) -> Vec<(String, String)> {
+    analyse(attribute.chars())
+        .into_iter()
+        .filter(|option| !option.is_empty())
+        .map(|option| {
+            if let Some((name, value)) = split_assignment(&option) {
+                (name, value)
+            } else if let (Some(open), true) = (option.find('('), option.ends_with(')')) {
+                (option[..open].trim().to_string(), option[open + 1..option.len() - 1].trim().to_string())
+            } else {
+                (option, String::new())
+            }
+        })
+        .collect()
+}
+
+// Separate the named arguments that are taken by the macros themselves, such as `code = "E100"`,
+// from the other arguments, which are passed on to format! The arguments that follow `fields:` are
+// each named "fields" and hold a key and value pair, such as `request_id = id`, as do the arguments
+// given with the sigils of the tracing crate, such as `?key` or `%user.name`, which are named after
+// the variable or field that they format. A named argument whose name is referred to by the format
+// string of the message, such as the `code` of `"bad code {code}", code = c`, is passed on to format!
+// instead.
+fn split_named(attributes: Vec<String>, names: &[&str]) -> (Vec<String>, Vec<(String, String)>) {
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    let mut fields = false;
+    let formatted = formatted_names(&attributes, names);
+    let names: Vec<&str> = names.iter().copied().filter(|name| !formatted.contains(name)).collect();
+    let names = names.as_slice();
+    let (attributes, captures) = split_captures(attributes, names);
+    for attribute in attributes {
+        if let Some(sigil) = attribute.chars().next().filter(|&c| c == '?' || c == '%') {
+            let path = field_path(&attribute[1..]).unwrap_or_else(|| {
+                panic!("The field '{attribute}' must name a variable or one of its fields, such as ?key or %user.name")
+            });
+            let value = if sigil == '?' { format!("?{path}") } else { path.clone() };
+            named.push((String::from("fields"), format!("{path} = {value}")));
+            continue;
+        }
+        let section = attribute.strip_prefix("fields")
+            .and_then(|rest| rest.trim_start().strip_prefix(':'))
+            .filter(|rest| !rest.starts_with(':'));
+        if let Some(rest) = section {
+            fields = true;
+            named.push((String::from("fields"), rest.trim().to_string()));
+            continue;
+        }
+        match split_assignment(&attribute) {
+            Some((name, value)) if names.contains(&name.as_str()) => named.push((name, value)),
+            _ if fields => named.push((String::from("fields"), attribute)),
+            _ => positional.push(attribute),
+        }
+    }
+    for capture in captures {
+        let path = field_path(&capture).unwrap_or_else(|| {
+            panic!("The captured variable '{capture}' must name a variable or one of its fields, such as state or cfg.path")
+        });
+        named.push((String::from("fields"), format!("{path} = ?{path}")));
+    }
+    (positional, named)
+}
+
+// The names taken by the macros that are referred to by a format string among the arguments, such
+// as `{code}` or `{user:>8}`, other than in the value of an argument of the same name
+fn formatted_names<'a>(attributes: &[String], names: &[&'a str]) -> Vec<&'a str> {
+    names.iter().copied().filter(|name| {
+        attributes.iter()
+            .filter(|attribute| attribute.contains('"'))
+            .filter(|attribute| !split_assignment(attribute).is_some_and(|(left, _)| names.contains(&left.as_str())))
+            .any(|attribute| attribute.contains(&format!("{{{name}}}")) || attribute.contains(&format!("{{{name}:")))
+    }).collect()
+}
+
+// Separate the variables captured after a semicolon, such as the `state, attempt` of `"retry
+// failed"; state, attempt`, from the other arguments. Named arguments taken by the macro may still
+// follow the captured variables, while a semicolon that separates the messages given for each
+// profile, such as `debug = "..."; release = "..."`, is left as it is.
+fn split_captures(attributes: Vec<String>, names: &[&str]) -> (Vec<String>, Vec<String>) {
+    let profile = |text: &str| split_assignment(text).is_some_and(|(name, _)| name == "debug" || name == "release");
+    let split = attributes.iter().enumerate().rev().find_map(|(index, attribute)| {
+        let mut segments = vec![attribute.clone()];
+        while let Some((left, right)) = split_top_level(segments.last()?, ';', |_| true) {
+            segments.pop();
+            segments.extend([left, right]);
+        }
+        let rest = segments.pop().filter(|rest| !segments.is_empty() && !profile(rest))?;
+        Some((index, segments.join("; "), rest))
+    });
+    let Some((index, before, rest)) = split else {
+        return (attributes, Vec::new());
+    };
+    let mut remaining = attributes[..index].to_vec();
+    remaining.push(before);
+    let mut captures = vec![rest];
+    for attribute in attributes.into_iter().skip(index + 1) {
+        match split_assignment(&attribute) {
+            Some((name, _)) if names.contains(&name.as_str()) => remaining.push(attribute),
+            _ => captures.push(attribute),
+        }
+    }
+    captures.retain(|capture| !capture.is_empty());
+    (remaining, captures)
+}
+
+// The path of a variable or one of its fields, such as `user.name`, with the spaces between its
+// tokens removed, or None when the text is any other expression
+fn field_path(text: &str) -> Option<String> {
+    let path = text.replace(' ', "");
+    path.split('.').all(|part| {
+        !part.is_empty() && !part.starts_with(|c: char| c.is_ascii_digit())
+            && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+    }).then_some(path)
+}
+
+// Generate the format arguments of a message from the comma separated format arguments given to a
+// macro. The message is followed by the fields of the context and prefixed by the error code when
+// they are given. Sensitive values marked for redaction are rewritten first. The severity, tags,
+// fields, help, url, exit code, classification and user message of the site are not part of the
+// message and are recorded with the flags for the generators to consult. The arguments required by
+// the settings in nuhound.toml must be given and the format string of the message is checked
+// against the message policy of the crate, when it has one.
+fn message_generator(message: &[String], named: &[(String, String)]) -> String {
+    if let Some((_, retriable)) = named.iter().find(|(name, _)| name == "retriable") {
+        let retriable = retriable.parse::<bool>()
+            .unwrap_or_else(|_| panic!("The classification '{retriable}' must be 'true' or 'false', such as 'retriable = true'"));
+        flags::annotate(|flags| flags.retriable = Some(retriable));
+    }
+    if let Some((_, classification)) = named.iter().find(|(name, _)| name == "classification") {
+        if named.iter().any(|(name, _)| name == "retriable") {
+            panic!("The 'retriable' and 'classification' arguments cannot be given together");
+        }
+        let retriable = match classification.to_lowercase().as_str() {
+            "transient" => Some(true),
+            "permanent" => Some(false),
+            "unknown" => None,
+            _ => panic!("Unknown classification '{classification}'. Expected 'Transient', 'Permanent' or 'Unknown'"),
+        };
+        flags::annotate(|flags| flags.retriable = retriable);
+    }
+    if let Some((_, exit)) = named.iter().find(|(name, _)| name == "exit_code") {
+        let exit = exit.replace(' ', "").parse::<i32>()
+            .unwrap_or_else(|_| panic!("The exit code '{exit}' must be an integer literal, such as 'exit_code = 69'"));
+        flags::annotate(|flags| flags.exit = Some(exit));
+    }
+    if let Some((_, help)) = named.iter().find(|(name, _)| name == "help") {
+        flags::annotate(|flags| flags.help = Some(help.clone()));
+    }
+    if let Some((_, url)) = named.iter().find(|(name, _)| name == "url") {
+        flags::annotate(|flags| flags.url = Some(url.clone()));
+    }
+    if let Some((_, user)) = named.iter().find(|(name, _)| name == "user") {
+        flags::annotate(|flags| flags.user = Some(user.clone()));
+    }
+    let fields: Vec<(String, String)> = named.iter()
+        .filter(|(name, _)| name == "fields")
+        .map(|(_, field)| {
+            split_assignment(field)
+                .filter(|(key, _)| key.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_')))
+                .unwrap_or_else(|| panic!("The field '{field}' must take the form 'key = value'"))
+        })
+        .collect();
+    if !fields.is_empty() {
+        flags::annotate(|flags| flags.fields = fields);
+    }
+    for required in config::list("require") {
+        if !NAMED.contains(&required.as_str()) {
+            panic!("Unknown argument '{required}' required by the settings in nuhound.toml");
+        }
+        if named.iter().all(|(name, _)| *name != required) {
+            panic!("The '{required}' argument is required by the settings in nuhound.toml");
+        }
+    }
+    if let Some((_, tags)) = named.iter().find(|(name, _)| name == "tags") {
+        let list = tags.strip_prefix('[').and_then(|tags| tags.strip_suffix(']'))
+            .unwrap_or_else(|| panic!("The tags '{tags}' must be a list of string literals, such as [\"io\", \"startup\"]"));
+        let list: Vec<String> = analyse(list.chars()).into_iter().filter(|tag| !tag.is_empty()).collect();
+        if let Some(tag) = list.iter().find(|tag| !tag.starts_with('"') || !tag.ends_with('"') || tag.len() < 2) {
+            panic!("The tag '{tag}' must be a string literal");
+        }
+        flags::annotate(|flags| flags.tags = list);
+    }
+    if let Some((_, log)) = named.iter().find(|(name, _)| name == "log") {
+        let level = log_level(log).unwrap_or_else(|| {
+            panic!("Unknown log level '{log}'. Expected 'trace', 'debug', 'info', 'warn', 'error' or 'off'")
+        });
+        flags::annotate(|flags| flags.log = Some(level));
+    }
+    if let Some((_, severity)) = named.iter().find(|(name, _)| name == "severity") {
+        let level = severity.trim_matches('"').to_lowercase();
+        let level = LEVELS.iter().find(|&&known| known == level).unwrap_or_else(|| {
+            panic!("Unknown severity '{severity}'. Expected 'trace', 'debug', 'info', 'warn', 'error' or 'fatal'")
+        });
+        flags::annotate(|flags| flags.severity = Some(level));
+    }
+    let template = flags::current().template.or_else(|| message.first().map(|template| {
+        let template = template.strip_prefix('"').and_then(|template| template.strip_suffix('"')).unwrap_or(template);
+        template.replace("\\\"", "\"")
+    }));
+    if let Some(template) = template {
+        if let Some(policy) = policy::load() {
+            // Attribute bodies are not located, so the file of the attribute is used instead
+            let file = flags::location().map(|(file, _, _)| file)
+                .or_else(|| proc_macro::is_available().then(|| proc_macro::Span::call_site().file()))
+                .unwrap_or_default();
+            if let Err(reason) = policy.check(&file, &template, named.iter().any(|(name, _)| name == "code")) {
+                policy::breach(reason, message.first().cloned().unwrap_or_default());
+            }
+        }
+        flags::annotate(|flags| flags.template = Some(template));
+    }
+    flags::annotate(|flags| flags.format = message.to_vec());
+    let mut message = redact_generator(message);
+    if let Some((_, context)) = named.iter().find(|(name, _)| name == "ctx") {
+        message = format!("\"{{}} ({{}})\", format_args!({message}), {context}.__nuhound_context()");
+    }
+    match named.iter().find(|(name, _)| name == "code") {
+        Some((_, code)) if !code.starts_with('"') => panic!("The code '{code}' must be a string literal"),
+        Some((_, code)) => {
+            flags::annotate(|flags| flags.code = Some(code.trim_matches('"').to_string()));
+            format!("\"[{{}}] {{}}\", {code}, format_args!({message})")
+        }
+        None => message,
+    }
+}
+
+// Rewrite the sensitive values of a message, which are marked in the format string as
+// {redact:expression} or {redact:expression:spec}, into named arguments. The value of the
+// expression is only included when the 'disclose-debug' feature is enabled and is otherwise
+// replaced by (redacted) without being evaluated.
+fn redact_generator(message: &[String]) -> String {
+    let Some(format) = message.first() else {
+        return String::new();
+    };
+    let mut rewritten = String::new();
+    let mut redacted: Vec<(String, String)> = Vec::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                rewritten.push_str("{{");
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                match placeholder.strip_prefix("redact:") {
+                    Some(expression) if expression.trim().is_empty() => {
+                        panic!("The redacted value in '{format}' must be given an expression")
+                    }
+                    Some(expression) => {
+                        rewritten.push_str(&format!("{{__nuhound_redact{}}}", redacted.len()));
+                        redacted.push(split_spec(expression));
+                    }
+                    None => rewritten.push_str(&format!("{{{placeholder}}}")),
+                }
+            }
+            c => rewritten.push(c),
+        }
+    }
+    if redacted.is_empty() {
+        return message.join(", ");
+    }
+    let mut arguments = vec![rewritten];
+    arguments.extend_from_slice(&message[1..]);
+    arguments.extend(redacted.iter().enumerate().map(|(index, (expression, spec))| format!("\
+        __nuhound_redact{index} = {{ \
+            #[cfg(feature = \"disclose-debug\")] \
+            let value = format!(\"{{{spec}}}\", {expression}); \
+            #[cfg(not(feature = \"disclose-debug\"))] \
+            let value = {{ let _ = || {{ let _ = &({expression}); }}; \"(redacted)\" }}; \
+            value \
+        }}")));
+    arguments.join(", ")
+}
+
+// Split a redacted placeholder into its expression and any format spec following a single colon,
+// such as the ':?' of 'user.email:?', while leaving paths such as 'auth::token()' intact
+fn split_spec(placeholder: &str) -> (String, String) {
+    let bytes = placeholder.as_bytes();
+    let colon = (0..bytes.len()).rev().find(|&index| {
+        bytes[index] == b':'
+            && bytes.get(index + 1) != Some(&b':')
+            && (index == 0 || bytes[index - 1] != b':')
+    });
+    match colon {
+        Some(index) => (placeholder[..index].trim().to_string(), placeholder[index..].to_string()),
+        None => (placeholder.trim().to_string(), String::new()),
+    }
+}
+
+// Return the format arguments of the message that follow the expression, or a message formed from
+// the text of the expression, such as "text.parse::<u32>() failed", when none are given so that a
+// legacy '?' may be instrumented by wrapping its expression alone
+fn default_message(attributes: &[String]) -> Vec<String> {
+    if let Some(message) = localized_message(&attributes[1..]).or_else(|| profiled_message(&attributes[1..])) {
+        return message;
+    }
+    if attributes.len() > 1 {
+        return attributes[1..].to_vec();
+    }
+    vec![String::from("\"{} failed\""), format!("stringify!({})", attributes[0])]
+}
+
+// Return the format arguments of a localized message when the message is given as a key, such as
+// `key = "errors.config.missing", args = [path]`, or None when it is given as a format string. The
+// template of the key is looked up at error time in the catalog that the crate registers with the
+// catalog attribute, falling back to the key itself when the catalog doesn't know it.
+fn localized_message(attributes: &[String]) -> Option<Vec<String>> {
+    let key = match split_assignment(attributes.first()?) {
+        Some((name, key)) if name == "key" => key,
+        _ => return None,
+    };
+    let args = match attributes.get(1).and_then(|attribute| split_assignment(attribute)) {
+        Some((name, args)) if name == "args" => {
+            let list = args.strip_prefix('[').and_then(|args| args.strip_suffix(']'))
+                .unwrap_or_else(|| panic!("The args '{args}' must be a list of expressions, such as [path, line]"));
+            analyse(list.chars()).into_iter().filter(|arg| !arg.is_empty()).collect()
+        }
+        Some((name, _)) => panic!("Unexpected argument '{name}' following the key. Expected 'args = [...]'"),
+        None if attributes.len() > 1 => panic!("A message given as a key cannot also take format arguments"),
+        None => Vec::new(),
+    };
+    if attributes.len() > 2 {
+        panic!("A message given as a key cannot also take format arguments");
+    }
+    let literal = key.strip_prefix('"').and_then(|key| key.strip_suffix('"')).unwrap_or(&key).to_string();
+    flags::annotate(|flags| flags.key = Some(literal));
+    let args: Vec<String> = args.iter().map(|arg| format!("::std::format!(\"{{}}\", {arg})")).collect();
+    Some(vec![String::from("\"{}\""), format!("{{
+            let key: &str = {key};
+            let template = crate::__nuhound_catalog(key).unwrap_or_else(|| ::std::string::String::from(key));
+            let args: [::std::string::String; {0}] = [{1}];
+            {LOCALIZE}
+        }}", args.len(), args.join(", "))])
+}
+
+// Return the format arguments of a message given for each profile when it is given as a pair, such
+// as `debug = "raw packet was {:?}", pkt; release = "malformed packet received"`, or None otherwise.
+// Only the message of the profile being compiled exists in the binary, while the arguments of the
+// other are borrowed within a closure that is never called so that they aren't reported as unused.
+fn profiled_message(attributes: &[String]) -> Option<Vec<String>> {
+    let (name, _) = split_assignment(attributes.first()?)?;
+    if name != "debug" && name != "release" {
+        return None;
+    }
+    let text = attributes.join(", ");
+    let (first, second) = split_top_level(&text, ';', |_| true).unwrap_or_else(|| {
+        panic!("The {name} message must be followed by a message for the other profile, such as \
+            `debug = \"raw packet was {{:?}}\", pkt; release = \"malformed packet received\"`")
+    });
+    let variant = |text: &str| {
+        let (name, message) = split_assignment(text)
+            .filter(|(name, _)| name == "debug" || name == "release")
+            .unwrap_or_else(|| panic!("Expected a message for the 'debug' or 'release' profile but found '{text}'"));
+        (name, analyse(message.chars()))
+    };
+    let (first, second) = (variant(&first), variant(&second));
+    if first.0 == second.0 {
+        panic!("The {} message must not be given twice", first.0);
+    }
+    let (debug, release) = if first.0 == "debug" { (first.1, second.1) } else { (second.1, first.1) };
+    let template = release[0].strip_prefix('"').and_then(|template| template.strip_suffix('"')).unwrap_or(&release[0]);
+    let template = template.replace("\\\"", "\"");
+    flags::annotate(|flags| flags.template = Some(template));
+    let unused = |arguments: &[String]| arguments[1..].iter()
+        .map(|argument| format!("let _ = &({});", split_assignment(argument).map(|(_, value)| value).unwrap_or(argument.clone())))
+        .collect::<String>();
+    Some(vec![String::from("\"{}\""), format!("{{ \
+            #[cfg(debug_assertions)] \
+            let message = {{ let _ = || {{ {0} }}; ::std::format!({1}) }}; \
+            #[cfg(not(debug_assertions))] \
+            let message = {{ let _ = || {{ {2} }}; ::std::format!({3}) }}; \
+            message \
+        }}", unused(&release), redact_generator(&debug), unused(&debug), redact_generator(&release))])
+}
+
+// The statements that fill the placeholders of the template held in 'template' from the arguments
+// held in 'args'. A placeholder is either {} for the next argument or {N} for the Nth, while {{ and
+// }} stand for braces. A placeholder without a matching argument is left as it is written.
+const LOCALIZE: &str = "\
+    let mut message = ::std::string::String::new(); \
+    let mut chars = template.chars().peekable(); \
+    let mut next = 0usize; \
+    while let ::std::option::Option::Some(c) = chars.next() { \
+        match c { \
+            '{' | '}' if chars.peek() == ::std::option::Option::Some(&c) => { \
+                chars.next(); \
+                message.push(c); \
+            } \
+            '{' => { \
+                let placeholder: ::std::string::String = chars.by_ref().take_while(|&c| c != '}').collect(); \
+                let index = if placeholder.is_empty() { next += 1; next - 1 } else { placeholder.trim().parse().unwrap_or(usize::MAX) }; \
+                match args.get(index) { \
+                    ::std::option::Option::Some(arg) => message.push_str(arg), \
+                    ::std::option::Option::None => message.push_str(&::std::format!(\"{{{placeholder}}}\")), \
+                } \
+            } \
+            c => message.push(c), \
+        } \
+    } \
+    message";
+
 // The convert builder is used to create a macro that generates Nuhound type errors from any other
 // error cause provided that they employ the Error trait. This includes Nuhound errors too.
 fn convert_builder(item: String) -> String {
+    let (attributes, named) = split_named(analyse(item.chars()), &NAMED);
+    if attributes.is_empty() {
+        panic!("Contains insufficient parameters");
+    }
+    let message = message_generator(&default_message(&attributes), &named);
+    convert_generator(&attributes[0], &message, Some(&attributes[0]))
+}
+
+// Generate the code that converts the error in the expression into a Nuhound error with the message
+// formed from the comma separated format arguments. This is shared by the builders that need to
+// produce a convert style error.
+fn convert_generator(expression: &str, message: &str, snippet: Option<&str>) -> String {
+    let derived = [
+        if flags::current().severity.is_some() { "" } else { DERIVED_SEVERITY },
+        if flags::current().exit.is_some() { "" } else { DERIVED_EXIT },
+        &derived_advice_generator(),
+    ].join("\n");
+    let composed = compose_generator(message, snippet, &derived, Some("reason"));
+    shim_generator(format!("
+    {0}.report(|reason| {{
+        {1}
+        {2}
+        let code = reason.__nuhound_code();
+        {3}
+        {4}
+    }})
+    ", expression, link_generator().trim(), composed.trim(), detail_generator(),
+        noted_generator(&composed, &opaque_generator("reason", true, &display_generator()))))
+}
+
+// The statement that notes the exit code of an error that derives ExitCode, which is used by the main
+// attribute. The exit code given to the site takes precedence.
+const DERIVED_EXIT: &str = "\
+    if let ::std::option::Option::Some(exit) = reason.__nuhound_exit() { \
+        notes.push((\"exit\", exit.to_string())); \
+    }";
+
+// The statement that notes the severity of an error that derives Severity, which is logged at that
+// level by its inherent severity method. The severity given to the site takes precedence.
+const DERIVED_SEVERITY: &str = "\
+    if let ::std::option::Option::Some(severity) = reason.__nuhound_severity(&inform) { \
+        notes.push((\"severity\", severity.to_string())); \
+    }";
+
+// Generate the expression that creates the Nuhound error from the message in 'inform' and the named
+// cause. When the @opaque flag is given the cause is not linked into the chain and only its type is
+// recorded, such as "cause withheld: core::num::error::ParseIntError", preceded by the error code
+// held in 'code' when there is one, so that data held by the cause is not propagated. Otherwise the
+// given expression links the cause.
+fn opaque_generator(cause: &str, code: bool, link: &str) -> String {
+    if !flags::current().opaque {
+        return link.to_string();
+    }
+    let code = if code { "code" } else { "::std::option::Option::<&str>::None" };
+    format!("::nuhound::Nuhound::new(inform).caused_by(::nuhound::Nuhound::new(match {code} {{ \
+        ::std::option::Option::Some(code) => format!(\"[{{}}] cause withheld: {{}}\", code, ::std::any::type_name_of_val(&{cause})), \
+        ::std::option::Option::None => format!(\"cause withheld: {{}}\", ::std::any::type_name_of_val(&{cause})), \
+    }}))")
+}
+
+// Generate the expression that links the cause held in 'reason' to a new Nuhound error with the
+// message in 'inform'. When the @display flag is given the cause is linked by its Display output,
+// preceded by the error code held in 'code' when there is one, so that a cause that only implements
+// Display, such as a boxed trait object without Error, still appears in the chain. Its own causes
+// are lost as it has no source.
+fn display_generator() -> String {
+    if !flags::current().display {
+        return String::from("reason.__nuhound_link(inform, code)");
+    }
+    String::from("::nuhound::Nuhound::new(inform).caused_by(::nuhound::Nuhound::new(match code { \
+        ::std::option::Option::Some(code) => format!(\"[{}] {}\", code, reason), \
+        ::std::option::Option::None => ::std::string::ToString::to_string(&reason), \
+    }))")
+}
+
+// Generate the traits that link an error to a new Nuhound error with the message in 'inform'. Errors
+// that derive IntoNuhound have an inherent link method, which takes precedence, so that they are
+// converted using their From implementation. Likewise errors that derive ErrorCode have an inherent
+// code method so that their code prefixes the message of the linked error, errors that derive
+// Diagnostic have inherent help and url methods so that their advice follows the message, errors
+// that derive Severity have an inherent severity method that logs the message at their level and
+// errors that derive ExitCode have an inherent exit method so that their exit code is noted. A
+// Nuhound error is linked as it is, which keeps the notes of its layers, while any other error is
+// linked by reference. When the 'anyhow' or 'eyre' feature is enabled an anyhow::Error or
+// eyre::Report, which doesn't implement the Error trait itself, is linked through the error that it
+// holds so that its chain is preserved.
+fn link_generator() -> String {
+    String::from("
+        trait Metadata {
+            fn __nuhound_code(&self) -> ::std::option::Option<&'static str> {
+                ::std::option::Option::None
+            }
+            fn __nuhound_help(&self) -> ::std::option::Option<&'static str> {
+                ::std::option::Option::None
+            }
+            fn __nuhound_url(&self) -> ::std::option::Option<&'static str> {
+                ::std::option::Option::None
+            }
+            fn __nuhound_severity(&self, _inform: &str) -> ::std::option::Option<&'static str> {
+                ::std::option::Option::None
+            }
+            fn __nuhound_exit(&self) -> ::std::option::Option<i32> {
+                ::std::option::Option::None
+            }
+        }
+        impl<T: ?Sized> Metadata for T {}
+        trait NuhoundLink {
+            fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;
+        }
+        impl NuhoundLink for ::nuhound::Nuhound {
+            fn __nuhound_link(self, inform: ::std::string::String, _code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {
+                ::nuhound::Nuhound::new(inform).caused_by(self)
+            }
+        }
+        trait Link {
+            fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;
+        }
+        impl<E: ::std::error::Error> Link for E {
+            fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {
+                match code {
+                    ::std::option::Option::Some(code) => {
+                        let message = format!(\"[{}] {}\", code, self);
+                        ::nuhound::Nuhound::new(inform).caused_by(match self.source() {
+                            ::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),
+                            ::std::option::Option::None => ::nuhound::Nuhound::new(message),
+                        }.note(\"code\", code))
+                    }
+                    ::std::option::Option::None => ::nuhound::Nuhound::link(inform, self),
+                }
+            }
+        }
+        #[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]
+        trait ReportLink {
+            fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;
+        }
+        #[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]
+        fn __nuhound_report_link(error: &(dyn ::std::error::Error + 'static), inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {
+            match code {
+                ::std::option::Option::Some(code) => {
+                    let message = format!(\"[{}] {}\", code, error);
+                    ::nuhound::Nuhound::new(inform).caused_by(match error.source() {
+                        ::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),
+                        ::std::option::Option::None => ::nuhound::Nuhound::new(message),
+                    }.note(\"code\", code))
+                }
+                ::std::option::Option::None => ::nuhound::Nuhound::link(inform, error),
+            }
+        }
+        #[cfg(feature = \"anyhow\")]
+        impl ReportLink for ::anyhow::Error {
+            fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {
+                __nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)
+            }
+        }
+        #[cfg(feature = \"eyre\")]
+        impl ReportLink for ::eyre::Report {
+            fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {
+                __nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)
+            }
+        }
+    ")
+}
+
+// The convert let builder is used to create a macro that binds the value extracted from a Result or
+// Option or returns a convert style Nuhound error to the calling context. The first attribute takes
+// the form `pattern = expression`.
+fn convert_let_builder(item: String) -> String {
     let attributes = analyse(item.chars());
     if attributes.len() < 2 {
         panic!("Contains insufficient parameters");
     }
+    let (pattern, expression) = split_assignment(&attributes[0])
+        .unwrap_or_else(|| panic!("The first parameter must take the form 'pattern = expression'"));
     let message = attributes[1..].join(", ");
 
     format!("
-    {0}.report(|reason| {{
-        let cause: &dyn ::std::error::Error = &reason;
-        #[cfg(not(feature = \"disclose\"))]
-        let inform = format!({1});
-        #[cfg(feature = \"disclose\")]
-        let inform = format!(\"{{0}}:{{1}}:{{2}}: {{3}}\", file!(), line!(), column!(), format!({1}));
-        ::nuhound::Nuhound::link(inform, cause)
-    }})
-    ", attributes[0], message)
+    let {0} = match {1} {{
+        ::std::result::Result::Ok(value) => value,
+        ::std::result::Result::Err(error) => return ::std::result::Result::Err(::std::convert::From::from(error)),
+    }};
+    ", pattern, convert_generator(&expression, &message, Some(&expression)).trim())
 }
 
 // The examine builder is used to create a macro that generates Nuhound type errors from other
 // Nuhound errors. Unlike the convert builder, the causal error must be a Nuhound type which
 // simplifies the generated code after compilation.
 fn examine_builder(item: String) -> String {
-    let attributes = analyse(item.chars());
-    if attributes.len() < 2 {
+    let (attributes, named) = split_named(analyse(item.chars()), &NAMED);
+    if attributes.is_empty() {
         panic!("Contains insufficient parameters");
     }
-    let message = attributes[1..].join(", ");
+    let message = message_generator(&default_message(&attributes), &named);
+    examine_generator(&attributes[0], &message, Some(&attributes[0]))
+}
 
-    format!("
+// Generate the code that links the Nuhound error in the expression to a new Nuhound error with the
+// message formed from the comma separated format arguments.
+fn examine_generator(expression: &str, message: &str, snippet: Option<&str>) -> String {
+    let composed = compose_generator(message, snippet, "", Some("cause"));
+    shim_generator(format!("
     {0}.report(|cause| {{
-        #[cfg(not(feature = \"disclose\"))]
-        let inform = format!({1});
-        #[cfg(feature = \"disclose\")]
-        let inform = format!(\"{{0}}:{{1}}:{{2}}: {{3}}\", file!(), line!(), column!(), format!({1}));
-        ::nuhound::Nuhound::new(inform).caused_by(cause)
+        {1}
+        {2}
+        {3}
     }})
-    ", attributes[0], message)
+    ", expression, composed.trim(), detail_generator(),
+        noted_generator(&composed, &opaque_generator("cause", false, "::nuhound::Nuhound::new(inform).caused_by(cause)"))))
 }
 
 // The custom builder is used to create a macro that generates a Nuhound error.
 fn custom_builder(item: String) -> String {
-    let attributes = analyse(item.chars());
+    let (attributes, named) = split_named(analyse(item.chars()), &NAMED);
     if attributes.is_empty() {
         panic!("Contains insufficient parameters");
     }
-    let message = attributes.join(", ");
+    let message = localized_message(&attributes).or_else(|| profiled_message(&attributes)).unwrap_or(attributes);
+    let message = message_generator(&message, &named);
+    custom_generator(&message)
+}
+
+// Generate the code for a Nuhound error from the comma separated format arguments of the message.
+// This is shared by the builders that need to produce a custom style error.
+fn custom_generator(message: &str) -> String {
+    let composed = compose_generator(message, None, "", None);
+    shim_generator(format!("
+    {{
+        {0}
+        {1}
+        ::std::result::Result::Err({2})
+    }}
+    ", composed.trim(), detail_generator(), noted_generator(&composed, "::nuhound::Nuhound::new(inform)")))
+}
+
+// Generate the statements that compose the message in 'inform' from the comma separated format
+// arguments of the message together with the notes given by the flags and arguments of the site,
+// followed by any statements derived from the cause, which is named when there is one. Notes that
+// are kept apart from the message are collected in 'notes', which is only declared when the site has
+// any, until the error is created. This is shared by the builders that produce a convert, examine or
+// custom style error.
+fn compose_generator(message: &str, snippet: Option<&str>, derived: &str, cause: Option<&str>) -> String {
+    let statements = sample_generator(message, inform_generator(message, snippet, cause).trim()) + &code_generator()
+        + &location_note_generator(message) + &time_note_generator() + &snippet_note_generator() + &depth_generator()
+        + &function_hash_note_generator() + &severity_generator() + &exit_generator() + &retriable_generator() + &tags_generator() + &fields_generator() + &advice_generator()
+        + &user_generator() + derived;
+    if !statements.contains("notes.push(") {
+        return statements;
+    }
+    format!("
+        #[allow(unused_mut)]
+        let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();
+        {}
+    ", statements.trim())
+}
+
+// Generate the expression that adds the notes collected in 'notes' to the error created by the given
+// expression, which is left as it is when the composed statements hold no notes
+fn noted_generator(composed: &str, error: &str) -> String {
+    if !composed.contains("notes.push(") {
+        return error.to_string();
+    }
+    format!("notes.into_iter().fold({error}, |error, (key, value)| error.note(key, value))")
+}
+
+// Generate the statement that notes the error code given by a code argument, such as `code =
+// "E42"`, as the "code" metadata of the error, so that it may be serialized without parsing the
+// message
+fn code_generator() -> String {
+    let Some(code) = flags::current().code else {
+        return String::new();
+    };
+    format!("
+        notes.push((\"code\", {code:?}.to_string()));
+    ")
+}
+
+// Generate the statement that notes the disclosed location of the error as its "file", "line" and
+// "col" metadata, or the number of its site when the 'obfuscate' feature is enabled, so that it may be
+// serialized without parsing the message
+fn location_note_generator(message: &str) -> String {
+    let location = location_generator();
+    let parts = analyse(location.chars());
+    let (file, line, column) = (&parts[0], &parts[1], &parts[2]);
+    let template = analyse(message.chars()).into_iter().next().unwrap_or_default();
+    let template = template.strip_prefix('"').and_then(|template| template.strip_suffix('"')).unwrap_or(&template);
+    let site = registry::register_site(&template.replace("\\\"", "\""));
+    let some = |part: &str| format!("::std::option::Option::Some({part}.to_string())");
+    let none = "::std::option::Option::None";
+    let location = precision_generator(
+        &format!("({file}.to_string(), {}, {})", some(line), some(column)),
+        &format!("({file}.to_string(), {}, {none})", some(line)),
+        &format!("({file}.to_string(), {none}, {none})"),
+    );
+    let notes = format!("{{ \
+        #[cfg(not(feature = \"obfuscate\"))] \
+        {{ \
+            let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, \
+                ::std::option::Option<::std::string::String>) = {location}; \
+            notes.push((\"file\", file)); \
+            notes.extend(line.map(|line| (\"line\", line))); \
+            notes.extend(column.map(|column| (\"col\", column))); \
+        }} \
+        #[cfg(feature = \"obfuscate\")] \
+        notes.push((\"site\", \"{site}\".to_string())); \
+    }}");
+    disclose_generator("", &notes)
+}
+
+// Generate the statement that notes the line of source that holds the macro as the "snippet" metadata
+// of the error, and the byte range of the input within that line, such as "8..27", as its "label"
+// metadata, when the 'miette' feature is enabled and the location is disclosed. Both are taken from
+// the span of the input and the source file while compiling, so that the error is rendered by miette
+// with its source labelled without reading the file at run time.
+fn snippet_note_generator() -> String {
+    if flags::current().caller {
+        return String::new();
+    }
+    let Some((file, line, column)) = flags::location() else {
+        return String::new();
+    };
+    let Some(text) = std::fs::read_to_string(&file).ok()
+        .and_then(|contents| contents.lines().nth(line.saturating_sub(1)).map(str::to_string)) else {
+        return String::new();
+    };
+    let start = text.char_indices().nth(column.saturating_sub(1)).map_or(text.len(), |(offset, _)| offset);
+    let label = format!("{start}..{}", text.trim_end().len().max(start));
+    disclose_generator("", &format!("{{
+        #[cfg(all(feature = \"miette\", not(feature = \"obfuscate\")))]
+        notes.push((\"snippet\", {text:?}.to_string()));
+        #[cfg(all(feature = \"miette\", not(feature = \"obfuscate\")))]
+        notes.push((\"label\", {label:?}.to_string()));
+    }}"))
+}
 
+// Wrap the code generated for an error in a block that captures the location of the caller of the
+// enclosing function when the @caller(1) flag is given. The location is captured before any closure
+// in the code is entered, as a closure can't be marked with #[track_caller], and the enclosing
+// function must be marked with #[track_caller] for it to be the location of the caller.
+fn shim_generator(code: String) -> String {
+    if !flags::current().shim {
+        return code;
+    }
     format!("
     {{
-        #[cfg(not(feature = \"disclose\"))]
-        let inform = format!({0});
-        #[cfg(feature = \"disclose\")]
-        let inform = format!(\"{{0}}:{{1}}:{{2}}: {{3}}\", file!(), line!(), column!(), format!({0}));
-        ::std::result::Result::Err(::nuhound::Nuhound::new(inform))
+        let __nuhound_caller = ::std::panic::Location::caller();
+        {}
     }}
-    ", message)
+    ", code.trim())
 }
 
-//  convert macro
-/// A macro to prepare a `Nuhound` type error from any error type that implements the Error trait. This
-/// also includes Nuhound errors. Resultant errors may be handled using the `?` operator or by simply
-/// returning it to the calling context as a `Result::Err` directly.
-///
-/// The macro creates an error message that may optionally contain the name of the source file and
-/// location of the error. This behaviour is enabled by compiling the code with the 'disclose'
-/// feature.
-///
-/// This macro requires either `nuhound::ResultExtension` or `nuhound::OptionExtension` depending on
-/// whether the code being checked returns a `Result` or an `Option`.
-///
-/// # Examples
-/// The following example shows how the `convert` macro is used to report an error but still retain
-/// the underlying error or errors that can be displayed using the `trace` method.
-///
-/// ```ignore
-/// use nuhound::{Report, ResultExtension, convert};
-///
-/// fn my_result() -> Report<u32> {
-///     let text = "NaN";
-///     let value = convert!(text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text)?;
-///     Ok(value)
-/// }
-///
-/// fn layer2() -> Report<u32> {
-///     let result = convert!(my_result(), "Next level failure")?;
-///     Ok(result)
-/// }
-///
-/// fn layer1() -> Report<u32> {
-///     let result = convert!(layer2(), "Highest level failure")?;
-///     Ok(result)
-/// }
-///
-/// match layer1() {
-///     Ok(value) => println!("Value = {value}"),
-///     Err(e) => {
-///         #[cfg(feature = "disclose")]
-///         eprintln!("{}", e.trace());
-///         #[cfg(not(feature = "disclose"))]
-///         eprintln!("{}", e);
-///     },
-/// }
-///
-/// // using 'cargo run --features disclose' will emit the following message:
-/// //
-/// // 0: examples/nan.rs:16:22: Highest level failure
-/// // 1: examples/nan.rs:11:22: Next level failure
-/// // 2: examples/nan.rs:6:21: Oh dear - 'NaN' could not be converted to an integer
-/// // 3: invalid digit found in string
-/// //
-/// // using 'cargo run' without the disclose feature will emit the following message:
-/// //
-/// // Highest level failure
-/// //
-/// // Notice that the error detail is no longer visible
-///```
-#[proc_macro]
-pub fn convert(item: TokenStream) -> TokenStream {
-    convert_builder(item.to_string()).parse().unwrap()
+// Generate the statements that compose the message in 'inform' for only every Nth error at the site
+// when the @sample(N) flag is given, so that expected failures on hot paths stay cheap. The other
+// errors are given the message formatted from its arguments, without its location or any detail,
+// followed by the number of the occurrence, such as "cache miss for user:42 (occurrence 57)".
+// Whether the message was composed in full is held in a variable named '__nuhound_sampled'.
+fn sample_generator(message: &str, inform: &str) -> String {
+    let Some(count) = flags::current().sample else {
+        return inform.to_string();
+    };
+    let template = analyse(message.chars()).into_iter().next().unwrap_or_default();
+    let truncated = truncate_generator(message);
+    // The site has already been recorded when composing the message and so the same number is
+    // returned, which lets every occurrence be counted rather than only those that are composed
+    let counter = count_generator(registry::register_site(&template));
+    format!("
+        {counter}
+        let (inform, __nuhound_sampled) = {{
+            static OCCURRENCES: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0);
+            let occurrence = OCCURRENCES.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+            if occurrence % {count} == 0 {{
+                {inform}
+                (inform, true)
+            }} else {{
+                (format!(\"{{}} (occurrence {{}})\", {truncated}, occurrence + 1), false)
+            }}
+        }};
+    ")
 }
 
-//  examine macro
-/// A macro to prepare a `Nuhound` type error from previously handled `Nuhound` error(s). Whilst the
-/// `convert` macro is completely error type agnostic provided the error handler implements the
-/// `Error` trait, the `examine` macro requires much less binary code to implement and hence is
-/// more efficient. Resultant errors are handled using the `?` operator by simply returning it to the
-/// calling context as a `Result::Err` directly.
-///
-/// The macro creates an error message that may optionally contain the name of the source file and
-/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
-/// feature.
-///
-/// # Examples
-/// The following example shows how the `examine` macro is used to report an error but still retain
-/// the underlying error or errors that can be displayed using the `trace` method.
-///
-/// ```ignore
-/// use nuhound::{Report, ResultExtension, convert, examine};
-///
-/// fn my_result() -> Report<u32> {
-///     let text = "NaN";
-///     // We need to use the convert macro here, because the underlying error is of type
-///     // ParseIntError
-///     let value = convert!(text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text)?;
-///     Ok(value)
-/// }
-///
-/// fn layer2() -> Report<u32> {
-///     // The examine macro can be used here because the called context has already converted the
-///     // ParseIntError to Nuhound 
-///     let result = examine!(my_result(), "Next level failure")?;
-///     Ok(result)
-/// }
-///
-/// fn layer1() -> Report<u32> {
-///     // The examine macro can be used here because the called context is already Nuhound ready.
-///     let result = examine!(layer2(), "Highest level failure")?;
-///     Ok(result)
-/// }
-///
-/// match layer1() {
-///     Ok(value) => println!("Value = {value}"),
-///     Err(e) => {
-///         #[cfg(feature = "disclose")]
-///         eprintln!("{}", e.trace());
-///         #[cfg(not(feature = "disclose"))]
-///         eprintln!("{}", e);
-///     },
-/// }
-///
-/// // using `cargo run --features disclose` will emit the following message:
-/// //
-/// // 0: examples/nan.rs:16:22: Highest level failure
-/// // 1: examples/nan.rs:11:22: Next level failure
-/// // 2: examples/nan.rs:6:21: Oh dear - 'NaN' could not be converted to an integer
-/// // 3: invalid digit found in string
-/// //
-/// // using `cargo run` without the disclose feature will emit the following message:
-/// //
-/// // Highest level failure
-/// //
-/// // Notice that the error detail is no longer visible
-///```
-#[proc_macro]
-pub fn examine(item: TokenStream) -> TokenStream {
-    examine_builder(item.to_string()).parse().unwrap()
+// Generate the statement that notes the number of layers beneath the error that should be displayed
+// as its "depth" metadata when the @depth(N) flag is given. The main attribute stops printing the
+// trace at that depth while the full chain remains available to the program.
+fn depth_generator() -> String {
+    let Some(depth) = flags::current().depth else {
+        return String::new();
+    };
+    format!("
+        notes.push((\"depth\", \"{depth}\".to_string()));
+    ")
 }
 
-//  custom macro
-/// A macro to prepare a `Nuhound` type error. Whilst the `convert` and `examine` macros are
-/// designed to respond to previously handled errors, the `custom` macro will always generate a
-/// `Nuhound` error without any pre-conditions. Resultant errors may be handled using the `?`
-/// operator or by simply returning it to the calling context as a `Result::Err` directly. Normal
-/// usage of this macro would suggest using it inside a condition block and returning the error to
-/// the calling context with `return custom!("My error message");`.
-///
-/// This macro creates an error message that may optionally contain the name of the source file and
-/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
-/// feature.
-///
-/// # Examples
-/// The following example shows how the `custom` macro is used in conjunction with the `examine`
-/// macro to report an error but still retain the originating error that can be displayed using the
-/// `trace` method.
-///
-/// ```ignore
-/// use nuhound::{Report, ResultExtension, examine, custom};
-///
-/// fn my_result() -> Report<u32> {
-///     let value = 99;
-///     if value == 99 {
-///         return custom!("Oh dear - '{value}' was not expected");
-///     }
-///     Ok(value)
-/// }
-///
-/// fn layer2() -> Report<u32> {
-///     let result = examine!(my_result(), "Next level failure")?;
-///     Ok(result)
-/// }
-///
-/// fn layer1() -> Report<u32> {
-///     let result = examine!(layer2(), "Highest level failure")?;
-///     Ok(result)
-/// }
-///
-/// match layer1() {
-///     Ok(value) => println!("Value = {value}"),
-///     Err(e) => {
-///         #[cfg(feature = "disclose")]
-///         eprintln!("{}", e.trace());
-///         #[cfg(not(feature = "disclose"))]
-///         eprintln!("{}", e);
-///     },
-/// }
-///
-/// // using `cargo run --features disclose` will emit the following message:
-/// //
-/// // 0: examples/custom.rs:18:22: Highest level failure
-/// // 1: examples/custom.rs:13:22: Next level failure
-/// // 2: examples/custom.rs:7:20: Oh dear - '99' was not expected
-/// //
-/// // using `cargo run` without the disclose feature will emit the following message:
-/// //
-/// // Highest level failure
-/// //
-/// // Notice that the error detail is no longer visible
-///```
-#[proc_macro]
-pub fn custom(item: TokenStream) -> TokenStream {
-    custom_builder(item.to_string()).parse().unwrap()
+// Generate the statements that log the message in 'inform' at the level given by a severity argument,
+// such as `severity = warn`, and note the level as the "severity" metadata of the error, so that sinks
+// and handlers may route errors by their severity. The message isn't logged when the severity is
+// below the log_threshold setting. A log argument, such as `log = warn` or `log = off`, takes
+// precedence over the severity and otherwise the message of an error without a severity is logged at
+// the level given by the log setting, if any. An error that isn't logged emits no tracing event.
+fn severity_generator() -> String {
+    let flags = flags::current();
+    let level = match (flags.log, flags.severity) {
+        (Some("off"), _) => None,
+        (Some(level), _) => Some(level),
+        (None, Some(_)) if below_threshold("log_threshold") => None,
+        (None, Some(severity)) => Some(if severity == "fatal" { "error" } else { severity }),
+        (None, None) => match config::setting("log") {
+            Some(level) => log_level(&level).unwrap_or_else(|| {
+                panic!("Unknown log level '{level}' given to 'log' in nuhound.toml. Expected 'trace', 'debug', \
+                    'info', 'warn', 'error' or 'off'")
+            }).into(),
+            None => return sampled_generator(format!("{}\n    ", defmt_generator("error"))),
+        },
+    };
+    let log = match level {
+        Some("off") | None => String::new(),
+        Some(level) => sampled_generator(event_generator(level)),
+    };
+    let Some(severity) = flags.severity else {
+        return log;
+    };
+    format!("
+        {}
+        notes.push((\"severity\", \"{severity}\".to_string()));
+    ", log.trim())
+}
+
+// Wrap the statements of a side effect of the error, such as logging the message, so that they only
+// run for the errors whose message is composed in full when the @sample flag is given. The errors
+// that are left out by sampling are still created and counted but aren't logged.
+fn sampled_generator(statements: String) -> String {
+    if flags::current().sample.is_none() || statements.trim().is_empty() {
+        return statements;
+    }
+    format!("
+        if __nuhound_sampled {{
+            {}
+        }}
+    ", statements.trim())
+}
+
+// Generate the statements that emit the message in 'inform' as an event at the given level when the
+// 'tracing-events' feature is enabled, carrying the location, error code and severity of the site as
+// structured fields, so that errors appear natively in the pipeline of a tracing subscriber. The
+// location is left out when the 'obfuscate' feature is enabled. The message is logged with the 'log'
+// feature instead when the 'tracing-events' feature isn't enabled.
+fn event_generator(level: &str) -> String {
+    let location = location_generator();
+    let parts = analyse(location.chars());
+    let flags = flags::current();
+    let mut fields = Vec::new();
+    if let Some(code) = &flags.code {
+        fields.push(format!("code = {code:?}, "));
+    }
+    if let Some(severity) = flags.severity {
+        fields.push(format!("severity = {severity:?}, "));
+    }
+    let fields = fields.concat();
+    format!("
+        #[cfg(all(feature = \"tracing-events\", not(feature = \"obfuscate\")))]
+        ::tracing::event!(::tracing::Level::{0}, file = %{1}, line = {2}, column = {3}, {fields}\"{{}}\", inform);
+        #[cfg(all(feature = \"tracing-events\", feature = \"obfuscate\"))]
+        ::tracing::event!(::tracing::Level::{0}, {fields}\"{{}}\", inform);
+        #[cfg(all(feature = \"log\", not(feature = \"tracing-events\")))]
+        ::log::{level}!(\"{{}}\", inform);{4}
+    ", level.to_uppercase(), parts[0], parts[1], parts[2], defmt_generator(level))
+}
+
+// Generate the statements that log the message at the given level with defmt when the 'defmt'
+// feature is enabled, for embedded targets where messages are not formatted on the device. The
+// format string is interned by defmt together with the location of the site, or the number of the
+// site when the 'obfuscate' feature is enabled, and the code of the error, while the arguments of
+// the message are encoded by defmt. Nothing is generated for a message whose format string isn't
+// given as a string literal, such as a message with profiles.
+fn defmt_generator(level: &str) -> String {
+    let flags = flags::current();
+    let Some(written) = flags.format.first() else {
+        return String::new();
+    };
+    let literal = written.strip_prefix('"').and_then(|written| written.strip_suffix('"'));
+    if literal.map(|literal| literal.replace("\\\"", "\"")) != flags.template {
+        return String::new();
+    }
+    let (format, arguments) = defmt_format(&flags.format);
+    let code = flags.code.as_ref().map(|code| format!("[{code}] ")).unwrap_or_default();
+    let count = arguments.len();
+    let arguments: String = arguments.iter().map(|argument| format!(", {argument}")).collect();
+    let (location, caller) = if flags.caller {
+        (format!("{{{count}=str}}:{{{}=u32}}:{{{}=u32}}", count + 1, count + 2),
+            ", __nuhound_caller.file(), __nuhound_caller.line(), __nuhound_caller.column()")
+    } else {
+        let (file, line, column) = registry::location();
+        let file = defmt_file(&file, config::setting("path_prefix").as_deref());
+        (format!("{}:{line}:{column}", file.escape_default()), "")
+    };
+    let site = registry::register_site(flags.template.as_deref().unwrap_or_default());
+    format!("
+        #[cfg(all(feature = \"defmt\", not(feature = \"obfuscate\")))]
+        ::defmt::{level}!(\"{location}: {code}{format}\"{arguments}{caller});
+        #[cfg(all(feature = \"defmt\", feature = \"obfuscate\"))]
+        ::defmt::{level}!(\"[site {site}]: {code}{format}\"{arguments});")
+}
+
+// Convert the format string and arguments of a message into those of defmt, which takes neither
+// named arguments nor variables captured by the format string, by numbering every placeholder, such
+// as "unable to load {path}" into "unable to load {0}" with the argument path. The value of a
+// redacted placeholder is never logged.
+fn defmt_format(message: &[String]) -> (String, Vec<String>) {
+    let template = message[0].strip_prefix('"').and_then(|template| template.strip_suffix('"')).unwrap_or(&message[0]);
+    let mut arguments = Vec::new();
+    let mut named = Vec::new();
+    for argument in &message[1..] {
+        match split_assignment(argument) {
+            Some((name, value)) if name.chars().all(|c| c.is_alphanumeric() || c == '_') => named.push((name, value)),
+            _ => arguments.push(argument.clone()),
+        }
+    }
+    let positional = arguments.len();
+    let mut captured: Vec<String> = Vec::new();
+    let mut format = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                format.push_str("{{");
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                format.push_str("}}");
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                if placeholder.starts_with("redact:") {
+                    format.push_str("(redacted)");
+                    continue;
+                }
+                let (name, spec) = match placeholder.split_once(':') {
+                    Some((name, spec)) => (name.trim(), format!(":{spec}")),
+                    None => (placeholder.trim(), String::new()),
+                };
+                let index = if name.is_empty() {
+                    next += 1;
+                    next - 1
+                } else if let Ok(index) = name.parse::<usize>() {
+                    index
+                } else {
+                    let index = captured.iter().position(|known| known == name).unwrap_or_else(|| {
+                        captured.push(name.to_string());
+                        let value = named.iter().find(|(known, _)| known == name).map(|(_, value)| value.clone());
+                        arguments.push(value.unwrap_or_else(|| name.to_string()));
+                        captured.len() - 1
+                    });
+                    positional + index
+                };
+                format.push_str(&format!("{{{index}{spec}}}"));
+            }
+            c => format.push(c),
+        }
+    }
+    (format, arguments)
+}
+
+// Return the path of a source file with the path prefix applied when the macro is expanded, in the
+// same way as the file generator does at run time, so that the path may be interned by defmt
+fn defmt_file(file: &str, prefix: Option<&str>) -> String {
+    let Some(prefix) = prefix else {
+        return file.to_string();
+    };
+    match prefix.split_once('=') {
+        None => file.strip_prefix(prefix).unwrap_or(file).to_string(),
+        Some((from, to)) => match file.strip_prefix(from) {
+            Some(rest) => format!("{to}{rest}"),
+            None => file.to_string(),
+        },
+    }
+}
+
+// The level at which a message is logged given as the value of a log argument or setting, such as
+// `warn`, or None when it isn't a known level
+fn log_level(level: &str) -> Option<&'static str> {
+    let level = level.trim_matches('"').to_lowercase();
+    LOG_LEVELS.iter().find(|&&known| known == level).copied()
+}
+
+// Whether the severity given to the error by a severity argument is below the threshold of the named
+// setting, such as `backtrace_threshold = "fatal"`, so that the side effect that it governs is left
+// out of the generated code. An error without a severity, or a setting that isn't given, is never
+// below the threshold.
+fn below_threshold(setting: &str) -> bool {
+    let Some(severity) = flags::current().severity else {
+        return false;
+    };
+    let Some(threshold) = config::setting(setting) else {
+        return false;
+    };
+    let rank = |level: &str| LEVELS.iter().position(|&known| known == level);
+    let limit = rank(&threshold.to_lowercase()).unwrap_or_else(|| {
+        panic!("Unknown severity '{threshold}' given to '{setting}' in nuhound.toml. Expected 'trace', 'debug', \
+            'info', 'warn', 'error' or 'fatal'")
+    });
+    rank(severity).is_some_and(|rank| rank < limit)
+}
+
+// Generate the statement that notes the exit code given by an exit_code argument, such as `exit_code =
+// 69`, as the "exit" metadata of the error, so that the main attribute exits with that code when the
+// error reaches it.
+fn exit_generator() -> String {
+    let Some(exit) = flags::current().exit else {
+        return String::new();
+    };
+    format!("
+        notes.push((\"exit\", \"{exit}\".to_string()));
+    ")
+}
+
+// Generate the statement that notes the classification given by a retriable argument, such as
+// `retriable = false`, as the "retriable" metadata of the error, so that the retry attribute and the
+// is_retriable macro may find it in the chain.
+fn retriable_generator() -> String {
+    let Some(retriable) = flags::current().retriable else {
+        return String::new();
+    };
+    format!("
+        notes.push((\"retriable\", \"{retriable}\".to_string()));
+    ")
+}
+
+// Generate the expression that determines whether the error, which is borrowed rather than moved,
+// is transient. Errors that derive Retriable have an inherent method, which takes precedence, while
+// other errors are transient unless the outermost layer of their chain that was given a retriable
+// argument says otherwise. Errors that don't implement the Error trait are transient.
+fn retriable_check_generator(error: &str) -> String {
+    format!("{{
+            trait Retriable {{
+                fn __nuhound_retriable(&self) -> bool {{
+                    true
+                }}
+            }}
+            impl<T: ?Sized> Retriable for &T {{}}
+            trait Classified: ::std::error::Error + Sized + 'static {{
+                fn __nuhound_retriable(&self) -> bool {{
+                    let noted = {NOTED};
+                    {CLASSIFIED}
+                }}
+            }}
+            impl<T: ::std::error::Error + 'static> Classified for T {{}}
+            (&{error}).__nuhound_retriable()
+        }}")
+}
+
+// The statements that search the chain of the error held in 'self' for the outermost layer with the
+// "retriable" metadata, found by the closure in 'noted', returning its classification or true when
+// there is none
+const CLASSIFIED: &str = "\
+    let mut layer: ::std::option::Option<&(dyn ::std::error::Error + 'static)> = ::std::option::Option::Some(self); \
+    while let ::std::option::Option::Some(current) = layer { \
+        match noted(current, \"retriable\").as_deref() { \
+            ::std::option::Option::Some(\"true\") => return true, \
+            ::std::option::Option::Some(\"false\") => return false, \
+            _ => layer = current.source(), \
+        } \
+    } \
+    true";
+
+// Generate the expression that determines whether any layer of the chain of the error, which is
+// borrowed rather than moved, is permanent. An error that derives Retriable is permanent when its
+// inherent method says so, while the chain of any other error is searched for a layer that was given
+// a `retriable = false` or `classification = Permanent` argument. Errors that don't implement the
+// Error trait are not permanent.
+fn permanent_check_generator(error: &str) -> String {
+    format!("{{
+            trait Permanent {{
+                fn __nuhound_permanent(&self) -> bool {{
+                    false
+                }}
+            }}
+            impl<T: ?Sized> Permanent for &T {{}}
+            trait Classified: ::std::error::Error + Sized + 'static {{
+                fn __nuhound_permanent(&self) -> bool {{
+                    let noted = {NOTED};
+                    {PERMANENT}
+                }}
+            }}
+            impl<T: ::std::error::Error + 'static> Classified for T {{}}
+            (&{error}).__nuhound_permanent()
+        }}")
+}
+
+// The expression that searches every layer of the chain of the error held in 'self' for one with
+// the "retriable" metadata of false, found by the closure in 'noted'
+const PERMANENT: &str = "\
+    ::std::iter::successors(::std::option::Option::Some(self as &(dyn ::std::error::Error + 'static)), |layer| ::std::error::Error::source(*layer)) \
+        .any(|layer| noted(layer, \"retriable\").as_deref() == ::std::option::Option::Some(\"false\"))";
+
+// Generate the statements that note the tags given by a tags argument, such as `tags = ["io",
+// "startup"]`, after the message, such as "[tags: io, startup]", when the 'disclose-tags' feature is
+// enabled, so that errors may be filtered by subsystem. A JSON message gains a "tags" member instead.
+fn tags_generator() -> String {
+    let tags = flags::current().tags;
+    if tags.is_empty() {
+        return String::new();
+    }
+    let text = tags.iter().map(|tag| tag[1..tag.len() - 1].to_string()).collect::<Vec<_>>().join(", ");
+    let json = tags.iter().map(|tag| format!("\\{}\\\"", &tag[..tag.len() - 1])).collect::<Vec<_>>().join(",");
+    format!("
+        #[cfg(feature = \"disclose-tags\")]
+        let inform = if inform.starts_with(\"{{\\\"file\\\":\") && inform.ends_with('}}') {{
+            format!(\"{{}},\\\"tags\\\":[{}]}}}}\", &inform[..inform.len() - 1])
+        }} else {{
+            format!(\"{{}} [tags: {}]\", inform)
+        }};
+    ", json.replace('{', "{{").replace('}', "}}"), text.replace('{', "{{").replace('}', "}}"))
+}
+
+// Generate the statements that append the fields given after `fields:`, such as `fields: request_id
+// = id, shard = ?shard`, to the message as key=value pairs, such as "(request_id=42, shard=Some(3))",
+// and note them as a JSON object in the "fields" metadata of the error. The value is formatted with
+// Display, or with Debug when it is prefixed with '?', only when the error is created. A JSON message
+// gains a "fields" object instead.
+fn fields_generator() -> String {
+    let fields = flags::current().fields;
+    if fields.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = fields.iter()
+        .map(|(key, value)| match value.strip_prefix('?') {
+            Some(value) => format!("(\"{key}\", format!(\"{{:?}}\", {}))", value.trim()),
+            None => format!("(\"{key}\", format!(\"{{}}\", {value}))"),
+        })
+        .collect();
+    format!("
+        let inform = {{
+            let fields: [(&str, ::std::string::String); {}] = [{}];
+            let escape = {JSON_ESCAPE};
+            {FIELDS}
+            let inform = {};
+            notes.push((\"fields\", json));
+            inform
+        }};
+    ", pairs.len(), pairs.join(", "), member_generator(
+        "format!(\"{},\\\"fields\\\":{}}}\", &inform[..inform.len() - 1], json)",
+        "format!(\"{} ({})\", inform, fields.iter() \
+            .map(|(key, value)| format!(\"{}={}\", key, value)) \
+            .collect::<::std::vec::Vec<_>>().join(\", \"))",
+    ))
+}
+
+// The statement that formats the key and value pairs held in 'fields' as a JSON object in 'json'
+const FIELDS: &str = "\
+    let json = format!(\"{{{}}}\", fields.iter() \
+        .map(|(key, value)| format!(\"{}:{}\", escape(key), escape(value))) \
+        .collect::<::std::vec::Vec<_>>().join(\",\"));";
+
+// Generate the statements that append the advice given by a help argument, such as `help = "run
+// `myapp init` to create a default config"`, and the link given by a url argument, such as `url =
+// "https://docs.example.com/errors/E042"`, to the message as distinct "help:" and "see:" lines, in the
+// same way as the help and url of an error that derives Diagnostic, and note them as the "help" and
+// "url" metadata of the error. A JSON message gains "help" and "url" members instead.
+fn advice_generator() -> String {
+    let flags = flags::current();
+    [(flags.help, "help", "help"), (flags.url, "url", "see")].into_iter()
+        .filter_map(|(value, key, label)| value.map(|value| format!("
+        let inform = {{
+            let advice = format!(\"{{}}\", {value});
+            {}
+        }};
+        ", advise_generator(key, label))))
+        .collect()
+}
+
+// Generate the statements that append the help and url of a cause that derives Diagnostic to the
+// message in the same way as those given by the help and url arguments, which take their place when
+// they are given so that the message doesn't carry two of either
+fn derived_advice_generator() -> String {
+    let flags = flags::current();
+    [(flags.help.is_none(), "help", "help"), (flags.url.is_none(), "url", "see")].into_iter()
+        .filter(|(derived, _, _)| *derived)
+        .map(|(_, key, label)| format!("
+        let inform = match reason.__nuhound_{key}() {{
+            ::std::option::Option::Some(advice) => {{ let advice = advice.to_string(); {} }}
+            ::std::option::Option::None => inform,
+        }};
+        ", advise_generator(key, label)))
+        .collect()
+}
+
+// Generate the statements that append the advice held in 'advice' to the message in 'inform' as a
+// line with the given label and note it as the metadata of the error with the given key
+fn advise_generator(key: &str, label: &str) -> String {
+    let json = format!("format!(\"{{}},\\\"{key}\\\":{{}}}}}}\", &inform[..inform.len() - 1], ({JSON_ESCAPE})(&advice))");
+    let text = format!("format!(\"{{}}\\n{label}: {{}}\", inform, advice)");
+    format!("let inform = {}; \
+    notes.push((\"{key}\", advice)); \
+    inform", member_generator(&json, &text))
+}
+
+// Generate the statement that notes the message for end users given by a user argument, such as
+// `user = "The uploaded file appears to be corrupted"`, as the "user" metadata of the error, from
+// which the user_message macro retrieves it
+fn user_generator() -> String {
+    let Some(user) = flags::current().user else {
+        return String::new();
+    };
+    format!("
+        notes.push((\"user\", format!(\"{{}}\", {user})));
+    ")
+}
+
+// Generate the statements that add the backtrace, span fields and span trace to the message in
+// 'inform', which are left out of the errors that are not sampled when the @sample flag is given
+fn detail_generator() -> String {
+    let detail = format!("{}\n{}\n{}", backtrace_generator().trim(), span_fields_generator().trim(), span_trace_generator().trim());
+    if flags::current().sample.is_none() {
+        return detail;
+    }
+    format!("
+        let inform = if __nuhound_sampled {{
+            {detail}
+            inform
+        }} else {{
+            inform
+        }};
+    ")
+}
+
+// The statements that find the path of the enclosing function, such as my_crate::net::fetch, from
+// the type name of a nested function, ignoring any closures that enclose the macro
+const FUNCTION_PATH: &str = "\
+    fn __nuhound_function() {} \
+    fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } \
+    let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); \
+    while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; }";
+
+// An expression giving the name and id of the current thread, or the id alone when it is unnamed
+const THREAD: &str = "{ \
+    let thread = ::std::thread::current(); \
+    match thread.name() { \
+        ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), \
+        ::std::option::Option::None => format!(\"{:?}\", thread.id()), \
+    } \
+}";
+
+// An expression giving the time since the Unix epoch from the system clock
+const TIME: &str = "::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default()";
+
+// An expression giving the time of day in UTC to the microsecond, such as 14:03:27.512094
+const TIME_OF_DAY: &str = "{ \
+    let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); \
+    let seconds = time.as_secs(); \
+    format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) \
+}";
+
+// An expression giving the build identifier from the NUHOUND_BUILD_ID environment variable when the
+// crate is compiled, such as a commit hash, or an empty string when it is not set
+const BUILD: &str = "option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default()";
+
+// An expression giving the profile that the crate is compiled with
+const PROFILE: &str = "if cfg!(debug_assertions) { \"debug\" } else { \"release\" }";
+
+// A closure that looks up the metadata of a layer of an error by its key. The metadata is noted on
+// the Nuhound error of the layer as it is created, apart from its message, and so a layer of any
+// other type has none.
+const NOTED: &str = "|layer: &(dyn ::std::error::Error + 'static), key: &str| -> ::std::option::Option<::std::string::String> { \
+    layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key).map(::std::string::ToString::to_string) \
+}";
+
+// A closure that looks up the member of a layer serialized by the report json macro by its key,
+// scanning the members of the object outside of any string or nested value. A string member is
+// unescaped while any other member is given as it is written.
+const MEMBER: &str = r#"|text: &str, key: &str| -> ::std::option::Option<::std::string::String> { let (mut depth, mut quoted, mut escaped, mut start) = (0, false, false, 0); let (mut name, mut value) = (::std::option::Option::None, ::std::option::Option::None); for (index, c) in text.char_indices() { if quoted { match c { _ if escaped => escaped = false, '\\' => escaped = true, '"' => { quoted = false; if depth == 1 && name.is_none() { name = ::std::option::Option::Some(&text[start + 1..index]); } } _ => {} } continue; } match c { '"' => { quoted = true; start = index; } ':' if depth == 1 && name == ::std::option::Option::Some(key) => value = ::std::option::Option::Some(index + 1), ',' | '}' if depth == 1 && value.is_some() => { let raw = text[value?..index].trim(); let ::std::option::Option::Some(raw) = raw.strip_prefix('"').and_then(|raw| raw.strip_suffix('"')) else { return ::std::option::Option::Some(raw.to_string()).filter(|raw| raw != "null"); }; let mut chars = raw.chars(); let mut decoded = ::std::string::String::new(); while let ::std::option::Option::Some(c) = chars.next() { match c { '\\' => match chars.next()? { 'u' => { let code: ::std::string::String = chars.by_ref().take(4).collect(); decoded.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32)); } 'n' => decoded.push('\n'), 'r' => decoded.push('\r'), 't' => decoded.push('\t'), 'b' => decoded.push('\u{8}'), 'f' => decoded.push('\u{c}'), c => decoded.push(c), }, c => decoded.push(c), } } return ::std::option::Option::Some(decoded); } ',' if depth == 1 => name = ::std::option::Option::None, '{' | '[' => depth += 1, '}' | ']' => depth -= 1, _ => {} } } ::std::option::Option::None }"#;
+
+// A closure that quotes a string as a JSON string
+const JSON_ESCAPE: &str = r#"|text: &str| { let mut json = ::std::string::String::from("\""); for c in text.chars() { match c { '"' => json.push_str("\\\""), '\\' => json.push_str("\\\\"), c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)), c => json.push(c), } } json.push('"'); json }"#;
+
+// Generate the statements that compose the error message into a variable named 'inform'. The
+// message is formatted once from the comma separated format arguments and is prefixed, by joining
+// strings rather than formatting the message again, with the location of the error when the
+// 'disclose' feature is enabled, followed by the path of the enclosing function when the
+// 'disclose-fn' feature is enabled too. The thread is noted after the location when the
+// 'disclose-thread' feature is enabled and the time is noted when the 'disclose-time' feature is
+// enabled, followed by the time elapsed since the layer of the named cause, when there is one. The text of the checked expression, when there is one, follows the message when the
+// 'disclose-snippet' feature is enabled. The layout of the disclosed message may be replaced by a
+// template given by the NUHOUND_LOCATION_FORMAT environment variable or the location_format
+// setting, or by a compact JSON object when the 'disclose-json' feature is enabled. The location is
+// replaced by the number of the site, which is recorded when compiling, when the 'obfuscate'
+// feature is enabled.
+fn inform_generator(message: &str, snippet: Option<&str>, cause: Option<&str>) -> String {
+    let location = location_generator();
+    let parts = analyse(location.chars());
+    let (file, line, column) = (&parts[0], &parts[1], &parts[2]);
+    let template = analyse(message.chars()).into_iter().next().unwrap_or_default();
+    let template = template.strip_prefix('"').and_then(|template| template.strip_suffix('"')).unwrap_or(&template);
+    let site = registry::register_site(&template.replace("\\\"", "\""));
+    let capacity = config::setting("message_capacity");
+    let format = if capacity.is_some() { "format_args" } else { "format" };
+    let prefix = [
+        obfuscate_generator(&format!("\"[site {site}]\""), &precision_generator(
+            &format!("{format}!(\"{{}}:{{}}:{{}}\", {file}, {line}, {column})"),
+            &format!("{format}!(\"{{}}:{{}}\", {file}, {line})"),
+            &format!("{format}!(\"{{}}\", {file})"),
+        )),
+        build_generator(), version_generator(), time_generator(cause), thread_generator(), function_generator(),
+    ];
+    let truncated = truncate_generator(message);
+    let bound = format!("let message = {truncated}; ");
+    let (binding, text) = match config::setting("location_format") {
+        Some(template) => (bound, template_generator(&template)),
+        // The location is written into the buffer ahead of the message rather than joined to it
+        None => match &capacity {
+            Some(capacity) => (String::new(), capacity_generator(
+                &format!("\"{{}}{{}}: {{}}{{}}{{}}{{}}{{}}\", {}, format_args!({message})", prefix.join(", ")), capacity)),
+            None => (bound, format!("[&*{}, \": \", &*{}, &*message].concat()", prefix[..2].join(", &*"), prefix[2..].join(", &*"))),
+        },
+    };
+    // The JSON object is composed from the message bound ahead of it, which the buffer doesn't need
+    let json = match binding.is_empty() {
+        true => format!("{{ let message = {truncated}; {} }}", json_generator(file, line, column, site, cause)),
+        false => json_generator(file, line, column, site, cause),
+    };
+    let snippet = snippet.map(snippet_generator).unwrap_or_default();
+    let disclosed = format!("let inform = {{ \
+        {binding}\
+        #[cfg(feature = \"disclose-json\")] \
+        let inform = {json}; \
+        #[cfg(not(feature = \"disclose-json\"))] \
+        let inform = {text}; \
+        {snippet}\
+        inform \
+    }};");
+    let inform = disclose_generator(&format!("let inform = {truncated};"), &disclosed);
+    let count = if flags::current().sample.is_none() { count_generator(site) } else { String::new() };
+    format!("{}{}{}{}{}{}", inform, fingerprint_generator(message), site_generator(site), function_hash_generator(),
+        repeat_generator(), count)
+}
+
+// Generate an expression giving the disclosed message as a compact JSON object holding the file, the
+// line and column or the number of the site, and the message, for the 'disclose-json' feature. The
+// time elapsed since the layer of the named cause is given in seconds as an "elapsed" member.
+fn json_generator(file: &str, line: &str, column: &str, site: usize, cause: Option<&str>) -> String {
+    let members = obfuscate_generator(&format!("\",\\\"site\\\":{site}\""), &precision_generator(
+        &format!("format!({:?}, {line}, {column})", ",\"line\":{},\"col\":{}"),
+        &format!("format!({:?}, {line})", ",\"line\":{}"),
+        "::std::string::String::new()",
+    ));
+    let file = obfuscate_generator("\"null\"", &format!("escape(&{file})"));
+    let thread = format!("\
+        #[cfg(feature = \"disclose-thread\")] \
+        let inform = format!(\"{{}},\\\"thread\\\":{{}}}}}}\", &inform[..inform.len() - 1], escape(&{THREAD})); ");
+    let elapsed = match cause {
+        Some(cause) => format!("\
+            let inform = match {} {{ \
+                ::std::option::Option::Some(elapsed) => format!(\"{{}},\\\"elapsed\\\":{{}}.{{:06}}}}}}\", \
+                    &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), \
+                ::std::option::Option::None => inform, \
+            }}; ", elapsed_generator(cause)),
+        None => String::new(),
+    };
+    let time = format!("\
+        #[cfg(feature = \"disclose-time\")] \
+        let inform = {{ \
+            let time = {TIME}; \
+            let inform = format!(\"{{}},\\\"time\\\":{{}}.{{:06}}}}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); \
+            {elapsed}\
+            inform \
+        }}; ");
+    let version = format!("\
+        #[cfg(feature = \"disclose-version\")] \
+        let inform = format!(\"{{}},\\\"version\\\":{{}},\\\"profile\\\":{{}}}}}}\", \
+            &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape({PROFILE})); ");
+    format!("{{ \
+        let escape = {0}; \
+        let inform = format!({1:?}, {2}, {3}, escape(&message)); \
+        {thread}{time}{version}\
+        let inform = match {4} {{ \
+            \"\" => inform, \
+            build => format!(\"{{}},\\\"build\\\":{{}}}}}}\", &inform[..inform.len() - 1], escape(build)), \
+        }}; \
+        inform \
+    }}", JSON_ESCAPE, "{{\"file\":{}{},\"msg\":{}}}", file, members, BUILD)
+}
+
+// Compose the message from its format arguments, capped at the number of bytes given by the
+// NUHOUND_MAX_MESSAGE_LENGTH environment variable, or the max_message_length setting, so that a site
+// interpolating a huge payload can't flood a log pipeline. A longer message is cut at a character
+// boundary and followed by an ellipsis and its original length, such as "payload was [1, 2, …
+// (truncated from 9000 bytes)".
+fn truncate_generator(message: &str) -> String {
+    if let Some(capacity) = config::setting("message_capacity") {
+        return capacity_generator(message, &capacity);
+    }
+    let Some(limit) = config::setting("max_message_length") else {
+        return format!("format!({message})");
+    };
+    let limit = limit.trim().parse::<usize>().ok().filter(|&limit| limit > 0).unwrap_or_else(|| {
+        panic!("The maximum message length '{limit}' must be a positive number of bytes, such as 512")
+    });
+    format!("{{ \
+        let message = format!({message}); \
+        if message.len() > {limit} {{ \
+            let mut end = {limit}; \
+            while !message.is_char_boundary(end) {{ end -= 1; }} \
+            format!(\"{{}}… (truncated from {{}} bytes)\", &message[..end], message.len()) \
+        }} else {{ \
+            message \
+        }} \
+    }}")
+}
+
+// The smallest capacity of the buffer of a message, which leaves room for some of the message as
+// well as the ellipsis that follows a message that is cut
+const MINIMUM_CAPACITY: usize = 16;
+
+// Compose the message from its format arguments in a heapless::String with the capacity given by
+// the NUHOUND_MESSAGE_CAPACITY environment variable, or the message_capacity setting, so that the
+// message is formatted without allocating as it grows. The code and context of the message, and its
+// disclosed location, are written into the same buffer as format arguments rather than formatted
+// apart. Formatting stops when the buffer is full and the message is cut at a character boundary and
+// followed by an ellipsis, such as "payload was [1, 2, …". The buffer is only copied into the String
+// held by the error, in one allocation, once the message is complete.
+fn capacity_generator(message: &str, capacity: &str) -> String {
+    if config::setting("max_message_length").is_some() {
+        panic!("The 'message_capacity' and 'max_message_length' settings cannot be given together");
+    }
+    let capacity = capacity.trim().parse::<usize>().ok().filter(|&capacity| capacity >= MINIMUM_CAPACITY).unwrap_or_else(|| {
+        panic!("The message capacity '{capacity}' must be a number of bytes of at least {MINIMUM_CAPACITY}, such as 128")
+    });
+    format!("{{ \
+        struct Buffer(::heapless::String<{capacity}>, bool); \
+        impl ::core::fmt::Write for Buffer {{ \
+            fn write_str(&mut self, text: &str) -> ::core::fmt::Result {{ \
+                for c in text.chars() {{ \
+                    if self.0.push(c).is_err() {{ \
+                        self.1 = true; \
+                        return ::core::result::Result::Err(::core::fmt::Error); \
+                    }} \
+                }} \
+                ::core::result::Result::Ok(()) \
+            }} \
+        }} \
+        let mut buffer = Buffer(::heapless::String::new(), false); \
+        let _ = ::core::fmt::Write::write_fmt(&mut buffer, format_args!({message})); \
+        if buffer.1 {{ \
+            while buffer.0.len() + '…'.len_utf8() > {capacity} && buffer.0.pop().is_some() {{}} \
+            let _ = buffer.0.push('…'); \
+        }} \
+        ::std::string::String::from(buffer.0.as_str()) \
+    }}")
+}
+
+// Generate the statements that count the errors produced by the site with the given number. The
+// counters are held by the crate in the array declared by the site_counters macro when the
+// 'site-counts' feature is enabled, so that they may be read with the nuhound_sites macro, and the
+// nuhound_errors_total counter of the metrics facade is incremented when the 'metrics' feature is
+// enabled. The counter is labelled with the file and line of the site, or its number when the
+// 'obfuscate' feature is enabled, and with the error code of the site. The metrics counter is left
+// out when the severity of the error is below the metrics_threshold setting.
+fn count_generator(site: usize) -> String {
+    let location = match flags::location() {
+        Some((file, line, _)) => format!("{:?}", format!("{}:{line}", shorten(&file))),
+        None => String::from("concat!(file!(), \":\", line!())"),
+    };
+    let code = flags::current().code.unwrap_or_default();
+    let counter = format!("
+        #[cfg(feature = \"site-counts\")]
+        crate::__NUHOUND_SITE_COUNTS[{0}].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+    ", site - 1);
+    if below_threshold("metrics_threshold") {
+        return counter;
+    }
+    format!("
+        {}
+        #[cfg(feature = \"metrics\")]
+        ::metrics::counter!(\"nuhound_errors_total\", \"site\" => {}, \"code\" => {code:?}).increment(1);
+    ", counter.trim(), obfuscate_generator(&format!("\"{site}\""), &location))
+}
+
+// Shorten the path of a source file as given by the NUHOUND_PATH_PREFIX environment variable, or the
+// path_prefix setting, when the macro is expanded in the same way as the code generated by
+// file_generator does at run time
+fn shorten(file: &str) -> String {
+    let prefix = config::setting("path_prefix").unwrap_or_default();
+    match prefix.split_once('=') {
+        _ if prefix.is_empty() => file.to_string(),
+        None => file.strip_prefix(prefix.as_str()).unwrap_or(file).to_string(),
+        Some((from, to)) => file.strip_prefix(from).map(|rest| format!("{to}{rest}")).unwrap_or_else(|| file.to_string()),
+    }
+}
+
+// Generate the statements that prefix the message in 'inform' with the number of its site, such as
+// [site 12], when the 'site-id' feature is enabled, so that errors may be matched to the sites listed
+// by the error_sites macro. The number is left out when the 'obfuscate' feature gives it in place of
+// the location. A JSON message gains a "site" member instead.
+fn site_generator(site: usize) -> String {
+    format!("
+        #[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]
+        let inform = {};
+    ", member_generator(
+        &format!("format!(\"{{}},\\\"site\\\":{site}}}}}\", &inform[..inform.len() - 1])"),
+        &format!("format!(\"[site {site}] {{}}\", inform)"),
+    ))
+}
+
+// Generate the statement that appends the text of the checked expression to the message in 'inform'
+// when the 'disclose-snippet' feature is enabled, such as "... in `db.fetch(id)`", so that a trace
+// shows the code that failed without the source to hand. A JSON message gains a "snippet" member
+// instead.
+fn snippet_generator(expression: &str) -> String {
+    let json: String = expression.chars().fold(String::from("\""), |mut json, c| {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+        json
+    }) + "\"";
+    format!("\
+        #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] \
+        let inform = format!(\"{{}},\\\"snippet\\\":{{}}}}}}\", &inform[..inform.len() - 1], {json:?}); \
+        #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] \
+        let inform = format!(\"{{}} in `{{}}`\", inform, {expression:?}); \
+    ")
+}
+
+// Generate the statements that prefix the message in 'inform' with a short fingerprint of the site,
+// such as [a3f91c], when the 'fingerprint' feature is enabled. The fingerprint is a hash of the
+// source file and the format string of the message, computed when compiling, so that identical
+// errors may be grouped across versions whether or not the location is disclosed. The line is left
+// out so that the fingerprint survives edits elsewhere in the file. A JSON message gains a
+// "fingerprint" member instead.
+fn fingerprint_generator(message: &str) -> String {
+    let template = analyse(message.chars()).into_iter().next().unwrap_or_default();
+    format!("
+        #[cfg(feature = \"fingerprint\")]
+        let inform = {{ \
+            const FINGERPRINT: u32 = {{ \
+                let bytes = concat!(file!(), \":\", {template:?}).as_bytes(); \
+                let mut hash: u32 = 0x811c_9dc5; \
+                let mut index = 0; \
+                while index < bytes.len() {{ \
+                    hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); \
+                    index += 1; \
+                }} \
+                hash & 0xff_ffff \
+            }}; \
+            {0} \
+        }};
+    ", member_generator(
+        "format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT)",
+        "format!(\"[{:06x}] {}\", FINGERPRINT, inform)",
+    ))
+}
+
+// Generate the expression giving a short hash of the path of the enclosing function, which is computed
+// once for each site when the first error arises
+fn function_hash_expression() -> String {
+    format!("{{ \
+        static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); \
+        *FUNCTION_HASH.get_or_init(|| {{ \
+            {FUNCTION_PATH} \
+            name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff \
+        }}) \
+    }}")
+}
+
+// Generate the statements that prefix the message in 'inform' with a short hash of the path of the
+// enclosing function, such as [fn 4b0e2d], when the 'fingerprint-fn' feature is enabled, so that
+// errors may be grouped by function even when their messages hold differing values. A JSON message
+// gains a "function_hash" member instead.
+fn function_hash_generator() -> String {
+    format!("
+        #[cfg(feature = \"fingerprint-fn\")]
+        let inform = {{ \
+            let hash = {0}; \
+            {1} \
+        }};
+    ", function_hash_expression(), member_generator(
+        "format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash)",
+        "format!(\"[fn {:06x}] {}\", hash, inform)",
+    ))
+}
+
+// Generate the statement that notes the hash of the path of the enclosing function as the
+// "function_hash" metadata of the error when the 'fingerprint-fn' feature is enabled, so that it may
+// be looked up without parsing the message
+fn function_hash_note_generator() -> String {
+    format!("
+        #[cfg(feature = \"fingerprint-fn\")]
+        notes.push((\"function_hash\", format!(\"{{:06x}}\", {})));
+    ", function_hash_expression())
+}
+
+// Generate the statements that note in 'inform' how many times the same message has been repeated
+// at the site without a different message in between, such as "... (repeated 512 times since last
+// report)", when the 'count-repeats' feature is enabled, so that a flood of identical failures may be
+// collapsed into the first of them. The site keeps a hash of the last message and a count of its
+// repeats in statics. A JSON message gains a "repeated" member instead.
+fn repeat_generator() -> String {
+    format!("
+        #[cfg(feature = \"count-repeats\")]
+        let inform = {{ \
+            static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); \
+            static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); \
+            let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); \
+            if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash {{ \
+                REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); \
+                inform \
+            }} else {{ \
+                let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; \
+                {0} \
+            }} \
+        }};
+    ", member_generator(
+        "format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats)",
+        "format!(\"{} (repeated {} times since last report)\", inform, repeats)",
+    ))
+}
+
+// Generate the expression that composes the disclosed message using a template, such as
+// "{file}#{line} [{module}] {msg}". The placeholders are replaced by the parts of the location, the
+// module path, the path of the enclosing function, the message, which is held in a variable named
+// 'message', the current thread, the time of day, the build identifier, the version of the crate and
+// the profile that it is compiled with. Braces may be escaped by doubling them as with
+// format!.
+fn template_generator(template: &str) -> String {
+    const PLACEHOLDERS: [&str; 11] = [
+        "file", "line", "column", "module", "function", "msg", "thread", "time", "build", "version", "profile",
+    ];
+    let location = location_generator();
+    let location = analyse(location.chars());
+    let values = [
+        location[0].clone(),
+        location[1].clone(),
+        location[2].clone(),
+        String::from("module_path!()"),
+        format!("{{ {FUNCTION_PATH} name }}"),
+        String::from("message"),
+        String::from(THREAD),
+        String::from(TIME_OF_DAY),
+        String::from(BUILD),
+        String::from("env!(\"CARGO_PKG_VERSION\")"),
+        String::from(PROFILE),
+    ];
+    let mut format = String::new();
+    let mut used: Vec<usize> = Vec::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                format.push_str(if c == '{' { "{{" } else { "}}" });
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let index = PLACEHOLDERS.iter().position(|&placeholder| placeholder == name)
+                    .unwrap_or_else(|| panic!("Unknown placeholder '{{{name}}}' in NUHOUND_LOCATION_FORMAT. \
+                        Expected {{file}}, {{line}}, {{column}}, {{module}}, {{function}}, {{thread}}, {{time}}, \
+                        {{build}}, {{version}}, {{profile}} or {{msg}}"));
+                let position = used.iter().position(|&used| used == index).unwrap_or_else(|| {
+                    used.push(index);
+                    used.len() - 1
+                });
+                format.push_str(&format!("{{{position}}}"));
+            }
+            c => format.push(c),
+        }
+    }
+    if !used.contains(&5) {
+        panic!("NUHOUND_LOCATION_FORMAT must contain the {{msg}} placeholder");
+    }
+    let arguments: Vec<&str> = used.iter().map(|&index| values[index].as_str()).collect();
+    format!("format!({:?}, {})", format, arguments.join(", "))
+}
+
+// Generate an expression giving the path of the enclosing function followed by a colon when the
+// 'disclose-fn' feature is enabled, or nothing otherwise
+fn function_generator() -> String {
+    format!("{{ \
+        #[cfg(feature = \"disclose-fn\")] \
+        let function = {{ {FUNCTION_PATH} format!(\"{{}}: \", name) }}; \
+        #[cfg(not(feature = \"disclose-fn\"))] \
+        let function = \"\"; \
+        function \
+    }}")
+}
+
+// Generate an expression choosing between the full location of the error, the file and line, or the
+// file alone. The coarser forms are used when only the 'disclose-line' or 'disclose-file' feature is
+// enabled so that libraries may ship with coarse locations while applications opt into full detail.
+// The 'no-column' feature drops the column from the full location so that golden traces do not churn
+// as code is reformatted.
+fn precision_generator(full: &str, line: &str, file: &str) -> String {
+    let coarse = "all(feature = \"disclose-file\", \
+        not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))";
+    let code = format!("{{ \
+        #[cfg({coarse})] \
+        let location = {file}; \
+        #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), \
+            all(feature = \"no-column\", not({coarse}))))] \
+        let location = {line}; \
+        #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", \
+            not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] \
+        let location = {full}; \
+        location \
+    }}");
+    match disclose_tier() {
+        Some(feature) => code.replace(&format!("feature = \"{feature}\""), "all()"),
+        None => code,
+    }
+}
+
+// Generate an expression choosing the given obfuscated form of the location of the error when the
+// 'obfuscate' feature is enabled, or the disclosed form otherwise, so that the paths of the source
+// files are left out of binaries shipped to customers
+fn obfuscate_generator(obfuscated: &str, disclosed: &str) -> String {
+    format!("{{ \
+        #[cfg(feature = \"obfuscate\")] \
+        let location = {obfuscated}; \
+        #[cfg(not(feature = \"obfuscate\"))] \
+        let location = {disclosed}; \
+        location \
+    }}")
+}
+
+// Generate an expression giving the build identifier preceded by an @ when the NUHOUND_BUILD_ID
+// environment variable is set as the crate is compiled, or nothing otherwise, so that a trace
+// identifies the binary that produced it
+fn build_generator() -> String {
+    format!("match {BUILD} {{ \
+        \"\" => ::std::string::String::new(), \
+        build => format!(\"@{{}}\", build), \
+    }}")
+}
+
+// Generate an expression giving the version of the crate and the profile that it is compiled with in
+// brackets, such as [1.4.2 release], when the 'disclose-version' feature is enabled, or nothing
+// otherwise
+fn version_generator() -> String {
+    format!("{{ \
+        #[cfg(feature = \"disclose-version\")] \
+        let version = format!(\"[{{}} {{}}] \", env!(\"CARGO_PKG_VERSION\"), {PROFILE}); \
+        #[cfg(not(feature = \"disclose-version\"))] \
+        let version = \"\"; \
+        version \
+    }}")
+}
+
+// Generate an expression giving the time of day in brackets when the 'disclose-time' feature is
+// enabled, or nothing otherwise. The time is followed by the time elapsed since the layer of the named
+// cause, such as [14:03:27.512094 +1.204ms], when the cause is a Nuhound error that noted its time.
+fn time_generator(cause: Option<&str>) -> String {
+    let time = match cause {
+        Some(cause) => format!("match {} {{ \
+            ::std::option::Option::Some(elapsed) => format!(\"[{{}} +{{:?}}] \", {TIME_OF_DAY}, elapsed), \
+            ::std::option::Option::None => format!(\"[{{}}] \", {TIME_OF_DAY}), \
+        }}", elapsed_generator(cause)),
+        None => format!("format!(\"[{{}}] \", {TIME_OF_DAY})"),
+    };
+    format!("{{ \
+        #[cfg(feature = \"disclose-time\")] \
+        let time = {time}; \
+        #[cfg(not(feature = \"disclose-time\"))] \
+        let time = \"\"; \
+        time \
+    }}")
+}
+
+// Generate an expression giving the time elapsed since the layer of the named cause was created, as
+// noted by its "time" metadata, or None when the cause isn't a Nuhound error or noted no time. The
+// Nuhound error is told apart from any other cause by the precedence of a method found without
+// autoref over one found through it.
+fn elapsed_generator(cause: &str) -> String {
+    format!("{{ \
+        trait NoTime {{ \
+            fn __nuhound_time(&self) -> ::std::option::Option<&str> {{ ::std::option::Option::None }} \
+        }} \
+        impl<T: ?Sized> NoTime for &T {{}} \
+        trait Time {{ \
+            fn __nuhound_time(&self) -> ::std::option::Option<&str>; \
+        }} \
+        impl Time for ::nuhound::Nuhound {{ \
+            fn __nuhound_time(&self) -> ::std::option::Option<&str> {{ self.noted(\"time\") }} \
+        }} \
+        (&{cause}).__nuhound_time() \
+            .and_then(|time| {{ \
+                let (seconds, micros) = time.split_once('.')?; \
+                ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) \
+            }}) \
+            .and_then(|since| {TIME}.checked_sub(since)) \
+    }}")
+}
+
+// Generate the statement that notes the time since the Unix epoch at which the error is created as
+// its "time" metadata, to the microsecond, when the 'disclose-time' feature is enabled, so that the
+// layers created from it may show the time elapsed since
+fn time_note_generator() -> String {
+    disclose_generator("", &format!("
+        #[cfg(feature = \"disclose-time\")]
+        notes.push((\"time\", {{ let time = {TIME}; format!(\"{{}}.{{:06}}\", time.as_secs(), time.subsec_micros()) }}));"))
+}
+
+// Generate an expression giving the name and id of the current thread in brackets when the
+// 'disclose-thread' feature is enabled, or nothing otherwise
+fn thread_generator() -> String {
+    format!("{{ \
+        #[cfg(feature = \"disclose-thread\")] \
+        let thread = format!(\"[{{}}] \", {THREAD}); \
+        #[cfg(not(feature = \"disclose-thread\"))] \
+        let thread = \"\"; \
+        thread \
+    }}")
+}
+
+// Generate the arguments giving the file, line and column of the error. This is the location of the
+// input of the macro, taken from its span as literals, unless the @caller flag is given, in which case
+// it is the location of the caller of the enclosing function as captured by the track_caller
+// attribute. The location of the outermost macro is used when the span is not known. A file taken
+// from the span is shortened as the macro is expanded so that only the shortened path is held by
+// the binary.
+fn location_generator() -> String {
+    let prefix = config::setting("path_prefix");
+    if flags::current().caller {
+        format!("{}, __nuhound_caller.line(), __nuhound_caller.column()",
+            file_generator("__nuhound_caller.file()", prefix.as_deref()))
+    } else if let Some((file, line, column)) = flags::location() {
+        format!("{:?}, {line}u32, {column}u32", shorten(&file))
+    } else {
+        format!("{}, line!(), column!()", file_generator("file!()", prefix.as_deref()))
+    }
+}
+
+// Generate the expression giving the path of the source file. The prefix, taken from the
+// NUHOUND_PATH_PREFIX environment variable or the path_prefix setting when the macro is expanded,
+// is either a path to strip from the start of the file or takes the form 'from=to' to replace one
+// path with another.
+fn file_generator(file: &str, prefix: Option<&str>) -> String {
+    let Some(prefix) = prefix else {
+        return file.to_string();
+    };
+    match prefix.split_once('=') {
+        None => format!("{{ let file: &str = {file}; file.strip_prefix({prefix:?}).unwrap_or(file) }}"),
+        Some((from, to)) => format!("{{ \
+            let file: &str = {file}; \
+            match file.strip_prefix({from:?}) {{ \
+                ::std::option::Option::Some(rest) => format!(\"{{}}{{}}\", {to:?}, rest), \
+                ::std::option::Option::None => file.to_string(), \
+            }} \
+        }}"),
+    }
+}
+
+// The features that enable the disclosed form of the code, each giving a different precision of the
+// location of the error, together with the 'disclose-debug-only' feature which does so for debug
+// builds alone so that release builds are reduced to plain messages
+const DISCLOSE: &str = "feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", \
+    feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)";
+
+// Return the feature that the disclose setting of nuhound.toml stands for, which takes effect as if
+// the feature were enabled in every crate of the workspace, or None when the setting is not given or
+// is "off"
+fn disclose_tier() -> Option<&'static str> {
+    let tier = config::setting("disclose")?;
+    match tier.as_str() {
+        "off" => None,
+        "on" | "full" => Some("disclose"),
+        "line" => Some("disclose-line"),
+        "file" => Some("disclose-file"),
+        _ => panic!("Unknown disclose setting '{tier}'. Expected 'off', 'on', 'full', 'line' or 'file'"),
+    }
+}
+
+// Generate the plain form of the code, used unless one of the 'disclose' features is enabled,
+// together with the disclosed form. With the 'disclose-env' feature both forms are compiled and the
+// choice is made at run time from the NUHOUND_DISCLOSE environment variable. Only the plain form is
+// generated when the @quiet flag is given so that the output stays plain whatever features are
+// enabled, and only the disclosed form when the @disclose flag is given.
+fn disclose_generator(plain: &str, disclosed: &str) -> String {
+    if flags::current().quiet {
+        return format!("
+        {plain}
+    ");
+    }
+    if flags::current().disclose {
+        return format!("
+        {disclosed}
+    ");
+    }
+    // The disclosed form is used in place of the plain form when the disclose setting asks for it
+    let plain = if disclose_tier().is_some() { disclosed } else { plain };
+    // A plain form that is empty is left out rather than given the attribute of the plain form
+    let compiled = match plain.is_empty() {
+        true => String::new(),
+        false => format!("
+        #[cfg(not(any({DISCLOSE}, feature = \"disclose-env\")))]
+        {plain}"),
+    };
+    format!("{compiled}
+        #[cfg(any({DISCLOSE}))]
+        {disclosed}
+        #[cfg(all(feature = \"disclose-env\", not(any({DISCLOSE}))))]
+        {0}
+    ", runtime_generator(plain, disclosed))
+}
+
+// Generate the code that chooses between the plain and disclosed forms at run time. The environment
+// variable is read once and disclosure is enabled unless it is missing, empty, "0" or "false". When
+// both forms bind the same variable the choice is made within the binding so that the variable
+// remains in scope.
+fn runtime_generator(plain: &str, disclosed: &str) -> String {
+    let choose = |plain: &str, disclosed: &str| format!("{{ \
+        static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); \
+        let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; \
+        if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {{ \
+            {disclosed} \
+        }} else {{ \
+            {plain} \
+        }} \
+    }}");
+    let binding = |code: &str| code.strip_prefix("let ")
+        .and_then(|code| code.strip_suffix(';'))
+        .and_then(|code| code.split_once(" = "))
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+    match (binding(plain), binding(disclosed)) {
+        (Some((name, plain)), Some((other, disclosed))) if name == other => {
+            format!("let {name} = {};", choose(&plain, &disclosed))
+        }
+        _ => choose(plain, disclosed),
+    }
+}
+
+// Generate the statements that append a backtrace of the conversion site to the message in 'inform'
+// when the 'backtrace' feature is enabled. The backtrace is captured when the NUHOUND_BACKTRACE
+// environment variable is enabled, omitted when it is empty, "0" or "false", and otherwise captured
+// as RUST_LIB_BACKTRACE or RUST_BACKTRACE direct. A JSON message gains a "backtrace" member instead.
+// The backtrace is left out of an error whose severity is below the backtrace_threshold setting.
+fn backtrace_generator() -> String {
+    if below_threshold("backtrace_threshold") {
+        return String::new();
+    }
+    format!("
+        #[cfg(feature = \"backtrace\")]
+        let inform = {{ \
+            let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") {{ \
+                ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), \
+                ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), \
+                ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), \
+            }}; \
+            if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured {{ \
+                inform \
+            }} else {{ \
+                {0} \
+            }} \
+        }};
+    ", member_generator(
+        &format!("format!(\"{{}},\\\"backtrace\\\":{{}}}}}}\", &inform[..inform.len() - 1], ({JSON_ESCAPE})(&backtrace.to_string()))"),
+        "format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace)",
+    ))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// Generate an expression adding to the message in 'inform' with the given JSON expression when it is
+// a JSON object, which it may be when the 'disclose-json' feature is enabled, or the given text
+// expression otherwise
+fn member_generator(json: &str, text: &str) -> String {
+    format!("if inform.starts_with(\"{{\\\"file\\\":\") && inform.ends_with('}}') {{ {json} }} else {{ {text} }}")
+}
+
+// Generate the statements that note the fields recorded by the spans that are active where the
+// error arises, innermost first, after the message in 'inform', such as "[spans:
+// handle{request_id=42 peer=10.0.0.1}]", when the 'span-fields' feature is enabled, so that a trace
+// printed far from the failure still shows the request that it belonged to. The fields are those
+// recorded by the ErrorLayer of the tracing-error crate and spans without fields are left out. A
+// JSON message gains a "spans" array of objects with the name and fields of each span instead.
+fn span_fields_generator() -> String {
+    format!("
+        #[cfg(feature = \"span-fields\")]
+        let inform = {{ \
+            let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); \
+            ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| {{ \
+                if !fields.is_empty() {{ \
+                    spans.push((metadata.name(), fields.to_string())); \
+                }} \
+                true \
+            }}); \
+            if spans.is_empty() {{ \
+                inform \
+            }} else {{ \
+                {0} \
+            }} \
+        }};
+    ", member_generator(
+        &format!("format!(\"{{}},\\\"spans\\\":[{{}}]}}}}\", &inform[..inform.len() - 1], spans.iter() \
+            .map(|(name, fields)| format!(\"{{{{\\\"name\\\":{{}},\\\"fields\\\":{{}}}}}}\", ({JSON_ESCAPE})(name), ({JSON_ESCAPE})(fields))) \
+            .collect::<::std::vec::Vec<_>>().join(\",\"))"),
+        "format!(\"{} [spans: {}]\", inform, spans.iter() \
+            .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) \
+            .collect::<::std::vec::Vec<_>>().join(\", \"))",
+    ))
+}
+
+// Generate the statements that append the span trace of the conversion site to the message in
+// 'inform' when the 'span-trace' feature is enabled, so that the layers of a trace may be correlated with
+// the spans that were active. The span trace is taken from the ErrorLayer of the tracing-error crate
+// and nothing is appended when no span is active. A JSON message gains a "span_trace" member instead.
+fn span_trace_generator() -> String {
+    format!("
+        #[cfg(feature = \"span-trace\")]
+        let inform = {{ \
+            let span_trace = ::tracing_error::SpanTrace::capture(); \
+            if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED {{ \
+                inform \
+            }} else {{ \
+                {0} \
+            }} \
+        }};
+    ", member_generator(
+        &format!("format!(\"{{}},\\\"span_trace\\\":{{}}}}}}\", &inform[..inform.len() - 1], ({JSON_ESCAPE})(&span_trace.to_string()))"),
+        "format!(\"{}\\nspan trace:\\n{}\", inform, span_trace)",
+    ))
+}
+
+// The debug custom builder is used to create a macro that generates a Nuhound error in debug
+// builds only. Release builds compile the error out altogether and evaluate to Ok(()).
+fn debug_custom_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.is_empty() {
+        panic!("Contains insufficient parameters");
+    }
+    let message = attributes.join(", ");
+
+    format!("
+    {{
+        #[cfg(debug_assertions)]
+        let outcome: ::std::result::Result<(), ::nuhound::Nuhound> = {0};
+        #[cfg(not(debug_assertions))]
+        let outcome: ::std::result::Result<(), ::nuhound::Nuhound> = ::std::result::Result::Ok(());
+        outcome
+    }}
+    ", custom_generator(&message).trim())
+}
+
+// The guard builder is used to create a macro that binds a refutable pattern or returns a Nuhound
+// error to the calling context when the pattern does not match, converted into the error type of the
+// context by From in the same way as the convert_let macro. The first attribute takes the form `let
+// pattern = expression` and the pattern may include alternatives and bindings. The pattern is
+// always parenthesised because let statements require this of top-level alternatives.
+fn guard_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() < 2 {
+        panic!("Contains insufficient parameters");
+    }
+    let (pattern, expression) = attributes[0]
+        .strip_prefix("let")
+        .filter(|binding| binding.starts_with(char::is_whitespace))
+        .and_then(split_assignment)
+        .unwrap_or_else(|| panic!("The first parameter must take the form 'let pattern = expression'"));
+    let message = attributes[1..].join(", ");
+
+    format!("
+    #[allow(unused_parens)]
+    let ({0}) = {1} else {{
+        match {2} {{
+            ::std::result::Result::Err(error) => return ::std::result::Result::Err(::std::convert::From::from(error)),
+            ::std::result::Result::Ok(()) => ::std::unreachable!(),
+        }}
+    }};
+    ", pattern, expression, custom_generator(&message).trim())
+}
+
+// The dbg report builder is used to create a macro that passes the value of a Report through
+// unchanged but prints the error, together with the location of the macro, when the value is Err.
+fn dbg_report_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+
+    shim_generator(format!("
+    match {0} {{
+        outcome => {{
+            if let ::std::result::Result::Err(error) = &outcome {{
+                {1}
+            }}
+            outcome
+        }}
+    }}
+    ", attributes[0], disclose_generator(
+        &format!("eprintln!(\"[{{}}:{{}}:{{}}] {{}} = {{}}\", {0}, stringify!({1}), error);", location_generator(), attributes[0]),
+        &format!("eprintln!(\"[{{}}:{{}}:{{}}] {{}} =\\n{{}}\", {0}, stringify!({1}), error.trace());", location_generator(), attributes[0]),
+    ).trim()))
+}
+
+// Separate an optional loop label such as 'outer from the start of the attributes
+fn split_label(attributes: &[String]) -> (String, &[String]) {
+    match attributes.first() {
+        Some(label) if label.starts_with('\'') => (label.to_string(), &attributes[1..]),
+        _ => (String::new(), attributes),
+    }
+}
+
+// The break custom builder is used to create a macro that breaks out of a loop, which may be
+// labelled, with a Nuhound error as the value of the loop expression.
+fn break_custom_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    let (label, attributes) = split_label(&attributes);
+    if attributes.is_empty() {
+        panic!("Contains insufficient parameters");
+    }
+    let message = attributes.join(", ");
+
+    format!("
+    break {0} {1}
+    ", label, custom_generator(&message).trim())
+}
+
+// The skip report builder is used to create a macro that records a Nuhound error in a collector and
+// continues with the next iteration of a loop, which may be labelled. The first attribute after
+// the optional label is the collector.
+fn skip_report_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    let (label, attributes) = split_label(&attributes);
+    if attributes.len() < 2 {
+        panic!("Contains insufficient parameters");
+    }
+    let message = attributes[1..].join(", ");
+
+    shim_generator(format!("
+    {{
+        {0}
+        {1}.push(::nuhound::Nuhound::new(inform));
+        continue {2};
+    }}
+    ", inform_generator(&message, None, None).trim(), attributes[0], label))
+}
+
+// The placeholder builder is used to create a macro that generates a Nuhound error for code that
+// has yet to be written. The message mirrors that of the standard todo! and unimplemented! macros
+// and any supplied message is appended to it.
+fn placeholder_builder(item: String, placeholder: &str) -> String {
+    let attributes: Vec<String> = analyse(item.chars())
+        .into_iter()
+        .filter(|attribute| !attribute.is_empty())
+        .collect();
+    let message = if attributes.is_empty() {
+        format!("\"{placeholder}\"")
+    } else {
+        format!("\"{placeholder}: {{}}\", format_args!({})", attributes.join(", "))
+    };
+    custom_generator(&message)
+}
+
+// Separate the body of a function item from the rest of the item, returning the item as a string in
+// which the body has been replaced by a placeholder together with the original tokens of the body.
+// The body tokens are later restored by splice_body so that the locations of any errors reported
+// within the body are preserved.
+fn detach_body(item: TokenStream) -> (String, TokenStream) {
+    let mut trees: Vec<TokenTree> = item.into_iter().collect();
+    match trees.pop() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+            trees.push(TokenTree::Group(Group::new(Delimiter::Brace, BODY.parse().unwrap())));
+            (TokenStream::from_iter(trees).to_string(), group.stream())
+        }
+        _ => panic!("This attribute may only be applied to a function with a body"),
+    }
+}
+
+// Rewrite the output of a macro for crates built without the standard library when the no_std
+// setting is true, so that the generated code uses the core and alloc crates instead. The settings
+// file and its environment variables are registered as dependencies by the first output of a crate.
+fn portable(stream: TokenStream) -> TokenStream {
+    let stream = match config::registration() {
+        Some(item) => rewrite::register(stream, item.parse().unwrap()),
+        None => stream,
+    };
+    match config::setting("no_std").as_deref() {
+        None | Some("false") => stream,
+        Some("true") => rewrite::no_std(stream),
+        Some(value) => panic!("Unknown no_std setting '{value}'. Expected 'true' or 'false'"),
+    }
+}
+
+// Build the output of a macro from its inputs, holding the identifiers of the inputs that the no_std
+// rewrite would replace in placeholders while the output is built and rewritten by portable, so that
+// only the generated code is rewritten and the code of the user is never altered
+fn shielded<const N: usize>(inputs: [TokenStream; N], build: impl FnOnce([TokenStream; N]) -> TokenStream) -> TokenStream {
+    policy::take_breach();
+    let written = inputs.clone();
+    let output = match config::setting("no_std").as_deref() {
+        Some("true") => {
+            let mut identifiers = Vec::new();
+            let inputs = inputs.map(|input| rewrite::shield(input, &mut identifiers));
+            rewrite::unshield(build(inputs), &identifiers)
+        }
+        _ => build(inputs),
+    };
+    match policy::take_breach() {
+        // A message that breaks the policy fails to compile at the literal that it is written as
+        Some((reason, literal)) => {
+            let span = written.into_iter().find_map(|input| rewrite::find_literal(input, &literal));
+            let error = format!("::core::compile_error! {{ {reason:?} }}").parse().unwrap();
+            rewrite::respan(error, span.unwrap_or_else(proc_macro::Span::call_site))
+        }
+        None => output,
+    }
+}
+
+// Replace the body placeholder in the generated tokens with the original tokens of the body
+fn splice_body(generated: TokenStream, body: &TokenStream) -> TokenStream {
+    rewrite::splice(generated, BODY, &TokenTree::Group(Group::new(Delimiter::Brace, body.clone())))
+}
+
+// The context builder is used to create an attribute macro that wraps the body of a function so
+// that any Nuhound error escaping from it gains a further layer carrying the message and the
+// location of the function.
+fn context_builder(attribute: String, item: String) -> String {
+    let attributes = analyse(attribute.chars());
+    if attributes[0].is_empty() {
+        panic!("Contains insufficient parameters");
+    }
+    let message = attributes.join(", ");
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+
+    function.with_body(&format!("
+    use ::nuhound::ResultExtension as _;
+    {0}
+    ", examine_generator(&function.invoke_body(), &message, None).trim()))
+}
+
+// The main builder is used to create an attribute macro that turns a main function returning a
+// Report into a real main function. The original function, together with its attributes, becomes a
+// nested function so that runtime attributes such as #[tokio::main] may still be applied to it. The
+// process exits with the first exit code noted in the chain of the error, or 1 when there is none.
+// The error is rendered by miette when the 'miette' feature is enabled.
+fn main_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The main attribute does not take any parameters");
+    }
+    let function = Function::parse(&item);
+    if !function.inputs.trim().is_empty() {
+        panic!("Function '{}' must not take any parameters", function.name);
+    }
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let runtime = function.attributes.iter().any(|attribute| {
+        let path = attribute.trim_start_matches("#[").split(['(', ']']).next().unwrap_or_default();
+        path.replace(' ', "").ends_with("::main")
+    });
+    if function.is_async() && !runtime {
+        panic!("Function '{}' must not be async unless it is given a runtime attribute, such as #[tokio::main], below the \
+            main attribute", function.name);
+    }
+
+    format!("
+    fn {0}() {{
+        {1}
+        if let ::std::result::Result::Err(error) = {0}() {{
+            let exit = {3}
+                .and_then(|code| code.parse::<i32>().ok())
+                .unwrap_or(1);
+            {2}
+            ::std::process::exit(exit);
+        }}
+    }}
+    ", function.name, function.with_body(&function.body), exit_report_generator(), metadata_generator("error", "exit").trim())
+}
+
+// The statements that print the error returned by main, rendered by miette when the 'miette' feature
+// is enabled and otherwise printed as text or as a trace according to disclosure.
+fn exit_report_generator() -> String {
+    format!("#[cfg(feature = \"miette\")]
+            eprintln!(\"{{:?}}\", {{ let noted = {NOTED}; {MIETTE_REPORT} }});
+            #[cfg(not(feature = \"miette\"))]
+            {{
+                {}
+            }}", disclose_generator("eprintln!(\"Error: {}\", error);", &format!("{{ let noted = {NOTED}; {DEPTH_TRACE} }}")).trim())
+}
+
+// The block that prints the trace of an error, stopping at the depth noted as the "depth" metadata of
+// the outermost layer given the @depth flag, found by the closure in 'noted', if any, and noting how
+// many layers are left out. It is a single block so that it may be selected by the cfg attributes of
+// the disclose generator.
+const DEPTH_TRACE: &str = "{ \
+    let mut limit = ::std::option::Option::None; \
+    let mut layer: ::std::option::Option<&(dyn ::std::error::Error + 'static)> = ::std::option::Option::Some(&error); \
+    let mut index = 0; \
+    while let ::std::option::Option::Some(current) = layer { \
+        let depth = noted(current, \"depth\").and_then(|depth| depth.parse::<usize>().ok()); \
+        if let ::std::option::Option::Some(depth) = depth { \
+            limit = ::std::option::Option::Some(index + depth + 1); \
+            break; \
+        } \
+        index += 1; \
+        layer = current.source(); \
+    } \
+    match limit { \
+        ::std::option::Option::None => eprintln!(\"Error:\\n{}\", error.trace()), \
+        ::std::option::Option::Some(limit) => { \
+            eprintln!(\"Error:\"); \
+            let mut layer: ::std::option::Option<&dyn ::std::error::Error> = ::std::option::Option::Some(&error); \
+            let mut index = 0; \
+            while let ::std::option::Option::Some(current) = layer { \
+                if index == limit { \
+                    let hidden = ::std::iter::successors(::std::option::Option::Some(current), |layer| ::std::error::Error::source(*layer)).count(); \
+                    eprintln!(\"... {} more layers\", hidden); \
+                    break; \
+                } \
+                eprintln!(\"{}: {}\", index, current); \
+                index += 1; \
+                layer = current.source(); \
+            } \
+        } \
+    } }";
+
+// The block that converts the Nuhound error in 'error' into a miette::Report so that it is rendered
+// by miette. The outermost layer becomes a diagnostic whose code, help and url are its metadata, found
+// by the closure in 'noted', and whose label points at the error in the line of source noted as its
+// "snippet" metadata when compiling, while the inner layers are the causes of the diagnostic.
+const MIETTE_REPORT: &str = "{ \
+    #[derive(Debug)] \
+    struct NuhoundDiagnostic { \
+        error: ::nuhound::Nuhound, \
+        message: ::std::string::String, \
+        code: ::std::option::Option<::std::string::String>, \
+        help: ::std::option::Option<::std::string::String>, \
+        url: ::std::option::Option<::std::string::String>, \
+        source: ::std::option::Option<::miette::NamedSource<::std::string::String>>, \
+        label: ::std::option::Option<::miette::LabeledSpan>, \
+    } \
+    impl ::std::fmt::Display for NuhoundDiagnostic { \
+        fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result { \
+            f.write_str(&self.message) \
+        } \
+    } \
+    impl ::std::error::Error for NuhoundDiagnostic { \
+        fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> { \
+            ::std::error::Error::source(&self.error) \
+        } \
+    } \
+    impl ::miette::Diagnostic for NuhoundDiagnostic { \
+        fn code<'a>(&'a self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::fmt::Display + 'a>> { \
+            self.code.as_ref().map(|code| ::std::boxed::Box::new(code) as ::std::boxed::Box<dyn ::std::fmt::Display + 'a>) \
+        } \
+        fn help<'a>(&'a self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::fmt::Display + 'a>> { \
+            self.help.as_ref().map(|help| ::std::boxed::Box::new(help) as ::std::boxed::Box<dyn ::std::fmt::Display + 'a>) \
+        } \
+        fn url<'a>(&'a self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::fmt::Display + 'a>> { \
+            self.url.as_ref().map(|url| ::std::boxed::Box::new(url) as ::std::boxed::Box<dyn ::std::fmt::Display + 'a>) \
+        } \
+        fn source_code(&self) -> ::std::option::Option<&dyn ::miette::SourceCode> { \
+            self.source.as_ref().map(|source| source as &dyn ::miette::SourceCode) \
+        } \
+        fn labels(&self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::iter::Iterator<Item = ::miette::LabeledSpan> + '_>> { \
+            self.label.clone().map(|label| ::std::boxed::Box::new(::std::iter::once(label)) as ::std::boxed::Box<dyn ::std::iter::Iterator<Item = ::miette::LabeledSpan>>) \
+        } \
+    } \
+    let code = noted(&error, \"code\"); \
+    let message = error.to_string().lines().next().unwrap_or_default().to_string(); \
+    let message = match code.as_deref().and_then(|code| message.strip_prefix(&format!(\"[{}] \", code))) { \
+        ::std::option::Option::Some(rest) => rest.to_string(), \
+        ::std::option::Option::None => message, \
+    }; \
+    let span = noted(&error, \"label\").and_then(|label| { \
+        let (start, end) = label.split_once(\"..\")?; \
+        ::std::option::Option::Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?)) \
+    }); \
+    let (source, label) = match (noted(&error, \"snippet\"), span) { \
+        (::std::option::Option::Some(snippet), ::std::option::Option::Some((start, end))) if start <= end && end <= snippet.len() => { \
+            let name = match (noted(&error, \"file\"), noted(&error, \"line\")) { \
+                (::std::option::Option::Some(file), ::std::option::Option::Some(line)) => format!(\"{}:{}\", file, line), \
+                (file, _) => file.unwrap_or_default(), \
+            }; \
+            let label = ::miette::LabeledSpan::new(::std::option::Option::Some(message.clone()), start, end - start); \
+            (::std::option::Option::Some(::miette::NamedSource::new(name, snippet)), ::std::option::Option::Some(label)) \
+        } \
+        _ => (::std::option::Option::None, ::std::option::Option::None), \
+    }; \
+    let (help, url) = (noted(&error, \"help\"), noted(&error, \"url\")); \
+    ::miette::Report::new(NuhoundDiagnostic { error, message, code, help, url, source, label }) \
+}";
+
+// The test builder is used to create an attribute macro for test functions that return a Report.
+// The original function becomes a nested function and the test fails with a panic that carries the
+// error when the nested function returns one. The attributes remain on the outer function so that
+// the test harness sees any #[should_panic] or #[ignore] attributes.
+fn test_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The test attribute does not take any parameters");
+    }
+    let function = Function::parse(&item);
+    if function.is_async() {
+        panic!("Test function '{}' must not be async", function.name);
+    }
+    if function.output.is_none() {
+        panic!("Test function '{}' must return a Report", function.name);
+    }
+    let attributes = function.attributes.join("\n");
+    let nested = Function {
+        attributes: Vec::new(),
+        qualifiers: String::new(),
+        ..function
+    };
+
+    format!("
+    #[test]
+    {0}
+    fn {1}() {{
+        {2}
+        if let ::std::result::Result::Err(error) = {1}() {{
+            {3}
+        }}
+    }}
+    ", attributes, nested.name, nested.with_body(&nested.body), disclose_generator(
+        "panic!(\"Error: {}\", error);",
+        "panic!(\"Error:\\n{}\", error.trace());",
+    ).trim())
+}
+
+// The should err builder is used to create an attribute macro for negative tests that return a
+// Report. The original function becomes a nested function, as for the test builder, and the test
+// fails unless the nested function returns an error whose trace contains each expected substring.
+// The trace is formed from the chain of error sources when the 'disclose' feature isn't enabled.
+fn should_err_builder(attribute: String, item: String) -> String {
+    let expected = analyse(attribute.chars());
+    if expected[0].is_empty() {
+        panic!("The should_err attribute requires at least one expected substring");
+    }
+    let function = Function::parse(&item);
+    if function.is_async() {
+        panic!("Test function '{}' must not be async", function.name);
+    }
+    if function.output.is_none() {
+        panic!("Test function '{}' must return a Report", function.name);
+    }
+    let attributes = function.attributes.join("\n");
+    let nested = Function {
+        attributes: Vec::new(),
+        qualifiers: String::new(),
+        ..function
+    };
+
+    format!("
+    #[test]
+    {0}
+    fn {1}() {{
+        {2}
+        let ::std::result::Result::Err(error) = {1}() else {{
+            panic!(\"Expected an error but '{1}' succeeded\");
+        }};
+        {4}
+        for expected in [{3}] {{
+            if !report.contains(expected) {{
+                panic!(\"The error does not contain the expected text\\n- expected: {{:?}}\\n+ actual:\\n{{}}\", expected,
+                    report.lines().map(|line| format!(\"+ {{}}\", line)).collect::<::std::vec::Vec<_>>().join(\"\\n\"));
+            }}
+        }}
+    }}
+    ", attributes, nested.name, nested.with_body(&nested.body), expected.join(", "), disclose_generator("let report = {
+            let mut report = error.to_string();
+            let mut source = ::std::error::Error::source(&error);
+            while let ::std::option::Option::Some(cause) = source {
+                report.push_str(&format!(\"\\n{}\", cause));
+                source = cause.source();
+            }
+            report
+        };", "let report = error.trace();").trim())
+}
+
+// The instrument builder is used to create an attribute macro that adds a layer of context to any
+// Nuhound error escaping from a function. The layer names the function and shows the values of its
+// parameters, which are captured before the body runs because the body may consume them. Values
+// are shown using Debug where the type implements it and as '..' otherwise. This relies on method
+// resolution preferring the Debugged implementation for the probe over the Opaque implementation
+// for a reference to the probe whenever the former applies.
+fn instrument_builder(attribute: String, item: String) -> String {
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let mut name = format!("\"{}\"", function.name);
+    let mut skip = Vec::new();
+    let mut skip_all = false;
+    for (option, value) in analyse_options(&attribute) {
+        match option.as_str() {
+            "skip" => skip.extend(analyse(value.chars())),
+            "skip_all" => skip_all = true,
+            "name" => name = value,
+            _ => panic!("Unknown option '{option}'. Expected 'skip(...)', 'skip_all' or 'name = \"...\"'"),
+        }
+    }
+
+    let call = call_generator(&function, &name, &skip, skip_all);
+    function.with_body(&format!("
+    use ::nuhound::ResultExtension as _;
+    let __nuhound_call = {0};
+    {1}
+    ", call, examine_generator(&function.invoke_body(), "\"{}\", __nuhound_call", None).trim()))
+}
+
+// Generate an expression that formats the name of a function together with the values of its
+// parameters, such as "my_mod::load(path=\"a.txt\", retries=3)". Values are shown using Debug where
+// the type implements it and as '..' otherwise. Parameters that are skipped, or that are bound by a
+// pattern rather than a name, are left out.
+fn call_generator(function: &Function, name: &str, skip: &[String], skip_all: bool) -> String {
+    let mut fields = Vec::new();
+    let mut values = Vec::new();
+    let skip_all = skip_all || flags::current().quiet;
+    for parameter in analyse(function.inputs.chars()).iter().filter(|_| !skip_all) {
+        let Some((pattern, _)) = split_parameter(parameter) else {
+            continue;
+        };
+        let binding = pattern.strip_prefix("mut ").unwrap_or(&pattern).trim().to_string();
+        let identifier = binding.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && binding.starts_with(|c: char| c.is_alphabetic() || c == '_');
+        if identifier && binding != "self" && binding != "_" && !skip.contains(&binding) {
+            fields.push(format!("{binding}={{}}"));
+            values.push(format!(", (&Probe(&{binding})).show()"));
+        }
+    }
+    let call = format!("format!(\"{{}}::{{}}({0})\", module_path!(), {1}{2})", fields.join(", "), name, values.concat());
+    if values.is_empty() {
+        call
+    } else {
+        format!("{{
+        struct Probe<'a, T: ?Sized>(&'a T);
+        trait Debugged {{
+            fn show(&self) -> String;
+        }}
+        impl<T: ::std::fmt::Debug + ?Sized> Debugged for Probe<'_, T> {{
+            fn show(&self) -> String {{
+                format!(\"{{:?}}\", self.0)
+            }}
+        }}
+        trait Opaque {{
+            fn show(&self) -> String;
+        }}
+        impl<T: ?Sized> Opaque for &Probe<'_, T> {{
+            fn show(&self) -> String {{
+                String::from(\"..\")
+            }}
+        }}
+        {call}
+    }}")
+    }
+}
+
+// The trace all builder is used to create an attribute macro that makes every question mark
+// operator in a function add a layer to the error naming the expression that failed. The body
+// itself is rewritten at the token level, using the trace generator for each expression, so that
+// each layer reports the location of its own question mark.
+fn trace_all_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The trace_all attribute does not take any parameters");
+    }
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+
+    function.with_body(&format!("
+    use ::nuhound::ResultExtension as _;
+    {0}
+    ", function.body))
+}
+
+// Generate the code that converts the error of a rewritten expression into a Nuhound error with the
+// text of the expression as its message
+fn trace_generator(expression: &str) -> Option<String> {
+    Some(convert_generator(EXPRESSION, &format!("\"{{}}\", {expression:?}"), None))
+}
+
+// Generate the code that converts the error of a rewritten expression into a Nuhound error naming
+// the awaited future, provided that the expression is an await
+fn await_generator(expression: &str) -> Option<String> {
+    let future = expression.trim_end().strip_suffix("await")?.trim_end().strip_suffix('.')?.trim_end();
+    Some(convert_generator(EXPRESSION, &format!("\"awaiting {{}}\", {future:?}"), None))
+}
+
+// Convert a duration such as "200ms" or "1.5s" into the code for a std::time::Duration. The units
+// may be ns, us, ms, s, m or h.
+fn duration_generator(duration: &str) -> String {
+    let text = duration.trim().trim_matches('"');
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let scale = match unit.trim() {
+        "ns" => 1.0,
+        "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        "m" => 60e9,
+        "h" => 3600e9,
+        _ => panic!("The duration '{text}' must end with one of the units ns, us, ms, s, m or h"),
+    };
+    let number: f64 = number.parse()
+        .unwrap_or_else(|_| panic!("The duration '{text}' must start with a number"));
+    format!("::std::time::Duration::from_nanos({})", (number * scale).round() as u64)
+}
+
+// Generate the statements that wait for a duration. Async functions require a runtime to provide
+// the timer, which is selected by the 'tokio' or 'async-std' feature.
+fn sleep_generator(function: &Function, duration: &str) -> String {
+    if function.is_async() {
+        format!("
+        #[cfg(feature = \"tokio\")]
+        ::tokio::time::sleep({0}).await;
+        #[cfg(all(feature = \"async-std\", not(feature = \"tokio\")))]
+        ::async_std::task::sleep({0}).await;
+        #[cfg(not(any(feature = \"tokio\", feature = \"async-std\")))]
+        ::std::compile_error!(\"Async functions require either the 'tokio' or the 'async-std' feature\");
+        ", duration)
+    } else {
+        format!("
+        ::std::thread::sleep({0});
+        ", duration)
+    }
+}
+
+// The retry builder is used to create an attribute macro that runs the body of a function
+// repeatedly until it succeeds or the number of attempts is exhausted, optionally waiting between
+// attempts. The final error is linked to a Nuhound error that notes the number of attempts and the
+// errors of the earlier attempts, keeping the notes of its layers when it is a Nuhound error. A
+// permanent error, as classified by the Retriable derive or by the retriable argument of the macros,
+// ends the attempts early.
+fn retry_builder(attribute: String, item: String) -> String {
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let mut attempts = None;
+    let mut sleep = String::new();
+    for (option, value) in analyse_options(&attribute) {
+        match option.as_str() {
+            "attempts" => attempts = Some(value.parse::<u32>()
+                .ok()
+                .filter(|&attempts| attempts > 0)
+                .unwrap_or_else(|| panic!("The number of attempts must be a positive integer"))),
+            "delay" => sleep = sleep_generator(&function, &duration_generator(&value)),
+            _ => panic!("Unknown option '{option}'. Expected 'attempts = ...' or 'delay = \"...\"'"),
+        }
+    }
+    let attempts = attempts.unwrap_or_else(|| panic!("The number of attempts must be given"));
+    let message = "\"failed after {} attempts{}\", __nuhound_attempt, __nuhound_causes";
+
+    function.with_body(&format!("
+    let mut __nuhound_causes = ::std::string::String::new();
+    let mut __nuhound_attempt = 0;
+    loop {{
+        __nuhound_attempt += 1;
+        let error = match {0} {{
+            ::std::result::Result::Ok(value) => return ::std::result::Result::Ok(value),
+            ::std::result::Result::Err(error) => error,
+        }};
+        let retriable = {4};
+        if __nuhound_attempt >= {1} || !retriable {{
+            {2}
+            trait NuhoundCause {{
+                fn __nuhound_cause(self, inform: ::std::string::String) -> ::nuhound::Nuhound;
+            }}
+            impl NuhoundCause for ::nuhound::Nuhound {{
+                fn __nuhound_cause(self, inform: ::std::string::String) -> ::nuhound::Nuhound {{
+                    ::nuhound::Nuhound::new(inform).caused_by(self)
+                }}
+            }}
+            trait Cause {{
+                fn __nuhound_cause(&self, inform: ::std::string::String) -> ::nuhound::Nuhound;
+            }}
+            impl<E: ::std::error::Error> Cause for E {{
+                fn __nuhound_cause(&self, inform: ::std::string::String) -> ::nuhound::Nuhound {{
+                    ::nuhound::Nuhound::link(inform, self)
+                }}
+            }}
+            return ::std::result::Result::Err(::std::convert::From::from(error.__nuhound_cause(inform)));
+        }}
+        __nuhound_causes.push_str(&format!(\"; attempt {{}}: {{}}\", __nuhound_attempt, error));
+        {3}
+    }}
+    ", function.invoke_body(), attempts, inform_generator(message, None, None).trim(), sleep.trim(),
+        retriable_check_generator("error")))
+}
+
+// The is retriable builder is used to create a macro that determines whether an error is transient,
+// so that application level retry loops may decide whether to try again without matching messages
+fn is_retriable_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+    retriable_check_generator(&format!("({})", attributes[0]))
+}
+
+// The is permanent builder is used to create a macro that determines whether any layer of an error is
+// permanent, so that backoff loops and circuit breakers may stop at once
+fn is_permanent_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+    permanent_check_generator(&format!("({})", attributes[0]))
+}
+
+// The user message builder is used to create a macro that retrieves the message for end users given
+// to the outermost layer of the chain of an error that has one, or None
+fn user_message_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+    metadata_generator(&attributes[0], "user")
+}
+
+// The severity of builder is used to create a macro that retrieves the severity given to the
+// outermost layer of the chain of an error that has one, or None, so that sinks may route errors
+fn severity_of_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+    metadata_generator(&attributes[0], "severity")
+}
+
+// Generate the expression that finds the metadata with the given key of the outermost layer of the
+// chain of the error, which is borrowed rather than moved, that has it, or None
+fn metadata_generator(error: &str, key: &str) -> String {
+    format!("
+    {{
+        let noted = {NOTED};
+        ::std::iter::successors(::std::option::Option::Some(&({error}) as &(dyn ::std::error::Error + 'static)), |layer| ::std::error::Error::source(*layer))
+            .find_map(|layer| noted(layer, \"{key}\"))
+    }}
+    ")
+}
+
+// The report json builder is used to create a macro that serializes every layer of the chain of an
+// error, outermost first, into a JSON array so that it may be sent to another service. Each layer
+// becomes an object with a "msg" member holding its message, which is a JSON object itself when the
+// 'disclose-json' feature is enabled, followed by a member for each of its metadata, such as its
+// "code", "file", "line" and "fields".
+fn report_json_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+
+    format!("
+    {{
+        let escape = {JSON_ESCAPE};
+        let noted = {NOTED};
+        let layers: ::std::vec::Vec<::std::string::String> = ::std::iter::successors(
+            ::std::option::Option::Some(&({0}) as &(dyn ::std::error::Error + 'static)), |layer| ::std::error::Error::source(*layer)
+        )
+            .map(|layer| {{
+                let mut json = format!(\"{{{{\\\"msg\\\":{{}}\", escape(&layer.to_string()));
+                for (key, quoted) in {METADATA} {{
+                    if let ::std::option::Option::Some(value) = noted(layer, key) {{
+                        let value = if quoted {{ escape(&value) }} else {{ value }};
+                        json.push_str(&format!(\",{{}}:{{}}\", escape(key), value));
+                    }}
+                }}
+                json.push('}}');
+                json
+            }})
+            .collect();
+        format!(\"[{{}}]\", layers.join(\",\"))
+    }}
+    ", attributes[0])
+}
+
+// The keys of the metadata that the layers of an error may hold, each paired with whether its value
+// is quoted as a JSON string. The other values, such as line numbers and fields, are JSON as they are.
+const METADATA: &str = "[(\"code\", true), (\"file\", true), (\"line\", false), (\"col\", false), (\"site\", false), \
+    (\"fields\", false), (\"severity\", true), (\"exit\", false), (\"retriable\", false), (\"depth\", false), \
+    (\"user\", true), (\"help\", true), (\"url\", true), (\"snippet\", true), (\"label\", true), (\"function_hash\", true), \
+    (\"time\", false)]";
+
+// The into eyre builder is used to create a macro that converts a Nuhound error into an eyre::Report
+// for codebases migrating between the two. The Nuhound error is held by the report, so that its
+// layers remain reachable through the source method and are displayed by the eyre handler.
+fn into_eyre_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+
+    format!("
+    {{
+        let error: ::nuhound::Nuhound = {0};
+        ::eyre::Report::new(error)
+    }}
+    ", attributes[0])
+}
+
+// The into miette builder is used to create a macro that converts a Nuhound error into a
+// miette::Report so that command line tools may render it as a miette diagnostic.
+fn into_miette_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+
+    format!("
+    {{
+        let error: ::nuhound::Nuhound = {0};
+        let noted = {NOTED};
+        {MIETTE_REPORT}
+    }}
+    ", attributes[0])
+}
+
+// The report chain builder is used to create a macro that re-hydrates a chain serialized by the
+// report json macro into a Nuhound error, or None when the text holds no layers. Each layer is
+// restored from its "msg" member with its other members noted as its metadata.
+fn report_chain_builder(item: String) -> String {
+    let attributes = analyse(item.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("Requires a single expression");
+    }
+
+    format!("
+    {{
+        let json: &str = &({0});
+        let member = {MEMBER};
+        let mut layers: ::std::vec::Vec<::nuhound::Nuhound> = ::std::vec::Vec::new();
+        let (mut depth, mut quoted, mut escaped, mut start) = (0usize, false, false, 0usize);
+        for (index, c) in json.char_indices() {{
+            if quoted {{
+                if escaped {{
+                    escaped = false;
+                }} else if c == '\\\\' {{
+                    escaped = true;
+                }} else if c == '\"' {{
+                    quoted = false;
+                }}
+                continue;
+            }}
+            match c {{
+                '\"' => quoted = true,
+                '{{' => {{
+                    if depth == 0 {{
+                        start = index;
+                    }}
+                    depth += 1;
+                }}
+                '}}' if depth > 0 => {{
+                    depth -= 1;
+                    if depth == 0 {{
+                        let layer = &json[start..=index];
+                        let message = member(layer, \"msg\").unwrap_or_default();
+                        layers.push({METADATA}.into_iter()
+                            .filter_map(|(key, _)| member(layer, key).map(|value| (key, value)))
+                            .fold(::nuhound::Nuhound::new(message), |error, (key, value)| error.note(key, value)));
+                    }}
+                }}
+                _ => {{}}
+            }}
+        }}
+        let mut layers = layers.into_iter().rev();
+        layers.next().map(|innermost| layers.fold(innermost, |cause, layer| layer.caused_by(cause)))
+    }}
+    ", attributes[0])
+}
+
+// The map err builder is used to create an attribute macro for functions that use Nuhound errors
+// internally but return a typed error publicly. The return type of the function is rewritten to
+// use the typed error and any Nuhound error escaping from the body is converted into it, either
+// with From or with a given constructor.
+fn map_err_builder(attribute: String, item: String) -> String {
+    let attributes = analyse(attribute.chars());
+    if attributes[0].is_empty() {
+        panic!("The error type must be given");
+    }
+    let error_type = &attributes[0];
+    let mut constructor = format!("<{error_type} as ::std::convert::From<::nuhound::Nuhound>>::from");
+    for (option, value) in analyse_options(&attributes[1..].join(", ")) {
+        match option.as_str() {
+            "with" => constructor = value,
+            _ => panic!("Unknown option '{option}'. Expected 'with = ...'"),
+        }
+    }
+    let function = Function::parse(&item);
+    let success = function.success_type()
+        .unwrap_or_else(|| panic!("Function '{}' must return a Report or Result", function.name));
+    let internal = Function {
+        output: Some(format!("::nuhound::Report<{success}>")),
+        ..Function::parse(&item)
+    };
+    let public = Function {
+        output: Some(format!("::std::result::Result<{success}, {error_type}>")),
+        ..function
+    };
+
+    public.with_body(&format!("
+    {0}.map_err({1})
+    ", internal.invoke_body(), constructor))
+}
+
+// The deadline builder is used to create an attribute macro that fails a function with a Nuhound
+// error when it runs for longer than the given duration. The body of an async function is raced
+// against a timer provided by the 'tokio' or 'async-std' feature. An ordinary function can't be
+// interrupted, so its duration is checked once the body has finished and any error from the body
+// becomes the cause of the deadline error.
+fn deadline_builder(attribute: String, item: String) -> String {
+    let attributes = analyse(attribute.chars());
+    if attributes.len() != 1 || attributes[0].is_empty() {
+        panic!("The deadline attribute requires a duration such as \"5s\"");
+    }
+    let deadline = duration_generator(&attributes[0]);
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let message = format!("\"deadline exceeded after {{}} in {{}}::{{}}\", {}, module_path!(), \"{}\"",
+        attributes[0], function.name);
+
+    let body = if function.is_async() {
+        format!("
+    let future = {0};
+    #[cfg(feature = \"tokio\")]
+    let outcome = ::tokio::time::timeout({1}, future).await;
+    #[cfg(all(feature = \"async-std\", not(feature = \"tokio\")))]
+    let outcome = ::async_std::future::timeout({1}, future).await;
+    #[cfg(not(any(feature = \"tokio\", feature = \"async-std\")))]
+    let outcome: ::std::result::Result<_, ()> = {{
+        ::std::compile_error!(\"Async functions require either the 'tokio' or the 'async-std' feature\");
+        ::std::result::Result::Ok(future.await)
+    }};
+    match outcome {{
+        ::std::result::Result::Ok(outcome) => outcome,
+        ::std::result::Result::Err(_) => {2},
+    }}
+    ", function.future_body(), deadline, custom_generator(&message).trim())
+    } else {
+        format!("
+    let __nuhound_start = ::std::time::Instant::now();
+    let outcome = {0};
+    if __nuhound_start.elapsed() <= {1} {{
+        return outcome;
+    }}
+    {2}
+    let error = ::nuhound::Nuhound::new(inform);
+    match outcome {{
+        ::std::result::Result::Ok(_) => ::std::result::Result::Err(error),
+        ::std::result::Result::Err(cause) => ::std::result::Result::Err(error.caused_by(cause)),
+    }}
+    ", function.invoke_body(), deadline, inform_generator(&message, None, None).trim())
+    };
+    function.with_body(&body)
+}
+
+// The catch panics builder is used to create an attribute macro that converts a panic within the
+// body of a function into a Nuhound error. The body of an ordinary function is run by catch_unwind
+// and so must be UnwindSafe unless the 'assert_unwind_safe' option is given. The future of an async
+// function is polled within catch_unwind, which can only be done by asserting that it is safe.
+fn catch_panics_builder(attribute: String, item: String) -> String {
+    let mut assert_unwind_safe = false;
+    for (option, _) in analyse_options(&attribute) {
+        match option.as_str() {
+            "assert_unwind_safe" => assert_unwind_safe = true,
+            _ => panic!("Unknown option '{option}'. Expected 'assert_unwind_safe'"),
+        }
+    }
+    let function = Function::parse(&item);
+    let output = function.output.as_deref()
+        .unwrap_or_else(|| panic!("Function '{}' must return a Report", function.name));
+
+    let outcome = if function.is_async() {
+        format!("
+    let mut future = ::std::pin::pin!({0});
+    let outcome = ::std::future::poll_fn(|context| {{
+        match ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {{
+            ::std::future::Future::poll(future.as_mut(), context)
+        }})) {{
+            ::std::result::Result::Ok(poll) => poll.map(::std::result::Result::Ok),
+            ::std::result::Result::Err(payload) => ::std::task::Poll::Ready(::std::result::Result::Err(payload)),
+        }}
+    }}).await;
+    ", function.future_body())
+    } else if assert_unwind_safe {
+        format!("
+    let outcome = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| -> {0} {{ {1} }}));
+    ", output, function.body)
+    } else {
+        format!("
+    let outcome = ::std::panic::catch_unwind(|| -> {0} {{ {1} }});
+    ", output, function.body)
+    };
+
+    function.with_body(&format!("
+    {0}
+    match outcome {{
+        ::std::result::Result::Ok(outcome) => outcome,
+        ::std::result::Result::Err(payload) => {{
+            let panic = if let ::std::option::Option::Some(message) = payload.downcast_ref::<&str>() {{
+                message.to_string()
+            }} else if let ::std::option::Option::Some(message) = payload.downcast_ref::<::std::string::String>() {{
+                message.clone()
+            }} else {{
+                \"unknown panic payload\".to_string()
+            }};
+            {1}
+        }}
+    }}
+    ", outcome.trim(), custom_generator(&format!("\"panicked in {{}}::{{}}: {{}}\", module_path!(), \"{}\", panic", function.name)).trim()))
+}
+
+// The report builder is used to create an attribute macro for impl blocks that applies context
+// wrapping to a single method. Any Nuhound error escaping from the method gains a further layer
+// naming the type and method. Methods that don't return a Report, or that are skipped, are left
+// untouched and None is returned.
+fn report_builder(skip: &[String], item: String) -> Option<String> {
+    let function = Function::parse(&item);
+    if !function.returns_report() || skip.contains(&function.name) {
+        return None;
+    }
+    let message = format!("\"{{}}::{{}}\", ::std::any::type_name::<Self>(), \"{}\"", function.name);
+    Some(function.with_body(&format!("
+    use ::nuhound::ResultExtension as _;
+    {0}
+    ", examine_generator(&function.invoke_body(), &message, None).trim())))
+}
+
+// The timed builder is used to create an attribute macro that adds a layer to any Nuhound error
+// escaping from a function stating how long the function ran before it failed.
+fn timed_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The timed attribute does not take any parameters");
+    }
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let message = format!("\"{{}} failed after {{:.1?}}\", \"{}\", __nuhound_start.elapsed()", function.name);
+
+    function.with_body(&format!("
+    use ::nuhound::ResultExtension as _;
+    let __nuhound_start = ::std::time::Instant::now();
+    {0}
+    ", examine_generator(&function.invoke_body(), &message, None).trim()))
+}
+
+// The ensure builder is used to create an attribute macro that checks a precondition before the
+// body of a function runs, returning a Nuhound error when it doesn't hold. The attribute macro
+// nearest the function is expanded last, so the first ensure attribute also takes the checks of
+// those that immediately follow it in order to make them run in the order they were written.
+fn ensure_builder(attribute: String, item: String) -> String {
+    let mut function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let mut conditions = vec![attribute];
+    while let Some(arguments) = function.attributes.first().and_then(|attribute| ensure_arguments(attribute)) {
+        conditions.push(arguments);
+        function.attributes.remove(0);
+    }
+
+    let mut checks = String::new();
+    for condition in conditions {
+        let attributes = analyse(condition.chars());
+        if attributes[0].is_empty() {
+            panic!("The ensure attribute requires a condition");
+        }
+        let message = if attributes.len() > 1 {
+            attributes[1..].join(", ")
+        } else {
+            format!("\"precondition failed: {{}}\", {:?}", attributes[0])
+        };
+        checks.push_str(&format!("
+    if !({0}) {{
+        return {1};
+    }}", attributes[0], custom_generator(&message).trim()));
+    }
+    function.with_body(&format!("{checks}\n    {BODY}"))
+}
+
+// Return the arguments of an ensure attribute given its text, or None for any other attribute
+fn ensure_arguments(attribute: &str) -> Option<String> {
+    let inner = attribute.strip_prefix('#')?.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let open = inner.find('(')?;
+    let path: String = inner[..open].split_whitespace().collect();
+    if !["ensure", "nuhound::ensure", "::nuhound::ensure"].contains(&path.as_str()) {
+        return None;
+    }
+    let arguments = inner[open + 1..].trim_end().strip_suffix(')')?;
+    Some(flags::Flags::split(arguments).1.to_string())
+}
+
+// The fallback builder is used to create an attribute macro for best-effort functions. The return
+// type of the function is rewritten to its success type and, when the body fails, the error is
+// recorded and the fallback expression is returned instead. The error is passed to the sink when
+// one is given, either as an option or by the sink setting in nuhound.toml, otherwise it is logged
+// using the 'tracing-events' or 'log' feature or written to stderr.
+fn fallback_builder(attribute: String, item: String) -> String {
+    let attributes = analyse(attribute.chars());
+    if attributes[0].is_empty() {
+        panic!("The fallback attribute requires an expression");
+    }
+    let mut sink = config::setting("sink");
+    for (option, value) in analyse_options(&attributes[1..].join(", ")) {
+        match option.as_str() {
+            "sink" => sink = Some(value),
+            _ => panic!("Unknown option '{option}'. Expected 'sink = ...'"),
+        }
+    }
+    let function = Function::parse(&item);
+    let success = function.success_type()
+        .unwrap_or_else(|| panic!("Function '{}' must return a Report", function.name));
+    let public = Function {
+        output: Some(success),
+        ..Function::parse(&item)
+    };
+
+    let record = match sink {
+        Some(sink) => format!("{sink}(&error);"),
+        None => format!("
+            {2}
+            {1}
+            #[cfg(not(any(feature = \"tracing-events\", feature = \"log\")))]
+            eprintln!(\"Warning: {{}}::{{}} failed, using the fallback: {{}}\", module_path!(), \"{0}\", report);
+        ", function.name, log_generator("warn", &format!("\"{{}}::{{}} failed, using the fallback: {{}}\", module_path!(), \"{}\", report", function.name)).trim(), report_generator().trim()),
+    };
+
+    public.with_body(&format!("
+    match {0} {{
+        ::std::result::Result::Ok(value) => value,
+        ::std::result::Result::Err(error) => {{
+            {1}
+            {2}
+        }}
+    }}
+    ", function.invoke_body(), record.trim(), attributes[0]))
+}
+
+// Generate the statement that describes the error in a variable named 'report'. This is the message
+// of the error unless the 'disclose' feature is enabled, in which case it is the full trace.
+fn report_generator() -> String {
+    disclose_generator("let report = error.to_string();", "let report = error.trace();")
+}
+
+// Generate the statements that log the message, formed from the comma separated format arguments,
+// at the given level using the 'tracing-events' feature or, failing that, the 'log' feature.
+fn log_generator(level: &str, message: &str) -> String {
+    format!("
+        #[cfg(feature = \"tracing-events\")]
+        ::tracing::{0}!({1});
+        #[cfg(all(feature = \"log\", not(feature = \"tracing-events\")))]
+        ::log::{0}!({1});
+    ", level, message)
+}
+
+// The trace calls builder is used to create an attribute macro that logs entry to and exit from a
+// function, optionally with the values of its parameters. When the function exits with an error
+// the exit record, which is logged as a warning, includes the trace of the error.
+fn trace_calls_builder(attribute: String, item: String) -> String {
+    let function = Function::parse(&item);
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+    let mut args = false;
+    let mut skip = Vec::new();
+    let mut level = String::from("debug");
+    for (option, value) in analyse_options(&attribute) {
+        match option.as_str() {
+            "args" => args = true,
+            "skip" => skip.extend(analyse(value.chars())),
+            "level" => level = value.trim_matches('"').to_string(),
+            _ => panic!("Unknown option '{option}'. Expected 'args', 'skip(...)' or 'level = \"...\"'"),
+        }
+    }
+    if !["trace", "debug", "info", "warn", "error"].contains(&level.as_str()) {
+        panic!("Unknown level '{level}'. Expected 'trace', 'debug', 'info', 'warn' or 'error'");
+    }
+    let name = format!("\"{}\"", function.name);
+    let call = if args {
+        call_generator(&function, &name, &skip, false)
+    } else {
+        format!("format!(\"{{}}::{{}}\", module_path!(), {name})")
+    };
+
+    function.with_body(&format!("
+    #[cfg(not(any(feature = \"tracing-events\", feature = \"log\")))]
+    ::std::compile_error!(\"The trace_calls attribute requires either the 'tracing-events' or the 'log' feature\");
+    let __nuhound_call = {0};
+    {1}
+    let outcome = {2};
+    match &outcome {{
+        ::std::result::Result::Ok(_) => {{
+            {3}
+        }}
+        ::std::result::Result::Err(error) => {{
+            {5}
+            {4}
+        }}
+    }}
+    outcome
+    ", call,
+        log_generator(&level, "\"enter {}\", __nuhound_call").trim(),
+        function.invoke_body(),
+        log_generator(&level, "\"exit {}\", __nuhound_call").trim(),
+        log_generator("warn", "\"exit {} with error:\\n{}\", __nuhound_call, report").trim(),
+        report_generator().trim()))
+}
+
+// Apply the report builder to each method of an impl block
+fn report_impl(attribute: &str, item: TokenStream) -> TokenStream {
+    let mut skip = Vec::new();
+    for (option, value) in analyse_options(attribute) {
+        match option.as_str() {
+            "skip" => skip.extend(analyse(value.chars())),
+            _ => panic!("Unknown option '{option}'. Expected 'skip(...)'"),
+        }
+    }
+    let mut trees: Vec<TokenTree> = item.into_iter().collect();
+    let is_impl = trees.iter().any(|tree| matches!(tree, TokenTree::Ident(ident) if ident.to_string() == "impl"));
+    let block = match trees.pop() {
+        Some(TokenTree::Group(group)) if is_impl && group.delimiter() == Delimiter::Brace => group,
+        _ => panic!("The report attribute may only be applied to an impl block"),
+    };
+    let methods = rewrite::rewrite_methods(block.stream(), &|method, span| {
+        let (item, body) = detach_body(method.clone());
+        match report_builder(&skip, item) {
+            Some(generated) => splice_body(rewrite::respan(generated.parse().unwrap(), span), &body),
+            None => method,
+        }
+    });
+    let mut rewritten = Group::new(Delimiter::Brace, methods);
+    rewritten.set_span(block.span());
+    trees.push(TokenTree::Group(rewritten));
+    TokenStream::from_iter(trees)
+}
+
+// The track caller builder is used to create an attribute macro for helper functions. The function
+// is marked with #[track_caller] and the location of its caller is captured before the body runs so
+// that the nuhound macros within the body, which are given the @caller flag, can report it.
+fn track_caller_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The track_caller attribute does not take any parameters");
+    }
+    let mut function = Function::parse(&item);
+    if function.is_async() {
+        panic!("Function '{}' must not be async", function.name);
+    }
+    function.attributes.push(String::from("#[track_caller]"));
+
+    function.with_body(&format!("
+    let __nuhound_caller = ::std::panic::Location::caller();
+    {BODY}
+    "))
+}
+
+// The catalog builder is used to create an attribute macro that registers a function as the catalog
+// of the templates of localized messages. The function is kept as it is written and a hidden
+// function is added alongside it under the name that the localized messages look it up by, which
+// is why the catalog must be registered in the root module of the crate.
+fn catalog_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The catalog attribute does not take any parameters");
+    }
+    let function = Function::parse(&item);
+    if function.is_async() {
+        panic!("Function '{}' must not be async", function.name);
+    }
+    if function.output.is_none() {
+        panic!("Function '{}' must return the template of the key as an Option", function.name);
+    }
+
+    format!("
+    {item}
+
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub(crate) fn __nuhound_catalog(key: &str) -> ::std::option::Option<::std::string::String> {{
+        {0}(key).map(::std::convert::Into::into)
+    }}
+    ", function.name)
+}
+
+// The async context builder is used to create an attribute macro that makes every await within an
+// async function whose result is passed to a question mark operator add a layer to the error naming
+// the awaited future. The body is rewritten at the token level, as for the trace all builder.
+fn async_context_builder(attribute: String, item: String) -> String {
+    if !attribute.trim().is_empty() {
+        panic!("The async_context attribute does not take any parameters");
+    }
+    let function = Function::parse(&item);
+    if !function.is_async() {
+        panic!("Function '{}' must be async", function.name);
+    }
+    if function.output.is_none() {
+        panic!("Function '{}' must return a Report", function.name);
+    }
+
+    function.with_body(&format!("
+    use ::nuhound::ResultExtension as _;
+    {0}
+    ", function.body))
+}
+
+// The site counters builder declares the array of the counters of the sites recorded so far for the
+// crate, which the statements generated by count_generator increment
+fn site_counters_builder() -> String {
+    let count = registry::sites().len();
+    format!("
+    #[doc(hidden)]
+    #[allow(dead_code)]
+    pub(crate) static __NUHOUND_SITE_COUNTS: [::std::sync::atomic::AtomicU64; {count}] =
+        [const {{ ::std::sync::atomic::AtomicU64::new(0) }}; {count}];
+    ")
+}
+
+// The nuhound sites builder pairs the number, file and line of each site recorded so far for the
+// crate with the value of its counter
+fn nuhound_sites_builder() -> String {
+    let sites = registry::locations();
+    format!("
+    {{
+        let sites: [(usize, &'static str, usize); {0}] = [{1}];
+        sites.into_iter()
+            .zip(crate::__NUHOUND_SITE_COUNTS.iter())
+            .map(|((site, file, line), count)| (site, file, line, count.load(::std::sync::atomic::Ordering::Relaxed)))
+    }}
+    ", sites.len(), sites.join(", "))
+}
+
+// Return the error code given to a custom!, convert! or examine! macro from the text of its input, or None
+// when it doesn't have one. This is used by the public api attribute to check each site.
+fn public_api_code(input: &str) -> Option<String> {
+    let (_, input) = flags::Flags::split(input);
+    let (_, named) = split_named(analyse(input.chars()), &NAMED);
+    named.into_iter().find(|(name, _)| name == "code").map(|(_, code)| code)
+}
+
+// Return the value of the option with the given name from the nuhound attributes, such as the code
+// of #[nuhound(code = "E100")]
+fn nuhound_option(attributes: &[String], name: &str) -> Option<String> {
+    data::find_attributes(attributes, "nuhound").iter()
+        .flat_map(|options| analyse_options(options))
+        .find(|(option, _)| option == name)
+        .map(|(_, value)| value)
+}
+
+// The into nuhound builder is used to create a derive macro that converts an existing error type
+// into a Nuhound error using its Display output, prefixed with the code of the variant when one is
+// given. The inherent link method allows the convert macro to use the conversion. When the type is
+// given the #[nuhound(chain)] attribute the source of the error, such as a field marked #[source] or
+// #[from] by thiserror, is linked beneath it so that each error of the chain becomes a layer.
+fn into_nuhound_builder(data: &Data) -> String {
+    if nuhound_option(&data.attributes, "chain").is_some() {
+        return conversion_generator(data, &source_link);
+    }
+    conversion_generator(data, &|message| format!("::nuhound::Nuhound::new({message})"))
+}
+
+// Return the expression that creates a Nuhound error from the expression of the message, linking the
+// source of the error in the variable 'error' when it has one
+fn source_link(message: &str) -> String {
+    format!(
+        "match ::std::error::Error::source(&error) {{ \
+            ::std::option::Option::Some(source) => ::nuhound::Nuhound::link({message}, source), \
+            ::std::option::Option::None => ::nuhound::Nuhound::new({message}), \
+        }}")
+}
+
+// Create the conversion of a derived error type into a Nuhound error. The construct closure turns
+// the expression of the message into the expression of the Nuhound error.
+fn conversion_generator(data: &Data, construct: &dyn Fn(&str) -> String) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let arms: Vec<String> = data.variants.iter()
+        .map(|variant| {
+            let code = match nuhound_option(&variant.attributes, "code") {
+                Some(code) => format!("::std::option::Option::Some({code})"),
+                None => String::from("::std::option::Option::None"),
+            };
+            format!("{} => {},", variant.wildcard(&data.path(variant)), code)
+        })
+        .collect();
+
+    format!("
+    impl{0} ::std::convert::From<{1}> for ::nuhound::Nuhound {2} {{
+        fn from(error: {1}) -> Self {{
+            let code: ::std::option::Option<&'static str> = match &error {{
+                {3}
+            }};
+            match code {{
+                ::std::option::Option::Some(code) => ({4}).note(\"code\", code),
+                ::std::option::Option::None => {5},
+            }}
+        }}
+    }}
+
+    impl{0} {1} {2} {{
+        #[doc(hidden)]
+        pub fn __nuhound_link(self, inform: ::std::string::String, _code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {{
+            ::nuhound::Nuhound::new(inform).caused_by(::nuhound::Nuhound::from(self))
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, arms.join("\n                "),
+        construct("format!(\"[{}] {}\", code, error)"), construct("error.to_string()"))
+}
+
+// Determine whether the type is a trait object held by a pointer, such as `Box<dyn Error + Send +
+// Sync>`, which doesn't implement Error itself and so is given as a source by the object it holds
+fn holds_trait_object(ty: &str) -> bool {
+    let ty: String = ty.chars().filter(|c| !c.is_whitespace()).collect();
+    let Some((pointer, inner)) = ty.split_once('<') else {
+        return false;
+    };
+    let pointer = pointer.rsplit("::").next().unwrap_or_default();
+    ["Box", "Arc", "Rc"].contains(&pointer) && inner.starts_with("dyn")
+}
+
+// The nuhound error builder is used to create a derive macro that implements Display, Error, From
+// for the source types marked with #[nuhound(from)] and the conversion into a Nuhound error. The
+// conversion links the source of the error so that its messages are disclosed in the trace.
+fn nuhound_error_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let mut display_arms = Vec::new();
+    let mut source_arms = Vec::new();
+    let mut from_impls = String::new();
+    for variant in &data.variants {
+        let path = data.path(variant);
+        let display = nuhound_option(&variant.attributes, "display").unwrap_or_else(|| {
+            panic!("'{}' must have a #[nuhound(display = \"...\")] attribute", variant.name)
+        });
+        display_arms.push(format!("{} => write!(f, {}),", variant.pattern(&path), display_format(&display)));
+
+        let fields = variant.fields();
+        let source = fields.iter().find(|field| {
+            nuhound_option(&field.attributes, "from").is_some()
+                || nuhound_option(&field.attributes, "source").is_some()
+                || field.name == "source"
+        });
+        match source {
+            Some(field) => source_arms.push(format!(
+                "{} => ::std::option::Option::Some({}{} as &(dyn ::std::error::Error + 'static)),",
+                variant.pattern(&path), if holds_trait_object(&field.ty) { "&**" } else { "" }, field.binding())),
+            None => source_arms.push(format!("{} => ::std::option::Option::None,", variant.wildcard(&path))),
+        }
+
+        if let Some(field) = fields.iter().find(|field| nuhound_option(&field.attributes, "from").is_some()) {
+            if fields.len() != 1 {
+                panic!("'{}' must have a single field to use #[nuhound(from)]", variant.name);
+            }
+            let construct = match variant.fields {
+                data::Fields::Named(_) => format!("{path} {{ {}: source }}", field.name),
+                _ => format!("{path}(source)"),
+            };
+            from_impls.push_str(&format!("
+    impl{0} ::std::convert::From<{1}> for {2} {3} {{
+        fn from(source: {1}) -> Self {{
+            {4}
+        }}
+    }}
+    ", data.generics, field.ty, ty, data.where_clause, construct));
+        }
+    }
+
+    let conversion = conversion_generator(data, &source_link);
+
+    format!("
+    impl{0} ::std::fmt::Display for {1} {2} {{
+        #[allow(unused_variables)]
+        fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {{
+            match self {{
+                {3}
+            }}
+        }}
+    }}
+
+    impl{0} ::std::error::Error for {1} {2} {{
+        #[allow(unused_variables)]
+        fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> {{
+            match self {{
+                {4}
+            }}
+        }}
+    }}
+    {5}{6}", data.generics, ty, data.where_clause, display_arms.join("\n                "),
+        source_arms.join("\n                "), from_impls, conversion)
+}
+
+// The error code builder is used to create a derive macro that maps each variant to the stable code
+// given by its #[code = "..."] attribute. The inherent code method used by the convert macro allows
+// the code to be picked up automatically when linking the error.
+fn error_code_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let mut codes: Vec<String> = Vec::new();
+    let mut code_arms = Vec::new();
+    let mut parse_arms = Vec::new();
+    for variant in &data.variants {
+        let code = data::find_attributes(&variant.attributes, "code").pop().unwrap_or_else(|| {
+            panic!("'{}' must have a #[code = \"...\"] attribute", variant.name)
+        });
+        if !code.starts_with('"') {
+            panic!("The code '{code}' must be a string literal");
+        }
+        if codes.contains(&code) {
+            panic!("The code {code} is used by more than one variant");
+        }
+        let path = data.path(variant);
+        code_arms.push(format!("{} => {},", variant.wildcard(&path), code));
+        parse_arms.push(match variant.fields {
+            data::Fields::Unit => format!("{code} => ::std::result::Result::Ok({path}),"),
+            _ => format!("{code} => ::std::result::Result::Err(::nuhound::Nuhound::new(format!(\"the error code '{{}}' belongs to a variant with fields\", code))),"),
+        });
+        codes.push(code);
+    }
+
+    format!("
+    impl{0} {1} {2} {{
+        /// The codes of all the variants of the error
+        pub const CODES: &'static [&'static str] = &[{3}];
+
+        /// Return the stable code of the error
+        pub fn code(&self) -> &'static str {{
+            match self {{
+                {4}
+            }}
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_code(&self) -> ::std::option::Option<&'static str> {{
+            ::std::option::Option::Some(self.code())
+        }}
+    }}
+
+    impl{0} ::std::str::FromStr for {1} {2} {{
+        type Err = ::nuhound::Nuhound;
+
+        fn from_str(code: &str) -> ::std::result::Result<Self, Self::Err> {{
+            match code {{
+                {5}
+                _ => ::std::result::Result::Err(::nuhound::Nuhound::new(format!(\"unknown error code '{{}}'\", code))),
+            }}
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, codes.join(", "), code_arms.join("\n                "),
+        parse_arms.join("\n                "))
+}
+
+// The diagnostic builder is used to create a derive macro that attaches help text and a documentation
+// url to each variant given by its #[nuhound(help = "...", url = "...")] attribute. The inherent
+// methods used by the convert macro render them as "help:" and "see:" lines.
+fn diagnostic_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let option = |variant: &data::Variant, name: &str| match nuhound_option(&variant.attributes, name) {
+        Some(value) if !value.starts_with('"') => panic!("The {name} '{value}' must be a string literal"),
+        Some(value) => format!("::std::option::Option::Some({value})"),
+        None => String::from("::std::option::Option::None"),
+    };
+    let help_arms: Vec<String> = data.variants.iter()
+        .map(|variant| format!("{} => {},", variant.wildcard(&data.path(variant)), option(variant, "help")))
+        .collect();
+    let url_arms: Vec<String> = data.variants.iter()
+        .map(|variant| format!("{} => {},", variant.wildcard(&data.path(variant)), option(variant, "url")))
+        .collect();
+
+    format!("
+    impl{0} {1} {2} {{
+        /// Return the help text of the error
+        pub fn help(&self) -> ::std::option::Option<&'static str> {{
+            match self {{
+                {3}
+            }}
+        }}
+
+        /// Return the url of the documentation of the error
+        pub fn url(&self) -> ::std::option::Option<&'static str> {{
+            match self {{
+                {4}
+            }}
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_help(&self) -> ::std::option::Option<&'static str> {{
+            self.help()
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_url(&self) -> ::std::option::Option<&'static str> {{
+            self.url()
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, help_arms.join("\n                "), url_arms.join("\n                "))
+}
+
+// The context fields builder is used to create a derive macro for a struct that renders its fields as
+// key=value pairs, which the macros append to the message of an error given a `ctx = ...` argument.
+// Fields marked with #[context(skip)] are left out and those marked with #[context(redact)] are
+// shown without their value.
+fn context_fields_builder(data: &Data) -> String {
+    if data.is_enum {
+        panic!("ContextFields may only be derived for a struct");
+    }
+    let pairs: Vec<String> = data.variants.iter()
+        .flat_map(|variant| variant.fields())
+        .filter_map(|field| {
+            let options: Vec<String> = data::find_attributes(&field.attributes, "context").iter()
+                .flat_map(|options| analyse_options(options))
+                .map(|(option, _)| option)
+                .collect();
+            if options.iter().any(|option| option == "skip") {
+                None
+            } else if options.iter().any(|option| option == "redact") {
+                Some(format!("(\"{}\", ::std::string::String::from(\"[redacted]\")),", field.name))
+            } else {
+                Some(format!("(\"{0}\", format!(\"{{:?}}\", self.{0})),", field.name))
+            }
+        })
+        .collect();
+
+    format!("
+    impl{0} {1}{2} {3} {{
+        /// Return the fields of the context as key and value pairs
+        pub fn context_fields(&self) -> ::std::vec::Vec<(&'static str, ::std::string::String)> {{
+            ::std::vec![
+                {4}
+            ]
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_context(&self) -> ::std::string::String {{
+            self.context_fields().iter()
+                .map(|(key, value)| format!(\"{{}}={{}}\", key, value))
+                .collect::<::std::vec::Vec<_>>()
+                .join(\", \")
+        }}
+    }}
+    ", data.generics, data.name, data.type_generics(), data.where_clause, pairs.join("\n                "))
+}
+
+// The severity builder is used to create a derive macro that assigns a level to each variant given
+// by its #[severity(...)] attribute, defaulting to error. The inherent severity method used by the
+// convert macro logs the message of the linked error at that level so that sinks may route by level.
+fn severity_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let arms: Vec<String> = data.variants.iter()
+        .map(|variant| {
+            let level = data::find_attributes(&variant.attributes, "severity").pop()
+                .unwrap_or_else(|| String::from("error"));
+            if !LEVELS.contains(&level.as_str()) {
+                panic!("Unknown severity '{level}'. Expected 'trace', 'debug', 'info', 'warn', 'error' or 'fatal'");
+            }
+            format!("{} => \"{}\",", variant.wildcard(&data.path(variant)), level)
+        })
+        .collect();
+    let log_arms: Vec<String> = LEVELS.iter()
+        .map(|&level| {
+            let logged = if level == "fatal" { "error" } else { level };
+            format!("\"{level}\" => {{ {} }}", log_generator(logged, "\"{}\", inform").trim())
+        })
+        .collect();
+
+    format!("
+    impl{0} {1} {2} {{
+        /// Return the severity of the error, which is one of trace, debug, info, warn, error or fatal
+        pub fn severity(&self) -> &'static str {{
+            match self {{
+                {3}
+            }}
+        }}
+
+        #[doc(hidden)]
+        #[allow(unused_variables)]
+        pub fn __nuhound_severity(&self, inform: &str) -> ::std::option::Option<&'static str> {{
+            match self.severity() {{
+                {4}
+                _ => (),
+            }}
+            ::std::option::Option::Some(self.severity())
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, arms.join("\n                "), log_arms.join("\n                "))
+}
+
+// The from nuhound builder is used to create a derive macro that reconstructs an error from a Nuhound
+// error by searching its chain for the outermost layer whose "code" metadata is the code of one of
+// the variants, given by either #[nuhound(code = ...)] or #[code = ...] attributes. A variant with
+// fields can't be rebuilt from the chain and so its code ends the search without reconstructing it.
+fn from_nuhound_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let generics = match data.generics.strip_prefix('<') {
+        Some(generics) => format!("<'__nuhound, {generics}"),
+        None => String::from("<'__nuhound>"),
+    };
+    let arms: Vec<String> = data.variants.iter()
+        .filter_map(|variant| {
+            let code = nuhound_option(&variant.attributes, "code")
+                .or_else(|| data::find_attributes(&variant.attributes, "code").pop())?;
+            if !code.starts_with('"') || !code.ends_with('"') {
+                panic!("The code '{code}' must be a string literal");
+            }
+            Some(match variant.fields {
+                data::Fields::Unit => format!("::std::option::Option::Some({code}) => return ::std::result::Result::Ok({}),",
+                    data.path(variant)),
+                _ => format!("::std::option::Option::Some({code}) => return ::std::result::Result::Err(error),"),
+            })
+        })
+        .collect();
+
+    format!("
+    impl{0} ::std::convert::TryFrom<&'__nuhound ::nuhound::Nuhound> for {1} {2} {{
+        type Error = &'__nuhound ::nuhound::Nuhound;
+
+        fn try_from(error: &'__nuhound ::nuhound::Nuhound) -> ::std::result::Result<Self, Self::Error> {{
+            let noted = {NOTED};
+            let mut layer: ::std::option::Option<&(dyn ::std::error::Error + 'static)> = ::std::option::Option::Some(error);
+            while let ::std::option::Option::Some(current) = layer {{
+                match noted(current, \"code\").as_deref() {{
+                    {3}
+                    _ => layer = current.source(),
+                }}
+            }}
+            ::std::result::Result::Err(error)
+        }}
+    }}
+    ", generics, ty, data.where_clause, arms.join("\n                    "))
+}
+
+// The retriable builder is used to create a derive macro that classifies the variants marked with
+// #[retriable] as transient and the others as permanent. The inherent method is consulted by the
+// retry attribute.
+fn retriable_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let arms: Vec<String> = data.variants.iter()
+        .map(|variant| {
+            let retriable = !data::find_attributes(&variant.attributes, "retriable").is_empty();
+            format!("{} => {},", variant.wildcard(&data.path(variant)), retriable)
+        })
+        .collect();
+
+    format!("
+    impl{0} {1} {2} {{
+        /// Determine whether the error is transient, in which case the operation may be retried
+        pub fn is_retriable(&self) -> bool {{
+            match self {{
+                {3}
+            }}
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_retriable(&self) -> bool {{
+            self.is_retriable()
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_permanent(&self) -> bool {{
+            !self.is_retriable()
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, arms.join("\n                "))
+}
+
+// The wrap error builder is used to create a derive macro for a struct that wraps a single inner
+// error. The struct is transparent, using the Display output and source of the inner error, so that
+// the chain of the inner error is preserved when it is linked by the convert macro.
+fn wrap_error_builder(data: &Data) -> String {
+    let fields = match data.variants.first() {
+        Some(variant) if !data.is_enum => variant.fields(),
+        _ => panic!("WrapError may only be derived for a struct"),
+    };
+    let [field] = fields else {
+        panic!("'{}' must have a single field containing the inner error", data.name);
+    };
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let construct = match data.variants[0].fields {
+        data::Fields::Named(_) => format!("Self {{ {}: inner }}", field.name),
+        _ => String::from("Self(inner)"),
+    };
+
+    format!("
+    impl{0} ::std::fmt::Display for {1} {2} {{
+        fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {{
+            ::std::fmt::Display::fmt(&self.{3}, f)
+        }}
+    }}
+
+    impl{0} ::std::error::Error for {1} {2} {{
+        fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> {{
+            ::std::error::Error::source(&self.{3})
+        }}
+    }}
+
+    impl{0} ::std::convert::From<{4}> for {1} {2} {{
+        fn from(inner: {4}) -> Self {{
+            {5}
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, field.name, field.ty, construct)
+}
+
+// The exit code builder is used to create a derive macro that maps each variant to the process exit
+// code given by its #[exit(...)] attribute. The inherent exit method used by the convert macro notes
+// the exit code in the chain so that the main attribute can find it.
+fn exit_code_builder(data: &Data) -> String {
+    let ty = format!("{}{}", data.name, data.type_generics());
+    let arms: Vec<String> = data.variants.iter()
+        .map(|variant| {
+            let exit = data::find_attributes(&variant.attributes, "exit").pop()
+                .unwrap_or_else(|| panic!("'{}' must have an #[exit(...)] attribute", variant.name));
+            if exit.parse::<i32>().is_err() {
+                panic!("The exit code '{exit}' must be an integer");
+            }
+            format!("{} => {},", variant.wildcard(&data.path(variant)), exit)
+        })
+        .collect();
+
+    format!("
+    impl{0} {1} {2} {{
+        /// Return the exit code of the process when the error ends it
+        pub fn exit_code(&self) -> i32 {{
+            match self {{
+                {3}
+            }}
+        }}
+
+        #[doc(hidden)]
+        pub fn __nuhound_exit(&self) -> ::std::option::Option<i32> {{
+            ::std::option::Option::Some(self.exit_code())
+        }}
+    }}
+    ", data.generics, ty, data.where_clause, arms.join("\n                "))
+}
+
+// Rewrite the positional placeholders of a display format, such as {0} or {1:?}, to refer to the
+// bindings of the tuple fields, such as {field0} or {field1:?}, so that they are captured by name
+fn display_format(display: &str) -> String {
+    let mut format = String::new();
+    let mut chars = display.chars().peekable();
+    while let Some(c) = chars.next() {
+        format.push(c);
+        if c == '{' {
+            match chars.peek() {
+                Some('{') => format.push(chars.next().unwrap()),
+                Some(c) if c.is_ascii_digit() => format.push_str("field"),
+                _ => (),
+            }
+        }
+    }
+    format
+}
+
+//  convert macro
+/// A macro to prepare a `Nuhound` type error from any error type that implements the Error trait. This
+/// also includes Nuhound errors. Resultant errors may be handled using the `?` operator or by simply
+/// returning it to the calling context as a `Result::Err` directly.
+///
+/// The macro creates an error message that may optionally contain the name of the source file and
+/// location of the error. This behaviour is enabled by compiling the code with the 'disclose'
+/// feature.
+///
+/// A stable error code may be given with a `code = "E100"` argument after the message, in which
+/// case the message is prefixed with the code such as "[E100] ". Each code may only be given to one
+/// site of the crate, otherwise compilation fails naming both sites. The code of an error that
+/// derives `ErrorCode` is likewise prefixed to the message of the linked error. The fields of a
+/// struct that derives `ContextFields` may be appended to the message with a `ctx = ...` argument.
+/// A `severity = warn` argument, where the level is one of `trace`, `debug`, `info`, `warn`,
+/// `error` or `fatal`, logs the message at that level and notes it as metadata of the error for
+/// `severity_of!`, in place of the severity of an error that derives `Severity`.
+/// A `log = warn` argument logs the message at that level as the error is created without noting a
+/// severity, while `log = off` turns off the logging of the call.
+/// Tags may be given with a `tags = ["io", "startup"]` argument and are noted after the message,
+/// such as "[tags: io, startup]", when the `disclose-tags` feature is enabled.
+/// Key and value pairs may follow `fields:` as the last arguments, such as `fields: request_id =
+/// id, shard = ?shard`, and are appended to the message, such as "(request_id=42, shard=Some(3))".
+/// A variable may be given as a field named after it with the sigils of the tracing crate, such as
+/// `?key` for Debug or `%user.name` for Display.
+/// Variables may also be captured as fields formatted with Debug by listing them after a semicolon
+/// that follows the message, such as `"retry failed"; state, attempt, cfg.path`.
+/// Advice may be given with a `help = "..."` argument and follows the message on a distinct "help:"
+/// line, and a link to documentation may be given with a `url = "..."` argument and follows it on a
+/// "see:" line, in the same way as the help and url of an error that derives `Diagnostic`. An
+/// `exit_code = 69` argument notes the code as metadata of the error, so that
+/// `#[nuhound::main]` exits with it when the error reaches the top. A `retriable = true` or
+/// `retriable = false` argument classifies the error as transient or permanent for the
+/// `is_retriable!` macro and the `#[nuhound::retry]` attribute, as does a `classification =
+/// Transient`, `Permanent` or `Unknown` argument, which is also consulted by `is_permanent!`. A
+/// safe message for end users may be given with a `user = "..."` argument, which isn't displayed
+/// but noted as metadata and is retrieved from the chain by `user_message!`. The message may
+/// instead be given as a key that is looked up in the catalog registered with `#[nuhound::catalog]`,
+/// such as `key = "errors.config.missing", args = [path]`. A message may be given for each profile,
+/// such as `debug = "raw packet was {:?}", pkt; release = "malformed packet received"`, so that
+/// only the message of the profile being compiled is built into the binary.
+///
+/// The message may be left out, such as `convert!(text.parse::<u32>())`, in which case the text of
+/// the expression followed by "failed" is used, so that a legacy `?` may be instrumented quickly.
+///
+/// This macro requires either `nuhound::ResultExtension` or `nuhound::OptionExtension` depending on
+/// whether the code being checked returns a `Result` or an `Option`.
+///
+/// # Examples
+/// The following example shows how the `convert` macro is used to report an error but still retain
+/// the underlying error or errors that can be displayed using the `trace` method.
+///
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// fn my_result() -> Report<u32> {
+///     let text = "NaN";
+///     let value = convert!(text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text)?;
+///     Ok(value)
+/// }
+///
+/// fn layer2() -> Report<u32> {
+///     let result = convert!(my_result(), "Next level failure")?;
+///     Ok(result)
+/// }
+///
+/// fn layer1() -> Report<u32> {
+///     let result = convert!(layer2(), "Highest level failure")?;
+///     Ok(result)
+/// }
+///
+/// match layer1() {
+///     Ok(value) => println!("Value = {value}"),
+///     Err(e) => {
+///         #[cfg(feature = "disclose")]
+///         eprintln!("{}", e.trace());
+///         #[cfg(not(feature = "disclose"))]
+///         eprintln!("{}", e);
+///     },
+/// }
+///
+/// // using 'cargo run --features disclose' will emit the following message:
+/// //
+/// // 0: examples/nan.rs:16:22: Highest level failure
+/// // 1: examples/nan.rs:11:22: Next level failure
+/// // 2: examples/nan.rs:6:21: Oh dear - 'NaN' could not be converted to an integer
+/// // 3: invalid digit found in string
+/// //
+/// // using 'cargo run' without the disclose feature will emit the following message:
+/// //
+/// // Highest level failure
+/// //
+/// // Notice that the error detail is no longer visible
+///```
+#[proc_macro]
+pub fn convert(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| convert_builder(item.to_string())).parse().unwrap()))
+}
+
+//  convert_let macro
+/// A macro to bind the value held by a `Result` or `Option` or immediately return a `Nuhound` type
+/// error to the calling context. The first parameter takes the form `pattern = expression` and the
+/// remaining parameters form the message. The error is prepared in exactly the same way as the
+/// `convert` macro, so any error type that implements the Error trait may be used.
+///
+/// The macro expands to a `let` statement so that the binding is available to the code that
+/// follows it. It must therefore be used as a statement. The returned error is passed through
+/// `From` in the same manner as the `?` operator.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert_let};
+///
+/// fn port(text: &str) -> Report<u16> {
+///     convert_let!(value = text.parse::<u16>(), "couldn't obtain the port from '{}'", text);
+///     Ok(value)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message for "http":
+/// //
+/// // 0: examples/port.rs:4:5: couldn't obtain the port from 'http'
+/// // 1: invalid digit found in string
+///```
+#[proc_macro]
+pub fn convert_let(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| convert_let_builder(item.to_string())).parse().unwrap()))
+}
+
+//  examine macro
+/// A macro to prepare a `Nuhound` type error from previously handled `Nuhound` error(s). Whilst the
+/// `convert` macro is completely error type agnostic provided the error handler implements the
+/// `Error` trait, the `examine` macro requires much less binary code to implement and hence is
+/// more efficient. Resultant errors are handled using the `?` operator by simply returning it to the
+/// calling context as a `Result::Err` directly.
+///
+/// The macro creates an error message that may optionally contain the name of the source file and
+/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
+/// feature.
+///
+/// The message may be left out, such as `examine!(load_config())`, in which case the text of the
+/// expression followed by "failed" is used.
+///
+/// A stable error code may be given with a `code = "E100"` argument after the message, in which
+/// case the message is prefixed with the code such as "[E100] ", so that existing sites may be
+/// given codes without changing the macro that they use. Each code may only be given to one site of
+/// the crate, otherwise compilation fails naming both sites. The fields of a struct that derives
+/// `ContextFields` may be appended to the message with a `ctx = ...` argument. A `severity = warn`
+/// argument, where the level is one of `trace`, `debug`, `info`, `warn`, `error` or `fatal`, logs
+/// the message at that level and notes it as metadata of the error for `severity_of!`. A `log =
+/// warn` argument logs the message at that level as the error is created without noting a severity,
+/// while `log = off` turns off the logging of the call. Tags may be given with a `tags = ["io",
+/// "startup"]` argument and are noted after the message, such as "[tags: io, startup]", when the
+/// `disclose-tags` feature is enabled.
+/// Key and value pairs may follow `fields:` as the last arguments, such as `fields: request_id =
+/// id, shard = ?shard`, and are appended to the message, such as "(request_id=42, shard=Some(3))".
+/// A variable may be given as a field named after it with the sigils of the tracing crate, such as
+/// `?key` for Debug or `%user.name` for Display.
+/// Variables may also be captured as fields formatted with Debug by listing them after a semicolon
+/// that follows the message, such as `"retry failed"; state, attempt, cfg.path`.
+/// Advice may be given with a `help = "..."` argument and follows the message on a distinct "help:"
+/// line, and a link to documentation may be given with a `url = "..."` argument and follows it on a
+/// "see:" line, in the same way as the help and url of an error that derives `Diagnostic`. An
+/// `exit_code = 69` argument notes the code as metadata of the error, so that
+/// `#[nuhound::main]` exits with it when the error reaches the top. A `retriable = true` or
+/// `retriable = false` argument classifies the error as transient or permanent for the
+/// `is_retriable!` macro and the `#[nuhound::retry]` attribute, as does a `classification =
+/// Transient`, `Permanent` or `Unknown` argument, which is also consulted by `is_permanent!`. A
+/// safe message for end users may be given with a `user = "..."` argument, which isn't displayed
+/// but noted as metadata and is retrieved from the chain by `user_message!`. The message may
+/// instead be given as a key that is looked up in the catalog registered with `#[nuhound::catalog]`,
+/// such as `key = "errors.config.missing", args = [path]`. A message may be given for each profile,
+/// such as `debug = "raw packet was {:?}", pkt; release = "malformed packet received"`, so that
+/// only the message of the profile being compiled is built into the binary.
+///
+/// # Examples
+/// The following example shows how the `examine` macro is used to report an error but still retain
+/// the underlying error or errors that can be displayed using the `trace` method.
+///
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, examine};
+///
+/// fn my_result() -> Report<u32> {
+///     let text = "NaN";
+///     // We need to use the convert macro here, because the underlying error is of type
+///     // ParseIntError
+///     let value = convert!(text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text)?;
+///     Ok(value)
+/// }
+///
+/// fn layer2() -> Report<u32> {
+///     // The examine macro can be used here because the called context has already converted the
+///     // ParseIntError to Nuhound 
+///     let result = examine!(my_result(), "Next level failure")?;
+///     Ok(result)
+/// }
+///
+/// fn layer1() -> Report<u32> {
+///     // The examine macro can be used here because the called context is already Nuhound ready.
+///     let result = examine!(layer2(), "Highest level failure")?;
+///     Ok(result)
+/// }
+///
+/// match layer1() {
+///     Ok(value) => println!("Value = {value}"),
+///     Err(e) => {
+///         #[cfg(feature = "disclose")]
+///         eprintln!("{}", e.trace());
+///         #[cfg(not(feature = "disclose"))]
+///         eprintln!("{}", e);
+///     },
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // 0: examples/nan.rs:16:22: Highest level failure
+/// // 1: examples/nan.rs:11:22: Next level failure
+/// // 2: examples/nan.rs:6:21: Oh dear - 'NaN' could not be converted to an integer
+/// // 3: invalid digit found in string
+/// //
+/// // using `cargo run` without the disclose feature will emit the following message:
+/// //
+/// // Highest level failure
+/// //
+/// // Notice that the error detail is no longer visible
+///```
+#[proc_macro]
+pub fn examine(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| examine_builder(item.to_string())).parse().unwrap()))
+}
+
+//  custom macro
+/// A macro to prepare a `Nuhound` type error. Whilst the `convert` and `examine` macros are
+/// designed to respond to previously handled errors, the `custom` macro will always generate a
+/// `Nuhound` error without any pre-conditions. Resultant errors may be handled using the `?`
+/// operator or by simply returning it to the calling context as a `Result::Err` directly. Normal
+/// usage of this macro would suggest using it inside a condition block and returning the error to
+/// the calling context with `return custom!("My error message");`.
+///
+/// This macro creates an error message that may optionally contain the name of the source file and
+/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
+/// feature.
+///
+/// A stable error code may be given with a `code = "E100"` argument after the message, in which
+/// case the message is prefixed with the code such as "[E100] ". Each code may only be given to one
+/// site of the crate, otherwise compilation fails naming both sites.
+/// The fields of a struct that derives `ContextFields` may be appended to the message with a
+/// `ctx = ...` argument. A `severity = warn` argument, where the level is one of `trace`, `debug`,
+/// `info`, `warn`, `error` or `fatal`, logs the message at that level and notes it as metadata
+/// of the error for `severity_of!`. A `log = warn` argument logs the message at that level as the
+/// error is created without noting a severity, while `log = off` turns off the logging of the call.
+/// Tags may be given with a `tags = ["io", "startup"]` argument and are noted after the message,
+/// such as "[tags: io, startup]", when the `disclose-tags` feature is enabled.
+/// Key and value pairs may follow `fields:` as the last arguments, such as `fields: request_id =
+/// id, shard = ?shard`, and are appended to the message, such as "(request_id=42, shard=Some(3))".
+/// A variable may be given as a field named after it with the sigils of the tracing crate, such as
+/// `?key` for Debug or `%user.name` for Display.
+/// Variables may also be captured as fields formatted with Debug by listing them after a semicolon
+/// that follows the message, such as `"retry failed"; state, attempt, cfg.path`.
+/// Advice may be given with a `help = "..."` argument and follows the message on a distinct "help:"
+/// line, and a link to documentation may be given with a `url = "..."` argument and follows it on a
+/// "see:" line, in the same way as the help and url of an error that derives `Diagnostic`. An
+/// `exit_code = 69` argument notes the code as metadata of the error, so that
+/// `#[nuhound::main]` exits with it when the error reaches the top. A `retriable = true` or
+/// `retriable = false` argument classifies the error as transient or permanent for the
+/// `is_retriable!` macro and the `#[nuhound::retry]` attribute, as does a `classification =
+/// Transient`, `Permanent` or `Unknown` argument, which is also consulted by `is_permanent!`. A
+/// safe message for end users may be given with a `user = "..."` argument, which isn't displayed
+/// but noted as metadata and is retrieved from the chain by `user_message!`. The message may
+/// instead be given as a key that is looked up in the catalog registered with `#[nuhound::catalog]`,
+/// such as `key = "errors.config.missing", args = [path]`. A message may be given for each profile,
+/// such as `debug = "raw packet was {:?}", pkt; release = "malformed packet received"`, so that
+/// only the message of the profile being compiled is built into the binary.
+///
+/// # Examples
+/// The following example shows how the `custom` macro is used in conjunction with the `examine`
+/// macro to report an error but still retain the originating error that can be displayed using the
+/// `trace` method.
+///
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, examine, custom};
+///
+/// fn my_result() -> Report<u32> {
+///     let value = 99;
+///     if value == 99 {
+///         return custom!("Oh dear - '{value}' was not expected");
+///     }
+///     Ok(value)
+/// }
+///
+/// fn layer2() -> Report<u32> {
+///     let result = examine!(my_result(), "Next level failure")?;
+///     Ok(result)
+/// }
+///
+/// fn layer1() -> Report<u32> {
+///     let result = examine!(layer2(), "Highest level failure")?;
+///     Ok(result)
+/// }
+///
+/// match layer1() {
+///     Ok(value) => println!("Value = {value}"),
+///     Err(e) => {
+///         #[cfg(feature = "disclose")]
+///         eprintln!("{}", e.trace());
+///         #[cfg(not(feature = "disclose"))]
+///         eprintln!("{}", e);
+///     },
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // 0: examples/custom.rs:18:22: Highest level failure
+/// // 1: examples/custom.rs:13:22: Next level failure
+/// // 2: examples/custom.rs:7:20: Oh dear - '99' was not expected
+/// //
+/// // using `cargo run` without the disclose feature will emit the following message:
+/// //
+/// // Highest level failure
+/// //
+/// // Notice that the error detail is no longer visible
+///```
+#[proc_macro]
+pub fn custom(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| custom_builder(item.to_string())).parse().unwrap()))
+}
+
+//  debug_custom macro
+/// A macro to prepare a `Nuhound` type error in debug builds only. This behaves like the `custom`
+/// macro when the code is compiled with `debug_assertions` but is compiled out of release builds
+/// altogether, where it simply evaluates to `Ok(())`. It is intended for checking invariants that
+/// aren't worth shipping and is normally followed by the `?` operator.
+///
+/// Note that the message arguments are not evaluated in release builds.
+///
+/// # Examples
+/// The following example shows how the `debug_custom` macro is used to check an invariant during
+/// development without any cost in the release build.
+///
+/// ```ignore
+/// use nuhound::{Report, debug_custom};
+///
+/// fn average(values: &[u32]) -> Report<u32> {
+///     if values.is_empty() {
+///         debug_custom!("invariant broken: average of {} values requested", values.len())?;
+///         return Ok(0);
+///     }
+///     Ok(values.iter().sum::<u32>() / values.len() as u32)
+/// }
+///
+/// // Debug builds report the broken invariant as an error whilst release builds return Ok(0).
+///```
+#[proc_macro]
+pub fn debug_custom(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| debug_custom_builder(item.to_string())).parse().unwrap()))
+}
+
+//  guard macro
+/// A macro to bind a refutable pattern or immediately return a `Nuhound` type error to the calling
+/// context when the pattern does not match. It is a concise alternative to `let ... else` blocks
+/// whose only purpose is to report an error. The first parameter takes the form `let pattern =
+/// expression` where the pattern may contain alternatives and bindings. The remaining parameters
+/// form the message in the style of `custom`.
+///
+/// The macro expands to a `let` statement so that the bindings are available to the code that
+/// follows it. It must therefore be used as a statement.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, guard};
+///
+/// enum Config {
+///     Pending,
+///     Loaded(String),
+///     Cached(String),
+/// }
+///
+/// fn config_name(state: Config) -> Report<String> {
+///     guard!(let Config::Loaded(name) | Config::Cached(name) = state, "config not loaded yet");
+///     Ok(name)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message when the config is
+/// // pending:
+/// //
+/// // 0: examples/guard.rs:10:5: config not loaded yet
+///```
+#[proc_macro]
+pub fn guard(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| guard_builder(item.to_string())).parse().unwrap()))
+}
+
+//  dbg_report macro
+/// A macro in the style of `dbg!` for values of type `Report`. The value is returned unchanged but
+/// when it is an `Err` the error is printed to stderr together with the location of the macro and
+/// the text of the expression. With the `disclose` feature enabled the full trace is printed,
+/// otherwise only the error message is shown. This allows temporary diagnostics to be sprinkled
+/// through the code without changing the control flow.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, dbg_report};
+///
+/// fn parse(text: &str) -> Report<u32> {
+///     convert!(text.parse::<u32>(), "'{}' could not be converted to an integer", text)
+/// }
+///
+/// let value = dbg_report!(parse("NaN")).unwrap_or_default();
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // [examples/dbg.rs:7:13] parse("NaN") =
+/// // 0: examples/dbg.rs:4:5: 'NaN' could not be converted to an integer
+/// // 1: invalid digit found in string
+///```
+#[proc_macro]
+pub fn dbg_report(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| dbg_report_builder(item.to_string())).parse().unwrap()))
+}
+
+//  break_custom macro
+/// A macro to break out of a loop with a `Nuhound` type error as the value of the loop expression.
+/// An optional loop label may be given as the first parameter so that an outer loop can be broken
+/// and the remaining parameters form the message in the style of `custom`. This is useful where a
+/// loop is used as an expression that evaluates to a `Result`.
+///
+/// The macro creates an error message that may optionally contain the name of the source file and
+/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
+/// feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, break_custom};
+///
+/// fn find_blank(lines: &[&str]) -> Report<usize> {
+///     let mut index = 0;
+///     'outer: loop {
+///         for character in lines[index].chars() {
+///             if character == '\t' {
+///                 break_custom!('outer, "scan aborted: tab found on line {}", index + 1);
+///             }
+///         }
+///         if lines[index].trim().is_empty() {
+///             break 'outer Ok(index);
+///         }
+///         index += 1;
+///     }
+/// }
+///```
+#[proc_macro]
+pub fn break_custom(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| break_custom_builder(item.to_string())).parse().unwrap()))
+}
+
+//  skip_report macro
+/// A macro to record a `Nuhound` type error in a collector and continue with the next iteration of
+/// a loop. An optional loop label may be given as the first parameter. The next parameter is the
+/// collector, which is normally a `Vec<Nuhound>`, and the remaining parameters form the message in
+/// the style of `custom`.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Nuhound, skip_report};
+///
+/// let mut errors: Vec<Nuhound> = Vec::new();
+/// let mut total = 0;
+/// for text in ["1", "two", "3"] {
+///     let Ok(value) = text.parse::<u32>() else {
+///         skip_report!(errors, "'{}' is not a number", text);
+///     };
+///     total += value;
+/// }
+/// assert_eq!(total, 4);
+/// assert_eq!(errors.len(), 1);
+///```
+#[proc_macro]
+pub fn skip_report(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| skip_report_builder(item.to_string())).parse().unwrap()))
+}
+
+//  todo_report macro
+/// A macro to prepare a `Nuhound` type error in place of the standard `todo!` macro. Rather than
+/// panicking, the macro yields a `Result::Err` so that long running code can degrade gracefully
+/// whilst the trace still shows exactly where the stub is. The message starts with "not yet
+/// implemented" and may optionally be followed by a message in the style of `custom`.
+///
+/// The macro creates an error message that may optionally contain the name of the source file and
+/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
+/// feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, todo_report};
+///
+/// fn export(format: &str) -> Report<Vec<u8>> {
+///     if format == "xml" {
+///         return todo_report!("export to {}", format);
+///     }
+///     Ok(Vec::new())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // 0: examples/todo.rs:5:16: not yet implemented: export to xml
+///```
+#[proc_macro]
+pub fn todo_report(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| placeholder_builder(item.to_string(), "not yet implemented")).parse().unwrap()))
+}
+
+//  unimplemented_report macro
+/// A macro to prepare a `Nuhound` type error in place of the standard `unimplemented!` macro.
+/// Rather than panicking, the macro yields a `Result::Err` whose message starts with "not
+/// implemented" and may optionally be followed by a message in the style of `custom`.
+///
+/// The macro creates an error message that may optionally contain the name of the source file and
+/// location of the error. This behaviour is enabled by compiling the code with the `disclose`
+/// feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, unimplemented_report};
+///
+/// fn legacy_checksum(_data: &[u8]) -> Report<u32> {
+///     unimplemented_report!()
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // 0: examples/legacy.rs:4:5: not implemented
+///```
+#[proc_macro]
+pub fn unimplemented_report(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(flags::located(item, |item| placeholder_builder(item.to_string(), "not implemented")).parse().unwrap()))
+}
+
+//  context attribute
+/// An attribute macro that adds a layer of context to any `Nuhound` type error escaping from the
+/// annotated function. The attribute takes a message in the style of `custom` and the function must
+/// return a `Report`. This removes the need to wrap each call of the function in an `examine`
+/// macro. Both ordinary and async functions are supported.
+///
+/// The message may optionally contain the name of the source file and location of the function.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::context("loading configuration from '{}'", path)]
+/// fn load_config(path: &str) -> Report<String> {
+///     let text = convert!(std::fs::read_to_string(path), "unable to read the file")?;
+///     Ok(text)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message when the file is
+/// // missing:
+/// //
+/// // 0: examples/config.rs:3:1: loading configuration from 'app.toml'
+/// // 1: examples/config.rs:5:16: unable to read the file
+/// // 2: No such file or directory (os error 2)
+///```
+#[proc_macro_attribute]
+pub fn context(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| context_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  main attribute
+/// An attribute macro for a `main` function that returns a `Report`. When the function returns an
+/// error it is printed to stderr and the process exits with a non-zero exit code. With the
+/// `disclose` feature enabled the full trace is printed, otherwise only the error message is
+/// shown. This replaces the `match` boilerplate that would otherwise be needed in every binary.
+/// With the `miette` feature enabled the error is rendered by miette instead, as by `into_miette!`,
+/// so that the user sees a graphical diagnostic with its code, help and location.
+///
+/// The exit code is 1 unless an error that derives `ExitCode` was linked into the chain by the
+/// `convert!` macro, in which case the exit code of that error is used, or a layer of the chain was
+/// given an `exit_code = ...` argument, in which case the first such code from the top is used.
+///
+/// Other attributes, such as `#[tokio::main]`, may be placed after this one. An `async fn main` must
+/// be given such a runtime attribute, as the main function itself can't be async.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::main]
+/// fn main() -> Report<()> {
+///     let value = convert!("NaN".parse::<u32>(), "the value could not be converted")?;
+///     println!("Value = {value}");
+///     Ok(())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // Error:
+/// // 0: examples/main.rs:5:17: the value could not be converted
+/// // 1: invalid digit found in string
+///```
+#[proc_macro_attribute]
+pub fn main(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| main_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  test attribute
+/// An attribute macro for test functions that return a `Report`. It is used in place of the usual
+/// `#[test]` attribute. When the test function returns an error the test fails with a panic whose
+/// message contains the error. With the `disclose` feature enabled the message contains the full
+/// trace rather than just the error message.
+///
+/// Because the error is carried by the panic message, `#[should_panic(expected = "...")]` may be
+/// used to check that a test fails with an error containing a particular substring.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::test]
+/// fn parses_good_input() -> Report<()> {
+///     let value = convert!("42".parse::<u32>(), "the value could not be converted")?;
+///     assert_eq!(value, 42);
+///     Ok(())
+/// }
+///
+/// #[nuhound::test]
+/// #[should_panic(expected = "the value could not be converted")]
+/// fn rejects_bad_input() -> Report<()> {
+///     convert!("NaN".parse::<u32>(), "the value could not be converted")?;
+///     Ok(())
+/// }
+///```
+#[proc_macro_attribute]
+pub fn test(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| test_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  should_err attribute
+/// An attribute macro for negative tests that return a `Report`. The test passes only when the
+/// function returns an error whose trace contains each of the given substrings. Otherwise the test
+/// fails with a message showing the expected text alongside the actual trace. It complements the
+/// `#[nuhound::test]` attribute and may be used with other test attributes such as `#[ignore]`.
+///
+/// The trace includes the name of the source file and location of each error when the code is
+/// compiled with the `disclose` feature. Otherwise it consists of the message of each error in the
+/// chain of causes.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::should_err("unable to parse 'x'", "invalid digit")]
+/// fn rejects_letters() -> Report<()> {
+///     let _port = convert!("x".parse::<u16>(), "unable to parse '{}'", "x")?;
+///     Ok(())
+/// }
+///```
+#[proc_macro_attribute]
+pub fn should_err(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| should_err_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  trace_calls attribute
+/// An attribute macro that logs entry to and exit from the annotated function. When the function
+/// exits with an error, the exit record is logged as a warning and includes the trace of the error.
+/// Unlike the `instrument` attribute, the error itself is left unchanged. The function must return
+/// a `Report`.
+///
+/// The records are logged using the `tracing` crate or the `log` crate, one of which must be
+/// enabled with the `tracing-events` or `log` feature in the calling crate. The following options are
+/// available:
+///
+/// * `args` - include the values of the parameters in the records, using `Debug` where the type
+///   implements it and `..` otherwise
+/// * `skip(a, b)` - leave out the named parameters when `args` is given
+/// * `level = "info"` - the level of the entry and successful exit records, which defaults to
+///   `debug`
+///
+/// The trace may optionally contain the name of the source file and location of each error. This
+/// behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::trace_calls(args, level = "info")]
+/// fn load(path: &str) -> Report<String> {
+///     let text = convert!(std::fs::read_to_string(path), "unable to read '{}'", path)?;
+///     Ok(text)
+/// }
+///
+/// // using `cargo run --features disclose,log` will log the following records when the file can't
+/// // be read:
+/// //
+/// // INFO  enter app::load(path="settings.toml")
+/// // WARN  exit app::load(path="settings.toml") with error:
+/// // 0: src/main.rs:5:16: unable to read 'settings.toml'
+/// // 1: No such file or directory (os error 2)
+///```
+#[proc_macro_attribute]
+pub fn trace_calls(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| trace_calls_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  instrument attribute
+/// An attribute macro that adds a layer of context to any `Nuhound` type error escaping from the
+/// annotated function. The layer names the function, including its module path, together with the
+/// values of its parameters in the form `my_crate::net::fetch(url="http://x", retries=3)`. The
+/// function must return a `Report`.
+///
+/// Parameter values are shown using their `Debug` representation and as `..` when the type does not
+/// implement `Debug`. The values are captured every time the function is called, so parameters that
+/// are expensive to format, or that should not appear in error messages, ought to be skipped. The
+/// following options are available:
+///
+/// - `skip(a, b)` omits the named parameters
+/// - `skip_all` omits all of the parameters
+/// - `name = "..."` replaces the name of the function in the message
+///
+/// The `self` parameter and parameters that are destructured by a pattern are always omitted.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::instrument(skip(password))]
+/// fn login(user: &str, password: &str, attempt: u32) -> Report<u32> {
+///     convert!(password.parse::<u32>(), "the password was rejected")
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message:
+/// //
+/// // 0: examples/login.rs:3:1: login::login(user="root", attempt=1)
+/// // 1: examples/login.rs:5:5: the password was rejected
+/// // 2: invalid digit found in string
+///```
+#[proc_macro_attribute]
+pub fn instrument(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| instrument_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  trace_all attribute
+/// An attribute macro that rewrites every question mark operator in the annotated function as if
+/// the expression it applies to had been wrapped in the `convert` macro with the text of the
+/// expression as its message. Each error therefore gains a layer naming the expression that failed
+/// and, with the `disclose` feature enabled, its location. This makes it quick to adopt nuhound in
+/// existing code without editing every line. The function must return a `Report`.
+///
+/// Question mark operators inside closures are rewritten too, so closures that use the operator on
+/// an `Option` or return a different error type should be moved out of the annotated function.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::Report;
+///
+/// #[nuhound::trace_all]
+/// fn sum(first: &str, second: &str) -> Report<u32> {
+///     let first = first.trim().parse::<u32>()?;
+///     let second = second.trim().parse::<u32>()?;
+///     Ok(first + second)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message for sum("1", "x"):
+/// //
+/// // 0: examples/sum.rs:6:44: second.trim().parse::<u32>()
+/// // 1: invalid digit found in string
+///```
+#[proc_macro_attribute]
+pub fn trace_all(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        flags::scoped(&attribute.to_string(), |attribute| {
+            let body = rewrite::rewrite_try(body, EXPRESSION, &trace_generator);
+            portable(splice_body(trace_all_builder(attribute.to_string(), item).parse().unwrap(), &body))
+        })
+    })
+}
+
+//  retry attribute
+/// An attribute macro that retries the body of the annotated function until it succeeds or the
+/// number of attempts is exhausted. When every attempt fails, the error of the final attempt is
+/// linked to a `Nuhound` type error that notes the number of attempts and the errors of the earlier
+/// attempts. The function must return a `Result` whose error implements the Error trait and
+/// `From<Nuhound>`, which includes `Report`. The following options are available:
+///
+/// - `attempts = 3` sets the maximum number of attempts and must be given
+/// - `delay = "200ms"` waits between attempts. The units may be ns, us, ms, s, m or h
+///
+/// When the function returns an error type that derives `Retriable`, the attempts stop as soon as
+/// an error that isn't retriable occurs.
+///
+/// Both ordinary and async functions are supported. A delay in an async function requires either
+/// the `tokio` or the `async-std` feature to be enabled in the calling crate so that the
+/// corresponding timer can be used. Since the body may run more than once it must not consume any
+/// of the function parameters.
+///
+/// The message may optionally contain the name of the source file and location of the function.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::retry(attempts = 3, delay = "200ms")]
+/// fn fetch(url: &str) -> Report<String> {
+///     let body = convert!(download(url), "unable to download '{}'", url)?;
+///     Ok(body)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message when the server is
+/// // unavailable:
+/// //
+/// // 0: examples/fetch.rs:3:1: failed after 3 attempts; attempt 1: unable to download 'x';
+/// //    attempt 2: unable to download 'x'
+/// // 1: examples/fetch.rs:5:16: unable to download 'x'
+/// // 2: connection refused
+///```
+#[proc_macro_attribute]
+pub fn retry(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| retry_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  map_err attribute
+/// An attribute macro for functions that use `Nuhound` type errors internally but need to return a
+/// typed error as part of a public API. The attribute takes the name of the error type and the
+/// return type of the function is rewritten so that `Report<T>`, or `Result<T, Nuhound>`, becomes
+/// `Result<T, MyCrateError>`. Any `Nuhound` error escaping from the body is converted using
+/// `From<Nuhound>`, which should keep the `Nuhound` error as its source so that the chain is
+/// preserved. Alternatively a constructor may be given with the `with = ...` option.
+///
+/// Both ordinary and async functions are supported.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Nuhound, Report, ResultExtension, convert};
+///
+/// #[derive(Debug)]
+/// pub enum MyCrateError {
+///     Internal(Nuhound),
+/// }
+///
+/// #[nuhound::map_err(MyCrateError, with = MyCrateError::Internal)]
+/// pub fn parse_port(text: &str) -> Report<u16> {
+///     let port = convert!(text.parse::<u16>(), "'{}' is not a valid port", text)?;
+///     Ok(port)
+/// }
+///
+/// // parse_port now returns Result<u16, MyCrateError>
+///```
+#[proc_macro_attribute]
+pub fn map_err(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| map_err_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  deadline attribute
+/// An attribute macro that fails the annotated function with a `Nuhound` type error when it runs
+/// for longer than the given duration. The error message takes the form "deadline exceeded after
+/// 5s in my_mod::my_fn". The units of the duration may be ns, us, ms, s, m or h. The function must
+/// return a `Report`.
+///
+/// The body of an async function is raced against a timer and abandoned when the deadline expires.
+/// This requires either the `tokio` or the `async-std` feature to be enabled in the calling crate
+/// so that the corresponding timer can be used. An ordinary function can't be interrupted, so the
+/// deadline is checked once its body has finished. If the body took too long, its result is
+/// replaced by the deadline error and any error that it returned becomes the cause.
+///
+/// The message may optionally contain the name of the source file and location of the function.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::deadline("5s")]
+/// async fn fetch_index(url: &str) -> Report<String> {
+///     let body = convert!(download(url).await, "unable to download '{}'", url)?;
+///     Ok(body)
+/// }
+///
+/// // using `cargo run --features disclose,tokio` will emit the following message when the
+/// // download is too slow:
+/// //
+/// // 0: examples/fetch.rs:3:1: deadline exceeded after 5s in fetch::fetch_index
+///```
+#[proc_macro_attribute]
+pub fn deadline(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| deadline_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  catch_panics attribute
+/// An attribute macro that converts a panic within the annotated function into a `Nuhound` type
+/// error carrying the panic message, so that a misbehaving function can't take down its caller.
+/// The error message takes the form "panicked in my_mod::my_fn: index out of bounds". The function
+/// must return a `Report`.
+///
+/// The body of an ordinary function must be `UnwindSafe`. This check may be waived by using
+/// `#[nuhound::catch_panics(assert_unwind_safe)]` when the caller knows that no broken invariants
+/// can be observed after a panic. An async function is always treated as unwind safe because each
+/// poll of its future is caught individually.
+///
+/// Note that the panic hook still runs, so the usual panic message is printed to stderr unless the
+/// hook has been replaced. Panics are not caught when the crate is compiled with `panic = "abort"`.
+///
+/// The message may optionally contain the name of the source file and location of the function.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::Report;
+///
+/// #[nuhound::catch_panics]
+/// fn run_plugin(plugin: fn(&str) -> Report<()>, input: &str) -> Report<()> {
+///     plugin(input)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message when the plugin panics:
+/// //
+/// // 0: src/host.rs:3:1: panicked in host::run_plugin: plugin failed
+///```
+#[proc_macro_attribute]
+pub fn catch_panics(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| catch_panics_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  report attribute
+/// An attribute macro for `impl` blocks, including trait implementations, that applies context
+/// wrapping to every method returning a `Report` or a `Result<T, Nuhound>`. A `Nuhound` type error
+/// escaping from such a method gains a further layer naming the type and the method, such as
+/// "my_crate::Store::load". Methods that return any other type are left untouched, as are any
+/// methods named in the optional `skip(...)` list.
+///
+/// The message may optionally contain the name of the source file and location of the method.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// struct Store {
+///     root: String,
+/// }
+///
+/// #[nuhound::report(skip(root))]
+/// impl Store {
+///     fn load(&self, key: &str) -> Report<String> {
+///         let text = convert!(std::fs::read_to_string(format!("{}/{}", self.root, key)), "unable to read '{}'", key)?;
+///         Ok(text)
+///     }
+///
+///     fn root(&self) -> Report<&str> {
+///         Ok(&self.root)
+///     }
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the key can't be
+/// // read:
+/// //
+/// // 0: src/store.rs:9:8: my_crate::Store::load
+/// // 1: src/store.rs:10:20: unable to read 'settings'
+/// // 2: No such file or directory (os error 2)
+///```
+#[proc_macro_attribute]
+pub fn report(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        portable(flags::scoped(&attribute.to_string(), |attribute| report_impl(attribute, item)))
+    })
+}
+
+//  timed attribute
+/// An attribute macro that adds a layer to any `Nuhound` type error escaping from the annotated
+/// function stating how long the function ran before it failed, such as "load_index failed after
+/// 3.2s". This helps to diagnose failures that are caused by, or close to, a timeout from the trace
+/// alone. The function must return a `Report`.
+///
+/// The message may optionally contain the name of the source file and location of the function.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::timed]
+/// fn load_index(path: &str) -> Report<String> {
+///     let text = convert!(std::fs::read_to_string(path), "unable to read '{}'", path)?;
+///     Ok(text)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the file can't be
+/// // read:
+/// //
+/// // 0: src/main.rs:3:1: load_index failed after 3.2s
+/// // 1: src/main.rs:5:16: unable to read 'index.txt'
+/// // 2: No such file or directory (os error 2)
+///```
+#[proc_macro_attribute]
+pub fn timed(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| timed_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  ensure attribute
+/// An attribute macro that checks a precondition before the body of the annotated function runs.
+/// When the condition is false the function returns a `Nuhound` type error with the given message,
+/// which is formed from format arguments in the same way as the `custom!` macro. When no message is
+/// given it takes the form "precondition failed: count > 0". The function must return a `Report`.
+///
+/// Several ensure attributes may be placed on the same function and the preconditions are checked
+/// in the order in which they are written.
+///
+/// The message may optionally contain the name of the source file and location of the function.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use std::path::Path;
+/// use nuhound::Report;
+///
+/// #[nuhound::ensure(!path.as_os_str().is_empty(), "path must not be empty")]
+/// #[nuhound::ensure(limit > 0)]
+/// fn read_lines(path: &Path, limit: usize) -> Report<Vec<String>> {
+///     ...
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message when the limit is 0:
+/// //
+/// // 0: src/main.rs:4:1: precondition failed: limit > 0
+///```
+#[proc_macro_attribute]
+pub fn ensure(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| ensure_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  fallback attribute
+/// An attribute macro for best-effort functions, such as cache warming, that returns the given
+/// fallback expression when the body of the annotated function fails instead of propagating the
+/// error. The function is written to return a `Report<T>` and the attribute rewrites its return
+/// type to `T`.
+///
+/// The error is recorded before the fallback is returned. When the `sink = path` option is given,
+/// the function at that path is called with a reference to the `Nuhound` error. Otherwise the error
+/// is logged as a warning using the `tracing` crate or the `log` crate when the `tracing-events`
+/// or `log` feature is enabled in the calling crate, or written to stderr when neither is enabled.
+///
+/// The recorded error may optionally contain the full trace, including the name of the source file
+/// and location of each error. This behaviour is enabled by compiling the code with the `disclose`
+/// feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Nuhound, Report, ResultExtension, convert};
+///
+/// fn record(error: &Nuhound) {
+///     metrics::counter!("cache_warm_failures").increment(1);
+///     eprintln!("{}", error);
+/// }
+///
+/// #[nuhound::fallback(0, sink = record)]
+/// fn warm_cache(path: &str) -> Report<usize> {
+///     let text = convert!(std::fs::read_to_string(path), "unable to read '{}'", path)?;
+///     Ok(preload(&text))
+/// }
+///
+/// // warm_cache now returns usize and evaluates to 0 when the file can't be read
+///```
+#[proc_macro_attribute]
+pub fn fallback(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| fallback_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  suppress_disclose attribute
+/// An attribute macro for items that handle sensitive data, such as credentials. The nuhound macros
+/// and attributes within the annotated item emit plain messages, without the locations of errors,
+/// traces or the values of parameters, even when the rest of the crate is compiled with the
+/// `disclose` feature. It may be applied to a function, an impl block or a module.
+///
+/// This has the effect of giving the `@quiet` flag to each nuhound macro within the item and to
+/// each attribute that is qualified by the nuhound crate, such as `#[nuhound::instrument]`. The flag
+/// may also be given directly to a single macro, such as `custom!(@quiet "access denied")`, and a
+/// macro given the `@disclose` flag directly is left disclosed. Attributes placed above this one are
+/// expanded first and so are not affected. The attribute is equivalent to `#[nuhound::disclose(off)]`.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::suppress_disclose]
+/// #[nuhound::instrument]
+/// fn login(user: &str, password: &str) -> Report<Session> {
+///     let session = convert!(authenticate(user, password), "unable to authenticate")?;
+///     Ok(session)
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when authentication
+/// // fails:
+/// //
+/// // 0: app::login()
+/// // 1: unable to authenticate
+/// // 2: invalid credentials
+///```
+#[proc_macro_attribute]
+pub fn suppress_disclose(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let attribute = attribute.to_string();
+    if !flags::Flags::split(&attribute).1.is_empty() {
+        panic!("The suppress_disclose attribute does not take any parameters");
+    }
+    rewrite::flag_macros(item, "@scoped_quiet", &MACROS, &ATTRIBUTES)
+}
+
+//  disclose attribute
+/// An attribute macro that turns the disclosure of the nuhound macros and attributes within the
+/// annotated item off or on, whatever features the crate is compiled with. It may be applied to a
+/// function, an impl block or a module, so that a whole module that handles sensitive data may be
+/// excluded from disclosure without touching each call, or a module under investigation may
+/// disclose its errors in a release build.
+///
+/// The `off` setting has the effect of giving the `@quiet` flag to each nuhound macro within the
+/// item and the `on` setting the `@disclose` flag. An attribute nested within the item takes
+/// precedence over this one, while a flag given directly to a single macro, such as
+/// `convert!(@disclose ...)`, takes precedence over both. Attributes placed above this one are
+/// expanded first and so are not affected.
+///
+/// The attribute may be written as an inner attribute, `#![nuhound::disclose(off)]`, at the top of
+/// a module where the compiler supports custom inner attributes.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::disclose(off)]
+/// mod credentials {
+///     pub fn load(path: &str) -> Report<String> {
+///         let text = convert!(std::fs::read_to_string(path), "unable to read the credentials")?;
+///         Ok(text)
+///     }
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the file is
+/// // missing:
+/// //
+/// // 0: unable to read the credentials
+/// // 1: No such file or directory (os error 2)
+///```
+#[proc_macro_attribute]
+pub fn disclose(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let attribute = attribute.to_string();
+    let flag = match flags::Flags::split(&attribute).1 {
+        "off" => "@scoped_quiet",
+        "on" => "@scoped_disclose",
+        _ => panic!("The disclose attribute expects either 'off' or 'on'"),
+    };
+    rewrite::flag_macros(item, flag, &MACROS, &ATTRIBUTES)
+}
+
+//  track_caller attribute
+/// An attribute macro for small helper functions that wrap the nuhound macros. The errors created by
+/// the nuhound macros within the annotated function report the location of the code that called the
+/// function rather than a location within the function itself. This makes the location disclosed
+/// in the trace useful when the same helper is called from many places.
+///
+/// The function is given the `#[track_caller]` attribute and so the location may be passed on
+/// through several helper functions provided that each of them is marked. Async functions are not
+/// supported.
+///
+/// The message may optionally contain the name of the source file and location of the caller.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert};
+///
+/// #[nuhound::track_caller]
+/// fn parse_number(text: &str) -> Report<u32> {
+///     let number = convert!(text.parse::<u32>(), "'{}' is not a number", text)?;
+///     Ok(number)
+/// }
+///
+/// fn main() -> Report<()> {
+///     let width = parse_number("10")?;
+///     let height = parse_number("ten")?;
+///     ...
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages:
+/// //
+/// // 0: src/main.rs:11:18: 'ten' is not a number
+/// // 1: invalid digit found in string
+///```
+#[proc_macro_attribute]
+pub fn track_caller(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        let body = rewrite::flag_macros(body, "@caller", &MACROS, &[]);
+        let generated = flags::scoped(&attribute.to_string(), |attribute| track_caller_builder(attribute.to_string(), item));
+        splice_body(portable(generated.parse().unwrap()), &body)
+    })
+}
+
+//  async_context attribute
+/// An attribute macro for async functions with many awaits that shows which of them failed. Each
+/// `.await` whose result is passed to the question mark operator, such as `fetch(url).await?`, adds
+/// a layer to the error naming the awaited future. The layer takes the location of the question
+/// mark. Other question mark operators are left unchanged, which distinguishes this attribute from
+/// the `trace_all` attribute. The function must return a `Report`.
+///
+/// The message may optionally contain the name of the source file and location of each await.
+/// This behaviour is enabled by compiling the code with the `disclose` feature.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::Report;
+///
+/// #[nuhound::async_context]
+/// async fn sync_accounts(client: &Client) -> Report<usize> {
+///     let accounts = client.list_accounts().await?;
+///     for account in &accounts {
+///         client.refresh(account).await?;
+///     }
+///     Ok(accounts.len())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when a refresh fails:
+/// //
+/// // 0: src/sync.rs:7:41: awaiting client.refresh(account)
+/// // 1: connection reset by peer
+///```
+#[proc_macro_attribute]
+pub fn async_context(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        let (item, body) = detach_body(item);
+        flags::scoped(&attribute.to_string(), |attribute| {
+            let body = rewrite::rewrite_try(body, EXPRESSION, &await_generator);
+            portable(splice_body(async_context_builder(attribute.to_string(), item).parse().unwrap(), &body))
+        })
+    })
+}
+
+//  catalog attribute
+/// An attribute macro that registers a function as the catalog of the templates of localized
+/// messages. An error whose message is given as a key, such as `custom!(key =
+/// "errors.config.missing", args = [path])`, looks up the template of the key in the catalog when
+/// the error is created and falls back to the key itself when the catalog returns `None`. The
+/// function takes the key as a `&str` and returns an `Option` of a `String` or `&'static str`, and
+/// so may be backed by fluent, gettext or a simple table chosen at run time.
+///
+/// A template holds `{}` placeholders that are filled with the args in order, or `{0}`, `{1}` and so
+/// on to refer to them by position, while `{{` and `}}` stand for braces. The catalog must be
+/// registered in the root module of the crate.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, custom};
+///
+/// #[nuhound::catalog]
+/// fn translate(key: &str) -> Option<&'static str> {
+///     match key {
+///         "errors.config.missing" => Some("configuration file {} is missing"),
+///         _ => None,
+///     }
+/// }
+///
+/// fn load(path: &str) -> Report<()> {
+///     custom!(key = "errors.config.missing", args = [path])
+/// }
+///
+/// // The message of the error is formed from the template, for example:
+/// //
+/// // configuration file app.toml is missing
+///```
+#[proc_macro_attribute]
+pub fn catalog(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    shielded([attribute, item], |[attribute, item]| {
+        portable(catalog_builder(attribute.to_string(), item.to_string()).parse().unwrap())
+    })
+}
+
+//  public_api attribute
+/// An attribute macro for functions that form part of a public API, requiring each error that they
+/// create to carry a stable code which downstream users may rely upon. Every `custom!`, `convert!`
+/// and `examine!` macro within the annotated function must be given a `code = "..."` argument,
+/// otherwise compilation fails at that macro, as it does when the code is already given to another
+/// site of the crate. The codes may be gathered into a table for the crate by the `error_codes!`
+/// macro.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, custom};
+///
+/// #[nuhound::public_api]
+/// pub fn parse_port(text: &str) -> Report<u16> {
+///     let port = convert!(text.parse::<u16>(), "'{}' is not a valid port", text, code = "E100")?;
+///     if port < 1024 {
+///         return custom!("port {} is reserved", port, code = "E101");
+///     }
+///     Ok(port)
+/// }
+///
+/// // The message of the error carries its code, for example:
+/// //
+/// // [E101] port 80 is reserved
+///```
+#[proc_macro_attribute]
+pub fn public_api(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    if !attribute.is_empty() {
+        panic!("The public_api attribute does not take any parameters");
+    }
+    shielded([item], |[item]| {
+        let mut output = item.clone();
+        for (span, input) in rewrite::find_macros(item, &["custom", "convert", "examine"]) {
+            if public_api_code(&input.to_string()).is_none() {
+                output.extend(rewrite::respan(
+                    "::std::compile_error!(\"Errors created within the public API must be given a stable code such as `code = \\\"E100\\\"`\");"
+                        .parse().unwrap(),
+                    span,
+                ));
+            }
+        }
+        portable(output)
+    })
+}
+
+//  error_codes macro
+/// A macro that evaluates to a `&'static [&'static str]` holding the error codes of the functions
+/// in the crate that are marked with the `public_api` attribute. The codes are read from the source
+/// of the module where the macro is used and the modules declared within it, in the order that they
+/// are written, and so the macro is used in the root module of the crate.
+///
+/// # Examples
+/// ```ignore
+/// // In lib.rs
+/// pub const ERROR_CODES: &[&str] = nuhound::error_codes!();
+///```
+#[proc_macro]
+pub fn error_codes(item: TokenStream) -> TokenStream {
+    if !item.is_empty() {
+        panic!("The error_codes macro does not take any parameters");
+    }
+    let file = proc_macro::Span::call_site().local_file()
+        .unwrap_or_else(|| panic!("The error_codes macro must be used within a source file of the crate"));
+    format!("&[{}]", registry::codes(&file).join(", ")).parse().unwrap()
+}
+
+//  error_sites macro
+/// A macro that evaluates to a `&'static [(usize, &'static str, usize, usize, &'static str)]`
+/// holding the number, file, line, column and format string of the message of each site in the
+/// crate where an error is created by the convert!, examine! or custom! macros. The sites are
+/// numbered in the order that they are compiled and so the macro must be used after all of them,
+/// such as at the end of the root module of the crate.
+///
+/// The number of the site prefixes each message, such as `[site 12] unable to connect`, when the
+/// `site-id` feature is enabled, so that the list may serve as the foundation for error dashboards
+/// and coverage tooling.
+///
+/// # Examples
+/// ```ignore
+/// // At the end of lib.rs
+/// pub const ERROR_SITES: &[(usize, &str, usize, usize, &str)] = nuhound::error_sites!();
+///
+/// // ERROR_SITES holds entries such as (12, "src/net.rs", 42, 17, "unable to connect to {}")
+///```
+#[proc_macro]
+pub fn error_sites(item: TokenStream) -> TokenStream {
+    if !item.is_empty() {
+        panic!("The error_sites macro does not take any parameters");
+    }
+    format!("&[{}]", registry::sites().join(", ")).parse().unwrap()
+}
+
+//  site_counters macro
+/// A macro that declares the counters of the errors produced by each site in the crate when the
+/// `site-counts` feature is enabled. Each site where an error is created by the convert!, examine!
+/// or custom! macros increments its own counter, which the `nuhound_sites!` macro reads. The
+/// counters are numbered in the same way as the sites listed by the error_sites macro and so this
+/// macro must be used after all of them, at the end of the root module of the crate. Every crate
+/// compiled with the feature, including its tests and examples, must declare its counters.
+///
+/// # Examples
+/// ```ignore
+/// // At the end of lib.rs
+/// nuhound::site_counters!();
+///```
+#[proc_macro]
+pub fn site_counters(item: TokenStream) -> TokenStream {
+    if !item.is_empty() {
+        panic!("The site_counters macro does not take any parameters");
+    }
+    portable(site_counters_builder().parse().unwrap())
+}
+
+//  nuhound_sites macro
+/// A macro that evaluates to an iterator of `(usize, &'static str, usize, u64)` holding the number,
+/// file and line of each site in the crate together with the number of errors that it has produced
+/// so far, which gives in-process error statistics for health endpoints. The errors are counted when
+/// the `site-counts` feature is enabled and the counters are declared by the `site_counters!`
+/// macro, otherwise the counts remain zero. As with the error_sites macro, it must be used after
+/// all of the sites in the crate.
+///
+/// # Examples
+/// ```ignore
+/// // At the end of lib.rs
+/// nuhound::site_counters!();
+///
+/// pub fn error_statistics() -> String {
+///     nuhound::nuhound_sites!()
+///         .filter(|(_, _, _, count)| *count > 0)
+///         .map(|(site, file, line, count)| format!("site {site} at {file}:{line}: {count}\n"))
+///         .collect()
+/// }
+///
+/// // error_statistics() returns lines such as "site 12 at src/net.rs:42: 3"
+///```
+#[proc_macro]
+pub fn nuhound_sites(item: TokenStream) -> TokenStream {
+    if !item.is_empty() {
+        panic!("The nuhound_sites macro does not take any parameters");
+    }
+    portable(nuhound_sites_builder().parse().unwrap())
+}
+
+//  IntoNuhound derive
+/// A derive macro for existing error types, such as enums using `thiserror`, that implements
+/// `From<MyError> for Nuhound` using the `Display` output of the error. This allows the `?` operator
+/// to convert the error in functions that return a `Report`. The `convert!` macro also uses this
+/// conversion when linking the error, rather than treating it as a `dyn Error`, and so the error
+/// need not implement the Error trait.
+///
+/// Each variant may be given a stable code with `#[nuhound(code = "...")]`, in which case the
+/// message is prefixed with the code such as "[E100] ". The attribute may also be placed on a
+/// struct.
+///
+/// The Display output of the error is a single layer unless the type is given the
+/// `#[nuhound(chain)]` attribute, in which case the source of the error, such as a field marked
+/// `#[source]` or `#[from]` by thiserror, is linked beneath it together with its own sources, so
+/// that each error of the chain becomes a separate layer. The messages are then best written
+/// without the source, such as `#[error("unable to open store")]` rather than
+/// `#[error("unable to open store: {0}")]`, so that it isn't repeated in the trace.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{IntoNuhound, Report, ResultExtension, convert};
+///
+/// #[derive(Debug, thiserror::Error, IntoNuhound)]
+/// enum StoreError {
+///     #[error("key '{0}' not found")]
+///     #[nuhound(code = "E100")]
+///     Missing(String),
+///     #[error("store is read only")]
+///     ReadOnly,
+/// }
+///
+/// fn update(key: &str) -> Report<()> {
+///     convert!(store.set(key), "unable to update '{}'", key)?;
+///     Ok(())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the key is
+/// // missing:
+/// //
+/// // 0: src/main.rs:13:5: unable to update 'name'
+/// // 1: [E100] key 'name' not found
+///```
+///
+/// ```ignore
+/// use nuhound::{IntoNuhound, Report, ResultExtension, convert};
+///
+/// #[derive(Debug, thiserror::Error, IntoNuhound)]
+/// #[nuhound(chain)]
+/// enum StoreError {
+///     #[error("unable to open store")]
+///     Open(#[from] std::io::Error),
+/// }
+///
+/// // 0: src/main.rs:13:5: unable to update 'name'
+/// // 1: unable to open store
+/// // 2: No such file or directory (os error 2)
+///```
+#[proc_macro_derive(IntoNuhound, attributes(nuhound))]
+pub fn into_nuhound(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(into_nuhound_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  ErrorCode derive
+/// A derive macro that maps each variant of an error to a stable code given by a
+/// `#[code = "..."]` attribute, for use by tooling that keys on the codes. Every variant requires a
+/// code, which must be unique. The attribute may also be placed on a struct.
+///
+/// The following are generated:
+/// - `fn code(&self) -> &'static str` returning the code of the error.
+/// - `const CODES: &'static [&'static str]` listing all the codes.
+/// - `FromStr`, which parses a code into its variant. This fails with a `Nuhound` error when the
+///   code is unknown or belongs to a variant with fields.
+///
+/// The `convert!` macro picks up the code automatically when linking such an error, so that the
+/// message of the error is prefixed with its code such as "[E1001] ". The error must implement the
+/// Error trait for this to happen.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{ErrorCode, Report, ResultExtension, convert};
+///
+/// #[derive(Debug, thiserror::Error, ErrorCode)]
+/// enum StoreError {
+///     #[error("key '{0}' not found")]
+///     #[code = "E1001"]
+///     Missing(String),
+///     #[error("store is read only")]
+///     #[code = "E1002"]
+///     ReadOnly,
+/// }
+///
+/// fn update(key: &str) -> Report<()> {
+///     convert!(store.set(key), "unable to update '{}'", key)?;
+///     Ok(())
+/// }
+///
+/// assert_eq!(StoreError::CODES, &["E1001", "E1002"]);
+/// assert!(matches!("E1002".parse::<StoreError>(), Ok(StoreError::ReadOnly)));
+///
+/// // using `cargo run --features disclose` will emit the following messages when the key is
+/// // missing:
+/// //
+/// // 0: src/main.rs:14:5: unable to update 'name'
+/// // 1: [E1001] key 'name' not found
+///```
+#[proc_macro_derive(ErrorCode, attributes(code))]
+pub fn error_code(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(error_code_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  Diagnostic derive
+/// A derive macro that attaches help text and the url of its documentation to each variant of an
+/// error, in the style of `miette` diagnostics, using a `#[nuhound(help = "...", url = "...")]`
+/// attribute. Either may be omitted and the attribute may also be placed on a struct. The
+/// `fn help(&self)` and `fn url(&self)` methods return them as an `Option<&'static str>`.
+///
+/// The `convert!` macro picks up the help text and url automatically when linking such an error,
+/// adding them to its message as "help:" and "see:" lines so that they are rendered by the trace.
+/// A `help` or `url` argument given to the `convert!` macro takes the place of the one derived.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Diagnostic, Report, ResultExtension, convert};
+///
+/// #[derive(Debug, thiserror::Error, Diagnostic)]
+/// enum StoreError {
+///     #[error("key '{0}' not found")]
+///     #[nuhound(help = "add the key with `store set`", url = "https://example.com/store#keys")]
+///     Missing(String),
+///     #[error("store is read only")]
+///     ReadOnly,
+/// }
+///
+/// fn update(key: &str) -> Report<()> {
+///     convert!(store.set(key), "unable to update '{}'", key)?;
+///     Ok(())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the key is
+/// // missing:
+/// //
+/// // 0: src/main.rs:13:5: unable to update 'name'
+/// // help: add the key with `store set`
+/// // see: https://example.com/store#keys
+/// // 1: key 'name' not found
+///```
+#[proc_macro_derive(Diagnostic, attributes(nuhound))]
+pub fn diagnostic(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(diagnostic_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  ContextFields derive
+/// A derive macro for request or context structs whose fields describe the circumstances of an
+/// error. The `fn context_fields(&self)` method returns the fields as key and value pairs using the
+/// `Debug` output of each value.
+///
+/// Giving the struct to the `convert!` or `custom!` macros with a `ctx = ...` argument appends the
+/// pairs to the message of the error, such as "(request_id=17, user=\"alice\")". A field marked
+/// with `#[context(skip)]` is left out and one marked with `#[context(redact)]` is shown as
+/// "[redacted]".
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{ContextFields, Report, ResultExtension, convert};
+///
+/// #[derive(ContextFields)]
+/// struct Request {
+///     request_id: u64,
+///     user: String,
+///     #[context(redact)]
+///     token: String,
+///     #[context(skip)]
+///     body: Vec<u8>,
+/// }
+///
+/// fn handle(request: &Request) -> Report<()> {
+///     convert!(store.set(&request.user), ctx = request, "unable to handle the request")?;
+///     Ok(())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following message when the store fails:
+/// //
+/// // 0: src/main.rs:15:5: unable to handle the request (request_id=17, user="alice", token=[redacted])
+/// // 1: store is read only
+///```
+#[proc_macro_derive(ContextFields, attributes(context))]
+pub fn context_fields(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(context_fields_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  Severity derive
+/// A derive macro that assigns a severity to each variant of an error using a `#[severity(...)]`
+/// attribute, where the severity is one of `trace`, `debug`, `info`, `warn`, `error` or `fatal`.
+/// Variants without the attribute have a severity of `error`. The attribute may also be placed on a
+/// struct. The `fn severity(&self) -> &'static str` method returns the severity.
+///
+/// The `convert!` macro picks up the severity automatically when linking such an error. The
+/// severity is noted as metadata of the error, which `severity_of!` retrieves, and the message is
+/// logged at that level when the 'tracing-events' or 'log' feature is enabled, so that sinks may route
+/// errors by level. Fatal errors are logged at the error level.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, Severity, convert};
+///
+/// #[derive(Debug, thiserror::Error, Severity)]
+/// enum StoreError {
+///     #[error("key '{0}' not found")]
+///     #[severity(warn)]
+///     Missing(String),
+///     #[error("store is corrupt")]
+///     #[severity(fatal)]
+///     Corrupt,
+/// }
+///
+/// fn update(key: &str) -> Report<()> {
+///     convert!(store.set(key), "unable to update '{}'", key)?;
+///     Ok(())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the key is
+/// // missing:
+/// //
+/// // 0: src/main.rs:14:5: unable to update 'name'
+/// // 1: key 'name' not found
+///```
+#[proc_macro_derive(Severity, attributes(severity))]
+pub fn severity(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(severity_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  FromNuhound derive
+/// A derive macro that reconstructs a typed error from a Nuhound error, so that errors that have
+/// travelled through nuhound internally may be matched exhaustively at an API boundary. The
+/// derived `TryFrom<&Nuhound>` searches the chain of the Nuhound error, starting with the outermost
+/// error, for the first layer whose code is one of the codes of the variants. The code is given by a
+/// `#[nuhound(code = "...")]` or `#[code = "..."]` attribute, as used by the `IntoNuhound` and
+/// `ErrorCode` derives and the `code = "..."` argument of the macros, which note it as metadata of
+/// the layer rather than relying on the text of its message. A variant with fields can't be rebuilt
+/// from the chain, so the Nuhound error is returned when its code is found first, as it is when none
+/// of the codes are found.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{FromNuhound, Nuhound};
+///
+/// #[derive(Debug, FromNuhound)]
+/// pub enum ApiError {
+///     #[code = "E100"]
+///     NotFound,
+///     #[code = "E200"]
+///     Unavailable { retry_after: u64 },
+/// }
+///
+/// pub fn handle(request: &Request) -> Result<Response, ApiError> {
+///     internal(request).map_err(|error| {
+///         ApiError::try_from(&error).unwrap_or(ApiError::Unavailable { retry_after: 0 })
+///     })
+/// }
+///```
+#[proc_macro_derive(FromNuhound, attributes(nuhound, code))]
+pub fn from_nuhound(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(from_nuhound_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  Retriable derive
+/// A derive macro that classifies the variants of an error as transient or permanent. Variants
+/// marked with `#[retriable]` are transient and the others are permanent. The
+/// `fn is_retriable(&self) -> bool` method returns the classification. The attribute may also be
+/// placed on a struct.
+///
+/// The `#[nuhound::retry]` attribute consults the classification automatically when the function
+/// returns the error, so that it stops retrying as soon as a permanent error occurs. A Nuhound error
+/// may be classified likewise with the `retriable = ...` argument of the macros.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::Retriable;
+///
+/// #[derive(Debug, thiserror::Error, Retriable)]
+/// enum FetchError {
+///     #[error("connection refused")]
+///     #[retriable]
+///     Refused,
+///     #[error("not found")]
+///     NotFound,
+/// }
+///
+/// // NotFound is returned after a single attempt whereas Refused is retried up to 3 times
+/// #[nuhound::retry(attempts = 3)]
+/// fn fetch(url: &str) -> Result<String, FetchError> {
+///     download(url)
+/// }
+///```
+#[proc_macro_derive(Retriable, attributes(retriable))]
+pub fn retriable(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(retriable_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  is_retriable macro
+/// A macro that evaluates to `true` when an error is transient and the operation that produced it
+/// may be tried again. The error is borrowed rather than moved. An error that derives `Retriable`
+/// is classified by its `is_retriable` method, while the chain of any other error is searched for
+/// the outermost layer that was given a `retriable = true` or `retriable = false` argument by the
+/// `convert!`, `examine!` or `custom!` macros. An error without a classification is transient.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, is_retriable};
+///
+/// fn fetch(url: &str) -> Report<String> {
+///     convert!(download(url), "unable to fetch {}", url, retriable = true)
+/// }
+///
+/// let page = loop {
+///     match fetch(url) {
+///         Ok(page) => break page,
+///         Err(error) if is_retriable!(error) => continue,
+///         Err(error) => return Err(error),
+///     }
+/// };
+///```
+#[proc_macro]
+pub fn is_retriable(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(is_retriable_builder(item.to_string()).parse().unwrap()))
+}
+
+//  user_message macro
+/// A macro that evaluates to the message for end users given by a `user = "..."` argument to the
+/// outermost layer of an error that has one, as an `Option<String>`. The error is borrowed rather
+/// than moved. This allows a single site to produce both a detailed message for operators, which
+/// may disclose its location, and a safe message that may be shown to the user of an application.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, user_message};
+///
+/// fn load(raw: &str) -> Report<Document> {
+///     convert!(parse(raw), "internal: parse of {} failed", raw,
+///         user = "The uploaded file appears to be corrupted")
+/// }
+///
+/// if let Err(error) = load(raw) {
+///     log::error!("{}", error.trace());
+///     let shown = user_message!(error).unwrap_or_else(|| String::from("Something went wrong"));
+///     respond(shown);
+/// }
+///```
+#[proc_macro]
+pub fn user_message(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(user_message_builder(item.to_string()).parse().unwrap()))
+}
+
+//  severity_of macro
+/// A macro that evaluates to the severity given by a `severity = ...` argument, or by deriving
+/// `Severity`, to the outermost layer of an error that has one, as an `Option<String>` such as
+/// `Some("warn")`. The error is borrowed rather than moved. This allows a sink or handler to route
+/// errors by their severity without matching the text of messages.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, severity_of};
+///
+/// fn store(path: &Path) -> Report<()> {
+///     convert!(fs::write(path, data), "disk nearly full", severity = warn)
+/// }
+///
+/// if let Err(error) = store(path) {
+///     match severity_of!(error).as_deref() {
+///         Some("warn") => alerts.notify(&error),
+///         _ => pager.page(&error),
+///     }
+/// }
+///```
+#[proc_macro]
+pub fn severity_of(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(severity_of_builder(item.to_string()).parse().unwrap()))
+}
+
+//  is_permanent macro
+/// A macro that evaluates to `true` when any layer of an error is permanent, so that a backoff loop
+/// or circuit breaker may stop as soon as retrying can't help, whatever the layers above it say. The
+/// error is borrowed rather than moved. An error that derives `Retriable` is permanent when its
+/// `is_retriable` method returns false, while every layer of the chain of any other error is
+/// searched for one that was given a `classification = Permanent` or `retriable = false` argument
+/// by the `convert!`, `examine!` or `custom!` macros.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, examine, is_permanent};
+///
+/// fn authenticate(token: &str) -> Report<Session> {
+///     convert!(login(token), "credentials rejected", classification = Permanent)
+/// }
+///
+/// fn sync(token: &str) -> Report<()> {
+///     let session = examine!(authenticate(token), "sync failed", classification = Transient)?;
+///     ...
+/// }
+///
+/// // The error of sync is permanent although its outermost layer is transient
+/// if let Err(error) = sync(token) {
+///     if is_permanent!(error) {
+///         breaker.open();
+///     }
+/// }
+///```
+#[proc_macro]
+pub fn is_permanent(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(is_permanent_builder(item.to_string()).parse().unwrap()))
+}
+
+//  report_json macro
+/// A macro that serializes the whole chain of an error into a JSON array, outermost layer first, as a
+/// `String`, so that it may be sent to another service and re-hydrated there with `report_chain!`.
+/// The error is borrowed rather than moved. Each layer is an object with a `msg` member followed by
+/// a member for each of its metadata, such as its `code`, the `file`, `line` and `col` of its
+/// location when it is disclosed, and its `fields`. When the `disclose-json` feature is enabled
+/// each layer is the JSON object of the layer itself, which already holds these members.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, report_json};
+///
+/// fn bind(port: u16) -> Report<Listener> {
+///     convert!(Listener::bind(port), "unable to bind port {}", port, code = "E42")
+/// }
+///
+/// if let Err(error) = bind(8080) {
+///     // [{"msg":"[E42] unable to bind port 8080","code":"E42"},{"msg":"address in use"}]
+///     respond(report_json!(error));
+/// }
+///```
+#[proc_macro]
+pub fn report_json(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(report_json_builder(item.to_string()).parse().unwrap()))
+}
+
+//  report_chain macro
+/// A macro that re-hydrates a chain serialized by `report_json!` into a Nuhound error, as an
+/// `Option<Nuhound>`, so that a service may display or extend the chain of an error that was
+/// received from another. The text is borrowed as a `&str` and None is returned when it holds no
+/// layers. Each layer displays as it did where the error was created and keeps its metadata.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, report_chain};
+///
+/// fn call(request: Request) -> Report<Response> {
+///     let response = client.send(request);
+///     if response.status() != 200 {
+///         let error = report_chain!(response.body())
+///             .unwrap_or_else(|| nuhound::Nuhound::new("remote call failed"));
+///         return Err(error);
+///     }
+///     Ok(response)
+/// }
+///```
+#[proc_macro]
+pub fn report_chain(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(report_chain_builder(item.to_string()).parse().unwrap()))
+}
+
+//  into_eyre macro
+/// A macro that converts a Nuhound error into an `eyre::Report`, the other direction of the `eyre`
+/// feature, for codebases migrating between the two. The error is moved into the report and its
+/// layers are the causes of the report, so that they are displayed by the eyre handler. This
+/// requires the eyre crate as a dependency.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, into_eyre};
+///
+/// fn load(path: &str) -> Report<Config> {
+///     convert!(Config::read(path), "unable to load {}", path)
+/// }
+///
+/// fn main() -> eyre::Result<()> {
+///     let config = load("app.toml").map_err(|error| into_eyre!(error))?;
+///     run(config)
+/// }
+///```
+#[proc_macro]
+pub fn into_eyre(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(into_eyre_builder(item.to_string()).parse().unwrap()))
+}
+
+//  into_miette macro
+/// A macro that converts a Nuhound error into a `miette::Report` so that a command line tool may
+/// render it with miette's graphical handler while its internals remain pure Nuhound. The code,
+/// help and url of the outermost layer become those of the diagnostic and, when the location of the
+/// error is disclosed, the diagnostic is labelled in the line of source embedded when compiling.
+/// The inner layers are shown as the causes of the diagnostic. This requires the miette crate as a
+/// dependency.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, convert, into_miette};
+///
+/// fn load(path: &str) -> Report<Config> {
+///     convert!(Config::read(path), "unable to load {}", path, code = "E3", help = "run `myapp init`")
+/// }
+///
+/// fn main() -> miette::Result<()> {
+///     let config = load("app.toml").map_err(|error| into_miette!(error))?;
+///     run(config)
+/// }
+///```
+#[proc_macro]
+pub fn into_miette(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(into_miette_builder(item.to_string()).parse().unwrap()))
+}
+
+//  WrapError derive
+/// A derive macro for a newtype struct that wraps an inner error, such as `struct DbError(sqlx::Error)`.
+/// The struct is transparent: `Display` and `Error::source` are those of the inner error, so that
+/// `convert!` preserves the chain of the inner error when linking the struct rather than flattening
+/// it to a single message. `From` is also implemented for the inner error so that the `?` operator
+/// may be used to wrap it.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{Report, ResultExtension, WrapError, convert};
+///
+/// #[derive(Debug, WrapError)]
+/// pub struct DbError(sqlx::Error);
+///
+/// async fn fetch_user(pool: &PgPool, id: i64) -> Result<User, DbError> {
+///     Ok(sqlx::query_as("SELECT * FROM users WHERE id = $1").bind(id).fetch_one(pool).await?)
+/// }
+///
+/// async fn profile(pool: &PgPool, id: i64) -> Report<User> {
+///     let user = convert!(fetch_user(pool, id).await, "unable to fetch user {}", id)?;
+///     Ok(user)
+/// }
+///```
+#[proc_macro_derive(WrapError)]
+pub fn wrap_error(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(wrap_error_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  ExitCode derive
+/// A derive macro that maps each variant of an error to a process exit code using an `#[exit(...)]`
+/// attribute, such as `#[exit(2)]`, which every variant requires. The attribute may also be placed
+/// on a struct. The `fn exit_code(&self) -> i32` method returns the exit code.
+///
+/// The `convert!` macro notes the exit code as metadata of the error when linking such an error,
+/// and `#[nuhound::main]` exits with the first exit code it finds in the chain so that
+/// command line tools have deterministic exit codes.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{ExitCode, Report, ResultExtension, convert};
+///
+/// #[derive(Debug, thiserror::Error, ExitCode)]
+/// enum CliError {
+///     #[error("invalid arguments")]
+///     #[exit(2)]
+///     Usage,
+///     #[error("input file missing")]
+///     #[exit(66)]
+///     NoInput,
+/// }
+///
+/// #[nuhound::main]
+/// fn main() -> Report<()> {
+///     convert!(parse_arguments(), "unable to start")?;
+///     Ok(())
+/// }
+///
+/// // the process exits with code 2 when the arguments are invalid
+///```
+#[proc_macro_derive(ExitCode, attributes(exit))]
+pub fn exit_code(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(exit_code_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+//  NuhoundError derive
+/// A derive macro that implements an error type in full, so that a separate crate such as
+/// `thiserror` is not needed. Each variant, or the struct itself, requires a
+/// `#[nuhound(display = "...")]` attribute giving its `Display` output. The format may refer to
+/// tuple fields by position, such as `{0}`, and to named fields by name, such as `{line}`.
+///
+/// The following are implemented:
+/// - `Display` using the display attributes.
+/// - `Error` where the source is the field marked with `#[nuhound(source)]` or
+///   `#[nuhound(from)]`, or a field named `source`.
+/// - `From` for the type of each field marked with `#[nuhound(from)]`, which must be the only field
+///   of its variant.
+/// - `From<MyError> for Nuhound`, as with the `IntoNuhound` derive, linking the source of the error
+///   so that its messages are disclosed. The conversion is also used by the `convert!` macro.
+///
+/// Variants may be given a stable code with `#[nuhound(code = "...")]` as with `IntoNuhound`, which
+/// should not be derived as well.
+///
+/// # Examples
+/// ```ignore
+/// use nuhound::{NuhoundError, Report, ResultExtension, convert};
+///
+/// #[derive(Debug, NuhoundError)]
+/// enum ConfigError {
+///     #[nuhound(display = "unable to read the configuration", code = "E200")]
+///     Read(#[nuhound(from)] std::io::Error),
+///     #[nuhound(display = "line {line}: unknown key '{key}'")]
+///     Key { line: usize, key: String },
+/// }
+///
+/// fn load(path: &str) -> Result<String, ConfigError> {
+///     Ok(std::fs::read_to_string(path)?)
+/// }
+///
+/// fn setup() -> Report<()> {
+///     convert!(load("app.toml"), "unable to set up the application")?;
+///     Ok(())
+/// }
+///
+/// // using `cargo run --features disclose` will emit the following messages when the file is
+/// // missing:
+/// //
+/// // 0: src/main.rs:17:5: unable to set up the application
+/// // 1: [E200] unable to read the configuration
+/// // 2: No such file or directory (os error 2)
+///```
+#[proc_macro_derive(NuhoundError, attributes(nuhound))]
+pub fn nuhound_error(item: TokenStream) -> TokenStream {
+    shielded([item], |[item]| portable(nuhound_error_builder(&Data::parse(item)).parse().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{Field, Fields, Variant};
+    // The built-in test attribute is named explicitly so it isn't confused with the one defined here
+    use core::prelude::v1::test;
+
+    #[test]
+    fn test_custom_builder() {
+        const ATTRIBUTES: &str = r##""Oh dear this failed because of {}", text"##;
+        let result = custom_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "{",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"Oh dear this failed because of {}\", text);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"Oh dear this failed because of {}\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"Oh dear this failed because of {}\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"Oh dear this failed because of {}\", text) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"Oh dear this failed because of {}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(all(feature = \"defmt\", not(feature = \"obfuscate\")))]",
+            "::defmt::error!(\":0:0: Oh dear this failed because of {0}\", text);",
+            "#[cfg(all(feature = \"defmt\", feature = \"obfuscate\"))]",
+            "::defmt::error!(\"[site 1]: Oh dear this failed because of {0}\", text);",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "}",
+        ];
+
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_debug_custom_builder() {
+        const ATTRIBUTES: &str = r##""invariant broken: {}", state"##;
+        let result = debug_custom_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "{",
+            "#[cfg(debug_assertions)]",
+            "let outcome: ::std::result::Result<(), ::nuhound::Nuhound> = {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"invariant broken: {}\", state);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"invariant broken: {}\", state); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"invariant broken: {}\", state); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"invariant broken: {}\", state) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"invariant broken: {}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "};",
+            "#[cfg(not(debug_assertions))]",
+            "let outcome: ::std::result::Result<(), ::nuhound::Nuhound> = ::std::result::Result::Ok(());",
+            "outcome",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_guard_builder() {
+        const ATTRIBUTES: &str = r##"let Config::Loaded(cfg) | Config::Cached(cfg) = state, "config not loaded yet""##;
+        let result = guard_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "#[allow(unused_parens)]",
+            "let (Config::Loaded(cfg) | Config::Cached(cfg)) = state else {",
+            "match {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"config not loaded yet\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"config not loaded yet\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"config not loaded yet\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"config not loaded yet\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"config not loaded yet\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "} {",
+            "::std::result::Result::Err(error) => return ::std::result::Result::Err(::std::convert::From::from(error)),",
+            "::std::result::Result::Ok(()) => ::std::unreachable!(),",
+            "}",
+            "};",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn assignment() {
+        let result = split_assignment("Some(n @ 1..=5) | Some(n @ 7) = lookup(a == b, |x| x >= 3)");
+        let required = Some((
+            "Some(n @ 1..=5) | Some(n @ 7)".to_string(),
+            "lookup(a == b, |x| x >= 3)".to_string(),
+        ));
+        assert_eq!(result, required);
+        assert_eq!(split_assignment("a == b"), None);
+    }
+
+    #[test]
+    fn test_dbg_report_builder() {
+        let result = dbg_report_builder("parse(\"NaN\")".to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "match parse(\"NaN\") {",
+            "outcome => {",
+            "if let ::std::result::Result::Err(error) = &outcome {",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "eprintln!(\"[{}:{}:{}] {} = {}\", file!(), line!(), column!(), stringify!(parse(\"NaN\")), error);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "eprintln!(\"[{}:{}:{}] {} =\\n{}\", file!(), line!(), column!(), stringify!(parse(\"NaN\")), error.trace());",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { eprintln!(\"[{}:{}:{}] {} =\\n{}\", file!(), line!(), column!(), stringify!(parse(\"NaN\")), error.trace()); } else { eprintln!(\"[{}:{}:{}] {} = {}\", file!(), line!(), column!(), stringify!(parse(\"NaN\")), error); } }",
+            "}",
+            "outcome",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_break_custom_builder() {
+        const ATTRIBUTES: &str = r##"'outer, "scan aborted: {}", reason"##;
+        let result = break_custom_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "break 'outer {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"scan aborted: {}\", reason);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"scan aborted: {}\", reason); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"scan aborted: {}\", reason); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"scan aborted: {}\", reason) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"scan aborted: {}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_skip_report_builder() {
+        const ATTRIBUTES: &str = r##"errors, "'{}' is not a number", text"##;
+        let result = skip_report_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "{",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"'{}' is not a number\", text);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"'{}' is not a number\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"'{}' is not a number\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"'{}' is not a number\", text) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"'{}' is not a number\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "errors.push(::nuhound::Nuhound::new(inform));",
+            "continue ;",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn labels() {
+        const ATTRIBUTES: &str = r##"'outer, 'a', '\'', "x""##;
+        let required = vec!["'outer", "'a'", "'\\''", "\"x\""];
+        let result = analyse(ATTRIBUTES.chars());
+        println!("{result:#?}");
+        assert_eq!(result, required);
+    }
+
+    #[test]
+    fn test_placeholder_builder() {
+        let result = placeholder_builder("".to_string(), "not implemented");
+        assert!(result.contains("let inform = format!(\"not implemented\");"));
+
+        const ATTRIBUTES: &str = r##""export to {}", format"##;
+        let result = placeholder_builder(ATTRIBUTES.to_string(), "not yet implemented");
+        println!("{result}");
+        assert!(result.contains(
+            "let inform = format!(\"not yet implemented: {}\", format_args!(\"export to {}\", format));"
+        ));
+    }
+
+    #[test]
+    fn test_examine_builder() {
+        const ATTRIBUTES: &str = r##"text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text"##;
+        let result = examine_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "text.parse::<u32>().report(|cause| {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"Oh dear - '{}' could not be converted to an integer\", text);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"Oh dear - '{}' could not be converted to an integer\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"text.parse::<u32>()\\\"\"); #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] let inform = format!(\"{} in `{}`\", inform, \"text.parse::<u32>()\"); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"Oh dear - '{}' could not be converted to an integer\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"text.parse::<u32>()\\\"\"); #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] let inform = format!(\"{} in `{}`\", inform, \"text.parse::<u32>()\"); inform } } else { format!(\"Oh dear - '{}' could not be converted to an integer\", text) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"Oh dear - '{}' could not be converted to an integer\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(all(feature = \"defmt\", not(feature = \"obfuscate\")))]",
+            "::defmt::error!(\":0:0: Oh dear - '{0}' could not be converted to an integer\", text);",
+            "#[cfg(all(feature = \"defmt\", feature = \"obfuscate\"))]",
+            "::defmt::error!(\"[site 1]: Oh dear - '{0}' could not be converted to an integer\", text);",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(::nuhound::Nuhound::new(inform).caused_by(cause), |error, (key, value)| error.note(key, value))",
+            "})",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_covert_builder() {
+        const ATTRIBUTES: &str = r##"text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text"##;
+        let result = convert_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "text.parse::<u32>().report(|reason| {",
+            "trait Metadata {",
+            "fn __nuhound_code(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_help(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_url(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_severity(&self, _inform: &str) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_exit(&self) -> ::std::option::Option<i32> {",
+            "::std::option::Option::None",
+            "}",
+            "}",
+            "impl<T: ?Sized> Metadata for T {}",
+            "trait NuhoundLink {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "impl NuhoundLink for ::nuhound::Nuhound {",
+            "fn __nuhound_link(self, inform: ::std::string::String, _code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "::nuhound::Nuhound::new(inform).caused_by(self)",
+            "}",
+            "}",
+            "trait Link {",
+            "fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "impl<E: ::std::error::Error> Link for E {",
+            "fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "match code {",
+            "::std::option::Option::Some(code) => {",
+            "let message = format!(\"[{}] {}\", code, self);",
+            "::nuhound::Nuhound::new(inform).caused_by(match self.source() {",
+            "::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(message),",
+            "}.note(\"code\", code))",
+            "}",
+            "::std::option::Option::None => ::nuhound::Nuhound::link(inform, self),",
+            "}",
+            "}",
+            "}",
+            "#[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]",
+            "trait ReportLink {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "#[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]",
+            "fn __nuhound_report_link(error: &(dyn ::std::error::Error + 'static), inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "match code {",
+            "::std::option::Option::Some(code) => {",
+            "let message = format!(\"[{}] {}\", code, error);",
+            "::nuhound::Nuhound::new(inform).caused_by(match error.source() {",
+            "::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(message),",
+            "}.note(\"code\", code))",
+            "}",
+            "::std::option::Option::None => ::nuhound::Nuhound::link(inform, error),",
+            "}",
+            "}",
+            "#[cfg(feature = \"anyhow\")]",
+            "impl ReportLink for ::anyhow::Error {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)",
+            "}",
+            "}",
+            "#[cfg(feature = \"eyre\")]",
+            "impl ReportLink for ::eyre::Report {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)",
+            "}",
+            "}",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"Oh dear - '{}' could not be converted to an integer\", text);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"Oh dear - '{}' could not be converted to an integer\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"text.parse::<u32>()\\\"\"); #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] let inform = format!(\"{} in `{}`\", inform, \"text.parse::<u32>()\"); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"Oh dear - '{}' could not be converted to an integer\", text); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"text.parse::<u32>()\\\"\"); #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] let inform = format!(\"{} in `{}`\", inform, \"text.parse::<u32>()\"); inform } } else { format!(\"Oh dear - '{}' could not be converted to an integer\", text) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"Oh dear - '{}' could not be converted to an integer\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(all(feature = \"defmt\", not(feature = \"obfuscate\")))]",
+            "::defmt::error!(\":0:0: Oh dear - '{0}' could not be converted to an integer\", text);",
+            "#[cfg(all(feature = \"defmt\", feature = \"obfuscate\"))]",
+            "::defmt::error!(\"[site 1]: Oh dear - '{0}' could not be converted to an integer\", text);",
+            "if let ::std::option::Option::Some(severity) = reason.__nuhound_severity(&inform) { notes.push((\"severity\", severity.to_string())); }",
+            "if let ::std::option::Option::Some(exit) = reason.__nuhound_exit() { notes.push((\"exit\", exit.to_string())); }",
+            "let inform = match reason.__nuhound_help() {",
+            "::std::option::Option::Some(advice) => { let advice = advice.to_string(); let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"help\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&advice)) } else { format!(\"{}\\nhelp: {}\", inform, advice) }; notes.push((\"help\", advice)); inform }",
+            "::std::option::Option::None => inform,",
+            "};",
+            "let inform = match reason.__nuhound_url() {",
+            "::std::option::Option::Some(advice) => { let advice = advice.to_string(); let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"url\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&advice)) } else { format!(\"{}\\nsee: {}\", inform, advice) }; notes.push((\"url\", advice)); inform }",
+            "::std::option::Option::None => inform,",
+            "};",
+            "let code = reason.__nuhound_code();",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(reason.__nuhound_link(inform, code), |error, (key, value)| error.note(key, value))",
+            "})",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_convert_let_builder() {
+        const ATTRIBUTES: &str = r##"mut value = text.parse::<u32>(), "couldn't obtain value""##;
+        let result = convert_let_builder(ATTRIBUTES.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "let mut value = match text.parse::<u32>().report(|reason| {",
+            "trait Metadata {",
+            "fn __nuhound_code(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_help(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_url(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_severity(&self, _inform: &str) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_exit(&self) -> ::std::option::Option<i32> {",
+            "::std::option::Option::None",
+            "}",
+            "}",
+            "impl<T: ?Sized> Metadata for T {}",
+            "trait NuhoundLink {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "impl NuhoundLink for ::nuhound::Nuhound {",
+            "fn __nuhound_link(self, inform: ::std::string::String, _code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "::nuhound::Nuhound::new(inform).caused_by(self)",
+            "}",
+            "}",
+            "trait Link {",
+            "fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "impl<E: ::std::error::Error> Link for E {",
+            "fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "match code {",
+            "::std::option::Option::Some(code) => {",
+            "let message = format!(\"[{}] {}\", code, self);",
+            "::nuhound::Nuhound::new(inform).caused_by(match self.source() {",
+            "::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(message),",
+            "}.note(\"code\", code))",
+            "}",
+            "::std::option::Option::None => ::nuhound::Nuhound::link(inform, self),",
+            "}",
+            "}",
+            "}",
+            "#[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]",
+            "trait ReportLink {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "#[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]",
+            "fn __nuhound_report_link(error: &(dyn ::std::error::Error + 'static), inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "match code {",
+            "::std::option::Option::Some(code) => {",
+            "let message = format!(\"[{}] {}\", code, error);",
+            "::nuhound::Nuhound::new(inform).caused_by(match error.source() {",
+            "::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(message),",
+            "}.note(\"code\", code))",
+            "}",
+            "::std::option::Option::None => ::nuhound::Nuhound::link(inform, error),",
+            "}",
+            "}",
+            "#[cfg(feature = \"anyhow\")]",
+            "impl ReportLink for ::anyhow::Error {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)",
+            "}",
+            "}",
+            "#[cfg(feature = \"eyre\")]",
+            "impl ReportLink for ::eyre::Report {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)",
+            "}",
+            "}",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"couldn't obtain value\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"couldn't obtain value\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"text.parse::<u32>()\\\"\"); #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] let inform = format!(\"{} in `{}`\", inform, \"text.parse::<u32>()\"); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"couldn't obtain value\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); #[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"text.parse::<u32>()\\\"\"); #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] let inform = format!(\"{} in `{}`\", inform, \"text.parse::<u32>()\"); inform } } else { format!(\"couldn't obtain value\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"couldn't obtain value\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "if let ::std::option::Option::Some(severity) = reason.__nuhound_severity(&inform) { notes.push((\"severity\", severity.to_string())); }",
+            "if let ::std::option::Option::Some(exit) = reason.__nuhound_exit() { notes.push((\"exit\", exit.to_string())); }",
+            "let inform = match reason.__nuhound_help() {",
+            "::std::option::Option::Some(advice) => { let advice = advice.to_string(); let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"help\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&advice)) } else { format!(\"{}\\nhelp: {}\", inform, advice) }; notes.push((\"help\", advice)); inform }",
+            "::std::option::Option::None => inform,",
+            "};",
+            "let inform = match reason.__nuhound_url() {",
+            "::std::option::Option::Some(advice) => { let advice = advice.to_string(); let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"url\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&advice)) } else { format!(\"{}\\nsee: {}\", inform, advice) }; notes.push((\"url\", advice)); inform }",
+            "::std::option::Option::None => inform,",
+            "};",
+            "let code = reason.__nuhound_code();",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(reason.__nuhound_link(inform, code), |error, (key, value)| error.note(key, value))",
+            "}) {",
+            "::std::result::Result::Ok(value) => value,",
+            "::std::result::Result::Err(error) => return ::std::result::Result::Err(::std::convert::From::from(error)),",
+            "};",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_context_builder() {
+        const ATTRIBUTE: &str = r##""loading configuration from '{}'", path"##;
+        const ITEM: &str = r##"#[inline] pub(crate) fn load_config(path: &str) -> Report<String> { let text = read(path)?; Ok(text) }"##;
+        let result = context_builder(ATTRIBUTE.to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "#[inline]",
+            "pub(crate) fn load_config(path: &str) -> Report<String> {",
+            "use ::nuhound::ResultExtension as _;",
+            "(|| -> Report<String> { let text = read(path)?; Ok(text) })().report(|cause| {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"loading configuration from '{}'\", path);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"loading configuration from '{}'\", path); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"loading configuration from '{}'\", path); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"loading configuration from '{}'\", path) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"loading configuration from '{}'\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(::nuhound::Nuhound::new(inform).caused_by(cause), |error, (key, value)| error.note(key, value))",
+            "})",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_main_builder() {
+        const ITEM: &str = r##"#[tokio::main] async fn main() -> Report<()> { __nuhound_body }"##;
+        let result = main_builder(String::new(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn main() {",
+            "#[tokio::main]",
+            "async fn main() -> Report<()> {",
+            "__nuhound_body",
+            "}",
+            "if let ::std::result::Result::Err(error) = main() {",
+            "let exit = {",
+            "let noted = |layer: &(dyn ::std::error::Error + 'static), key: &str| -> ::std::option::Option<::std::string::String> { layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key).map(::std::string::ToString::to_string) };",
+            "::std::iter::successors(::std::option::Option::Some(&(error) as &(dyn ::std::error::Error + 'static)), |layer| ::std::error::Error::source(*layer))",
+            ".find_map(|layer| noted(layer, \"exit\"))",
+            "}",
+            ".and_then(|code| code.parse::<i32>().ok())",
+            ".unwrap_or(1);",
+            "#[cfg(feature = \"miette\")]",
+            "eprintln!(\"{:?}\", { let noted = |layer: &(dyn ::std::error::Error + 'static), key: &str| -> ::std::option::Option<::std::string::String> { layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key).map(::std::string::ToString::to_string) }; { #[derive(Debug)] struct NuhoundDiagnostic { error: ::nuhound::Nuhound, message: ::std::string::String, code: ::std::option::Option<::std::string::String>, help: ::std::option::Option<::std::string::String>, url: ::std::option::Option<::std::string::String>, source: ::std::option::Option<::miette::NamedSource<::std::string::String>>, label: ::std::option::Option<::miette::LabeledSpan>, } impl ::std::fmt::Display for NuhoundDiagnostic { fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result { f.write_str(&self.message) } } impl ::std::error::Error for NuhoundDiagnostic { fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> { ::std::error::Error::source(&self.error) } } impl ::miette::Diagnostic for NuhoundDiagnostic { fn code<'a>(&'a self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::fmt::Display + 'a>> { self.code.as_ref().map(|code| ::std::boxed::Box::new(code) as ::std::boxed::Box<dyn ::std::fmt::Display + 'a>) } fn help<'a>(&'a self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::fmt::Display + 'a>> { self.help.as_ref().map(|help| ::std::boxed::Box::new(help) as ::std::boxed::Box<dyn ::std::fmt::Display + 'a>) } fn url<'a>(&'a self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::fmt::Display + 'a>> { self.url.as_ref().map(|url| ::std::boxed::Box::new(url) as ::std::boxed::Box<dyn ::std::fmt::Display + 'a>) } fn source_code(&self) -> ::std::option::Option<&dyn ::miette::SourceCode> { self.source.as_ref().map(|source| source as &dyn ::miette::SourceCode) } fn labels(&self) -> ::std::option::Option<::std::boxed::Box<dyn ::std::iter::Iterator<Item = ::miette::LabeledSpan> + '_>> { self.label.clone().map(|label| ::std::boxed::Box::new(::std::iter::once(label)) as ::std::boxed::Box<dyn ::std::iter::Iterator<Item = ::miette::LabeledSpan>>) } } let code = noted(&error, \"code\"); let message = error.to_string().lines().next().unwrap_or_default().to_string(); let message = match code.as_deref().and_then(|code| message.strip_prefix(&format!(\"[{}] \", code))) { ::std::option::Option::Some(rest) => rest.to_string(), ::std::option::Option::None => message, }; let span = noted(&error, \"label\").and_then(|label| { let (start, end) = label.split_once(\"..\")?; ::std::option::Option::Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()?)) }); let (source, label) = match (noted(&error, \"snippet\"), span) { (::std::option::Option::Some(snippet), ::std::option::Option::Some((start, end))) if start <= end && end <= snippet.len() => { let name = match (noted(&error, \"file\"), noted(&error, \"line\")) { (::std::option::Option::Some(file), ::std::option::Option::Some(line)) => format!(\"{}:{}\", file, line), (file, _) => file.unwrap_or_default(), }; let label = ::miette::LabeledSpan::new(::std::option::Option::Some(message.clone()), start, end - start); (::std::option::Option::Some(::miette::NamedSource::new(name, snippet)), ::std::option::Option::Some(label)) } _ => (::std::option::Option::None, ::std::option::Option::None), }; let (help, url) = (noted(&error, \"help\"), noted(&error, \"url\")); ::miette::Report::new(NuhoundDiagnostic { error, message, code, help, url, source, label }) } });",
+            "#[cfg(not(feature = \"miette\"))]",
+            "{",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "eprintln!(\"Error: {}\", error);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ let noted = |layer: &(dyn ::std::error::Error + 'static), key: &str| -> ::std::option::Option<::std::string::String> { layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key).map(::std::string::ToString::to_string) }; { let mut limit = ::std::option::Option::None; let mut layer: ::std::option::Option<&(dyn ::std::error::Error + 'static)> = ::std::option::Option::Some(&error); let mut index = 0; while let ::std::option::Option::Some(current) = layer { let depth = noted(current, \"depth\").and_then(|depth| depth.parse::<usize>().ok()); if let ::std::option::Option::Some(depth) = depth { limit = ::std::option::Option::Some(index + depth + 1); break; } index += 1; layer = current.source(); } match limit { ::std::option::Option::None => eprintln!(\"Error:\\n{}\", error.trace()), ::std::option::Option::Some(limit) => { eprintln!(\"Error:\"); let mut layer: ::std::option::Option<&dyn ::std::error::Error> = ::std::option::Option::Some(&error); let mut index = 0; while let ::std::option::Option::Some(current) = layer { if index == limit { let hidden = ::std::iter::successors(::std::option::Option::Some(current), |layer| ::std::error::Error::source(*layer)).count(); eprintln!(\"... {} more layers\", hidden); break; } eprintln!(\"{}: {}\", index, current); index += 1; layer = current.source(); } } } } }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let noted = |layer: &(dyn ::std::error::Error + 'static), key: &str| -> ::std::option::Option<::std::string::String> { layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key).map(::std::string::ToString::to_string) }; { let mut limit = ::std::option::Option::None; let mut layer: ::std::option::Option<&(dyn ::std::error::Error + 'static)> = ::std::option::Option::Some(&error); let mut index = 0; while let ::std::option::Option::Some(current) = layer { let depth = noted(current, \"depth\").and_then(|depth| depth.parse::<usize>().ok()); if let ::std::option::Option::Some(depth) = depth { limit = ::std::option::Option::Some(index + depth + 1); break; } index += 1; layer = current.source(); } match limit { ::std::option::Option::None => eprintln!(\"Error:\\n{}\", error.trace()), ::std::option::Option::Some(limit) => { eprintln!(\"Error:\"); let mut layer: ::std::option::Option<&dyn ::std::error::Error> = ::std::option::Option::Some(&error); let mut index = 0; while let ::std::option::Option::Some(current) = layer { if index == limit { let hidden = ::std::iter::successors(::std::option::Option::Some(current), |layer| ::std::error::Error::source(*layer)).count(); eprintln!(\"... {} more layers\", hidden); break; } eprintln!(\"{}: {}\", index, current); index += 1; layer = current.source(); } } } } } } else { eprintln!(\"Error: {}\", error); } }",
+            "}",
+            "::std::process::exit(exit);",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    #[should_panic(expected = "Function 'main' must not be async unless it is given a runtime attribute, such as #[tokio::main], below the main attribute")]
+    fn test_main_builder_async() {
+        main_builder(String::new(), "async fn main() -> Report<()> { __nuhound_body }".to_string());
+    }
+
+    #[test]
+    fn test_test_builder() {
+        const ITEM: &str = r##"#[should_panic(expected = "failed")] fn parses() -> Report<()> { __nuhound_body }"##;
+        let result = test_builder(String::new(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "#[test]",
+            "#[should_panic(expected = \"failed\")]",
+            "fn parses() {",
+            "fn parses() -> Report<()> {",
+            "__nuhound_body",
+            "}",
+            "if let ::std::result::Result::Err(error) = parses() {",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "panic!(\"Error: {}\", error);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "panic!(\"Error:\\n{}\", error.trace());",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { panic!(\"Error:\\n{}\", error.trace()); } else { panic!(\"Error: {}\", error); } }",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_instrument_builder() {
+        const ATTRIBUTE: &str = r##"skip(password), name = "sign_in""##;
+        const ITEM: &str = r##"fn login(&self, user: &str, password: &str, mut attempt: u32, (a, b): (u8, u8)) -> Report<()> { __nuhound_body }"##;
+        let result = instrument_builder(ATTRIBUTE.to_string(), ITEM.to_string());
+        println!("{result}");
+        assert!(result.contains(
+            "format!(\"{}::{}(user={}, attempt={})\", module_path!(), \"sign_in\", (&Probe(&user)).show(), (&Probe(&attempt)).show())"
+        ));
+        assert!(result.contains("(|| -> Report<()> { __nuhound_body })().report(|cause| {"));
+        assert!(result.contains("let inform = format!(\"{}\", __nuhound_call);"));
+
+        let result = instrument_builder("skip_all".to_string(), ITEM.to_string());
+        assert!(result.contains("let __nuhound_call = format!(\"{}::{}()\", module_path!(), \"login\");"));
+        assert!(!result.contains("Probe"));
+    }
+
+    #[test]
+    fn test_trace_generator() {
+        let result = trace_generator("text.parse::<u32>()").unwrap();
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "__nuhound_expression.report(|reason| {",
+            "trait Metadata {",
+            "fn __nuhound_code(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_help(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_url(&self) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_severity(&self, _inform: &str) -> ::std::option::Option<&'static str> {",
+            "::std::option::Option::None",
+            "}",
+            "fn __nuhound_exit(&self) -> ::std::option::Option<i32> {",
+            "::std::option::Option::None",
+            "}",
+            "}",
+            "impl<T: ?Sized> Metadata for T {}",
+            "trait NuhoundLink {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "impl NuhoundLink for ::nuhound::Nuhound {",
+            "fn __nuhound_link(self, inform: ::std::string::String, _code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "::nuhound::Nuhound::new(inform).caused_by(self)",
+            "}",
+            "}",
+            "trait Link {",
+            "fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "impl<E: ::std::error::Error> Link for E {",
+            "fn __nuhound_link(&self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "match code {",
+            "::std::option::Option::Some(code) => {",
+            "let message = format!(\"[{}] {}\", code, self);",
+            "::nuhound::Nuhound::new(inform).caused_by(match self.source() {",
+            "::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(message),",
+            "}.note(\"code\", code))",
+            "}",
+            "::std::option::Option::None => ::nuhound::Nuhound::link(inform, self),",
+            "}",
+            "}",
+            "}",
+            "#[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]",
+            "trait ReportLink {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound;",
+            "}",
+            "#[cfg(any(feature = \"anyhow\", feature = \"eyre\"))]",
+            "fn __nuhound_report_link(error: &(dyn ::std::error::Error + 'static), inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "match code {",
+            "::std::option::Option::Some(code) => {",
+            "let message = format!(\"[{}] {}\", code, error);",
+            "::nuhound::Nuhound::new(inform).caused_by(match error.source() {",
+            "::std::option::Option::Some(source) => ::nuhound::Nuhound::link(message, source),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(message),",
+            "}.note(\"code\", code))",
+            "}",
+            "::std::option::Option::None => ::nuhound::Nuhound::link(inform, error),",
+            "}",
+            "}",
+            "#[cfg(feature = \"anyhow\")]",
+            "impl ReportLink for ::anyhow::Error {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)",
+            "}",
+            "}",
+            "#[cfg(feature = \"eyre\")]",
+            "impl ReportLink for ::eyre::Report {",
+            "fn __nuhound_link(self, inform: ::std::string::String, code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)",
+            "}",
+            "}",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"{}\", \"text.parse::<u32>()\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"{}\", \"text.parse::<u32>()\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"{}\", \"text.parse::<u32>()\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&reason).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"{}\", \"text.parse::<u32>()\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"{}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "if let ::std::option::Option::Some(severity) = reason.__nuhound_severity(&inform) { notes.push((\"severity\", severity.to_string())); }",
+            "if let ::std::option::Option::Some(exit) = reason.__nuhound_exit() { notes.push((\"exit\", exit.to_string())); }",
+            "let inform = match reason.__nuhound_help() {",
+            "::std::option::Option::Some(advice) => { let advice = advice.to_string(); let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"help\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&advice)) } else { format!(\"{}\\nhelp: {}\", inform, advice) }; notes.push((\"help\", advice)); inform }",
+            "::std::option::Option::None => inform,",
+            "};",
+            "let inform = match reason.__nuhound_url() {",
+            "::std::option::Option::Some(advice) => { let advice = advice.to_string(); let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"url\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&advice)) } else { format!(\"{}\\nsee: {}\", inform, advice) }; notes.push((\"url\", advice)); inform }",
+            "::std::option::Option::None => inform,",
+            "};",
+            "let code = reason.__nuhound_code();",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(reason.__nuhound_link(inform, code), |error, (key, value)| error.note(key, value))",
+            "})",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_retry_builder() {
+        const ITEM: &str = r##"fn fetch(url: &str) -> Report<String> { __nuhound_body }"##;
+        let result = retry_builder("attempts = 3, delay = \"200ms\"".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn fetch(url: &str) -> Report<String> {",
+            "let mut __nuhound_causes = ::std::string::String::new();",
+            "let mut __nuhound_attempt = 0;",
+            "loop {",
+            "__nuhound_attempt += 1;",
+            "let error = match (|| -> Report<String> { __nuhound_body })() {",
+            "::std::result::Result::Ok(value) => return ::std::result::Result::Ok(value),",
+            "::std::result::Result::Err(error) => error,",
+            "};",
+            "let retriable = {",
+            "trait Retriable {",
+            "fn __nuhound_retriable(&self) -> bool {",
+            "true",
+            "}",
+            "}",
+            "impl<T: ?Sized> Retriable for &T {}",
+            "trait Classified: ::std::error::Error + Sized + 'static {",
+            "fn __nuhound_retriable(&self) -> bool {",
+            "let noted = |layer: &(dyn ::std::error::Error + 'static), key: &str| -> ::std::option::Option<::std::string::String> { layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key).map(::std::string::ToString::to_string) };",
+            "let mut layer: ::std::option::Option<&(dyn ::std::error::Error + 'static)> = ::std::option::Option::Some(self); while let ::std::option::Option::Some(current) = layer { match noted(current, \"retriable\").as_deref() { ::std::option::Option::Some(\"true\") => return true, ::std::option::Option::Some(\"false\") => return false, _ => layer = current.source(), } } true",
+            "}",
+            "}",
+            "impl<T: ::std::error::Error + 'static> Classified for T {}",
+            "(&error).__nuhound_retriable()",
+            "};",
+            "if __nuhound_attempt >= 3 || !retriable {",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"failed after {} attempts{}\", __nuhound_attempt, __nuhound_causes);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"failed after {} attempts{}\", __nuhound_attempt, __nuhound_causes); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"failed after {} attempts{}\", __nuhound_attempt, __nuhound_causes); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"failed after {} attempts{}\", __nuhound_attempt, __nuhound_causes) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"failed after {} attempts{}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "trait NuhoundCause {",
+            "fn __nuhound_cause(self, inform: ::std::string::String) -> ::nuhound::Nuhound;",
+            "}",
+            "impl NuhoundCause for ::nuhound::Nuhound {",
+            "fn __nuhound_cause(self, inform: ::std::string::String) -> ::nuhound::Nuhound {",
+            "::nuhound::Nuhound::new(inform).caused_by(self)",
+            "}",
+            "}",
+            "trait Cause {",
+            "fn __nuhound_cause(&self, inform: ::std::string::String) -> ::nuhound::Nuhound;",
+            "}",
+            "impl<E: ::std::error::Error> Cause for E {",
+            "fn __nuhound_cause(&self, inform: ::std::string::String) -> ::nuhound::Nuhound {",
+            "::nuhound::Nuhound::link(inform, self)",
+            "}",
+            "}",
+            "return ::std::result::Result::Err(::std::convert::From::from(error.__nuhound_cause(inform)));",
+            "}",
+            "__nuhound_causes.push_str(&format!(\"; attempt {}: {}\", __nuhound_attempt, error));",
+            "::std::thread::sleep(::std::time::Duration::from_nanos(200000000));",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_retry_runtime() {
+        const ITEM: &str = r##"async fn fetch(url: &str) -> Report<String> { __nuhound_body }"##;
+        let result = retry_builder("attempts = 2, delay = \"1s\"".to_string(), ITEM.to_string());
+        assert!(result.contains("#[cfg(feature = \"tokio\")]\n        ::tokio::time::sleep(::std::time::Duration::from_nanos(1000000000)).await;"));
+        assert!(result.contains("::async_std::task::sleep(::std::time::Duration::from_nanos(1000000000)).await;"));
+        assert!(result.contains("::std::compile_error!(\"Async functions require either the 'tokio' or the 'async-std' feature\");"));
+    }
+
+    #[test]
+    fn test_deadline_builder() {
+        const ITEM: &str = r##"fn index() -> Report<u32> { __nuhound_body }"##;
+        let result = deadline_builder("\"5s\"".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn index() -> Report<u32> {",
+            "let __nuhound_start = ::std::time::Instant::now();",
+            "let outcome = (|| -> Report<u32> { __nuhound_body })();",
+            "if __nuhound_start.elapsed() <= ::std::time::Duration::from_nanos(5000000000) {",
+            "return outcome;",
+            "}",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"deadline exceeded after {} in {}::{}\", \"5s\", module_path!(), \"index\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"deadline exceeded after {} in {}::{}\", \"5s\", module_path!(), \"index\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"deadline exceeded after {} in {}::{}\", \"5s\", module_path!(), \"index\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"deadline exceeded after {} in {}::{}\", \"5s\", module_path!(), \"index\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"deadline exceeded after {} in {}::{}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "let error = ::nuhound::Nuhound::new(inform);",
+            "match outcome {",
+            "::std::result::Result::Ok(_) => ::std::result::Result::Err(error),",
+            "::std::result::Result::Err(cause) => ::std::result::Result::Err(error.caused_by(cause)),",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+
+        let result = deadline_builder("\"200ms\"".to_string(), format!("async {ITEM}"));
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"tokio\")]\n    let outcome = ::tokio::time::timeout(::std::time::Duration::from_nanos(200000000), future).await;"));
+        assert!(result.contains("#[cfg(all(feature = \"async-std\", not(feature = \"tokio\")))]\n    let outcome = ::async_std::future::timeout(::std::time::Duration::from_nanos(200000000), future).await;"));
+    }
+
+    #[test]
+    fn test_catch_panics_builder() {
+        const ITEM: &str = r##"fn index() -> Report<u32> { __nuhound_body }"##;
+        let result = catch_panics_builder("".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn index() -> Report<u32> {",
+            "let outcome = ::std::panic::catch_unwind(|| -> Report<u32> { __nuhound_body });",
+            "match outcome {",
+            "::std::result::Result::Ok(outcome) => outcome,",
+            "::std::result::Result::Err(payload) => {",
+            "let panic = if let ::std::option::Option::Some(message) = payload.downcast_ref::<&str>() {",
+            "message.to_string()",
+            "} else if let ::std::option::Option::Some(message) = payload.downcast_ref::<::std::string::String>() {",
+            "message.clone()",
+            "} else {",
+            "\"unknown panic payload\".to_string()",
+            "};",
+            "{",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"panicked in {}::{}: {}\", module_path!(), \"index\", panic);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"panicked in {}::{}: {}\", module_path!(), \"index\", panic); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"panicked in {}::{}: {}\", module_path!(), \"index\", panic); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"panicked in {}::{}: {}\", module_path!(), \"index\", panic) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"panicked in {}::{}: {}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "}",
+            "}",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+
+        let result = catch_panics_builder("assert_unwind_safe".to_string(), ITEM.to_string());
+        assert!(result.contains("::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| -> Report<u32> { __nuhound_body }));"));
+
+        let result = catch_panics_builder("".to_string(), format!("async {ITEM}"));
+        println!("{result}");
+        assert!(result.contains("::std::future::Future::poll(future.as_mut(), context)"));
+    }
+
+    #[test]
+    fn test_report_builder() {
+        const ITEM: &str = r##"fn load(&self, key: &str) -> Report<String> { __nuhound_body }"##;
+        let result = report_builder(&[], ITEM.to_string()).unwrap();
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn load(&self, key: &str) -> Report<String> {",
+            "use ::nuhound::ResultExtension as _;",
+            "(|| -> Report<String> { __nuhound_body })().report(|cause| {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"{}::{}\", ::std::any::type_name::<Self>(), \"load\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"{}::{}\", ::std::any::type_name::<Self>(), \"load\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"{}::{}\", ::std::any::type_name::<Self>(), \"load\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"{}::{}\", ::std::any::type_name::<Self>(), \"load\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"{}::{}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(::nuhound::Nuhound::new(inform).caused_by(cause), |error, (key, value)| error.note(key, value))",
+            "})",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+
+        assert!(report_builder(&["load".to_string()], ITEM.to_string()).is_none());
+        assert!(report_builder(&[], "fn len(&self) -> usize { __nuhound_body }".to_string()).is_none());
+        assert!(report_builder(&[], "fn parse(&self) -> Result<u8, ParseIntError> { __nuhound_body }".to_string()).is_none());
+        assert!(report_builder(&[], "fn parse(&self) -> Result<u8, nuhound::Nuhound> { __nuhound_body }".to_string()).is_some());
+    }
+
+    #[test]
+    fn test_timed_builder() {
+        const ITEM: &str = r##"async fn load_index() -> Report<u32> { __nuhound_body }"##;
+        let result = timed_builder("".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "async fn load_index() -> Report<u32> {",
+            "use ::nuhound::ResultExtension as _;",
+            "let __nuhound_start = ::std::time::Instant::now();",
+            "async {",
+            "let outcome: Report<u32> = { __nuhound_body };",
+            "outcome",
+            "}.await.report(|cause| {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"{} failed after {:.1?}\", \"load_index\", __nuhound_start.elapsed());",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"{} failed after {:.1?}\", \"load_index\", __nuhound_start.elapsed()); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"{} failed after {:.1?}\", \"load_index\", __nuhound_start.elapsed()); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); let inform = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros()), ::std::option::Option::None => inform, }; inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = match { trait NoTime { fn __nuhound_time(&self) -> ::std::option::Option<&str> { ::std::option::Option::None } } impl<T: ?Sized> NoTime for &T {} trait Time { fn __nuhound_time(&self) -> ::std::option::Option<&str>; } impl Time for ::nuhound::Nuhound { fn __nuhound_time(&self) -> ::std::option::Option<&str> { self.noted(\"time\") } } (&cause).__nuhound_time() .and_then(|time| { let (seconds, micros) = time.split_once('.')?; ::std::option::Option::Some(::std::time::Duration::new(seconds.parse().ok()?, micros.parse::<u32>().ok()? * 1000)) }) .and_then(|since| ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default().checked_sub(since)) } { ::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }, elapsed), ::std::option::Option::None => format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }), }; #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"{} failed after {:.1?}\", \"load_index\", __nuhound_start.elapsed()) } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"{} failed after {:.1?}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "notes.into_iter().fold(::nuhound::Nuhound::new(inform).caused_by(cause), |error, (key, value)| error.note(key, value))",
+            "})",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_ensure_builder() {
+        const ITEM: &str = r##"#[nuhound :: ensure(limit > 0)] #[inline] fn read(path: &str, limit: usize) -> Report<u32> { __nuhound_body }"##;
+        let result = ensure_builder("! path.is_empty(), \"path must not be empty\"".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "#[inline]",
+            "fn read(path: &str, limit: usize) -> Report<u32> {",
+            "if !(! path.is_empty()) {",
+            "return {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"path must not be empty\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"path must not be empty\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"path must not be empty\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"path must not be empty\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"path must not be empty\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "};",
+            "}",
+            "if !(limit > 0) {",
+            "return {",
+            "#[allow(unused_mut)]",
+            "let mut notes: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new();",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let inform = format!(\"precondition failed: {}\", \"limit > 0\");",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let inform = { let message = format!(\"precondition failed: {}\", \"limit > 0\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform };",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let inform = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { let message = format!(\"precondition failed: {}\", \"limit > 0\"); #[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json }; let inform = format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":1\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = ::std::string::String::new(); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\",\\\"line\\\":{}\", line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!()); location }; location }, escape(&message)); #[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } })); #[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let inform = format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros()); inform }; #[cfg(feature = \"disclose-version\")] let inform = format!(\"{},\\\"version\\\":{},\\\"profile\\\":{}}}\", &inform[..inform.len() - 1], escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" })); let inform = match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => inform, build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build)), }; inform }; #[cfg(not(feature = \"disclose-json\"))] let inform = [&*{ #[cfg(feature = \"obfuscate\")] let location = \"[site 1]\"; #[cfg(not(feature = \"obfuscate\"))] let location = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!()); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!()); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!()); location }; location }, &*match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }, \": \", &*{ #[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" }); #[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }, &*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); let seconds = time.as_secs(); format!(\"{:02}:{:02}:{:02}.{:06}\", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60, time.subsec_micros()) }); #[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }, &*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current(); match thread.name() { ::std::option::Option::Some(name) => format!(\"{} {:?}\", name, thread.id()), ::std::option::Option::None => format!(\"{:?}\", thread.id()), } }); #[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }, &*{ #[cfg(feature = \"disclose-fn\")] let function = { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat(); inform } } else { format!(\"precondition failed: {}\", \"limit > 0\") } };",
+            "#[cfg(feature = \"fingerprint\")]",
+            "let inform = { const FINGERPRINT: u32 = { let bytes = concat!(file!(), \":\", \"\\\"precondition failed: {}\\\"\").as_bytes(); let mut hash: u32 = 0x811c_9dc5; let mut index = 0; while index < bytes.len() { hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193); index += 1; } hash & 0xff_ffff }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT) } else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };",
+            "#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]",
+            "let inform = if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"site\\\":1}}\", &inform[..inform.len() - 1]) } else { format!(\"[site 1] {}\", inform) };",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "let inform = { let hash = { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) }; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash) } else { format!(\"[fn {:06x}] {}\", hash, inform) } };",
+            "#[cfg(feature = \"count-repeats\")]",
+            "let inform = { static LAST: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); static REPEATS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0); let hash = inform.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)); if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash { REPEATS.store(0, ::std::sync::atomic::Ordering::Relaxed); inform } else { let repeats = REPEATS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) + 1; if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats) } else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };",
+            "#[cfg(feature = \"site-counts\")]",
+            "crate::__NUHOUND_SITE_COUNTS[0].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);",
+            "#[cfg(feature = \"metrics\")]",
+            "::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \"1\"; #[cfg(not(feature = \"obfuscate\"))] let location = concat!(file!(), \":\", line!()); location }, \"code\" => \"\").increment(1);",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "{ #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); }",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { { #[cfg(not(feature = \"obfuscate\"))] { let (file, line, column): (::std::string::String, ::std::option::Option<::std::string::String>, ::std::option::Option<::std::string::String>) = { #[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = (file!().to_string(), ::std::option::Option::None, ::std::option::Option::None); #[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None); #[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::Some(column!().to_string())); location }; notes.push((\"file\", file)); notes.extend(line.map(|line| (\"line\", line))); notes.extend(column.map(|column| (\"col\", column))); } #[cfg(feature = \"obfuscate\")] notes.push((\"site\", \"1\".to_string())); } } else {  } }",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) }));",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "{ static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) {",
+            "#[cfg(feature = \"disclose-time\")]",
+            "notes.push((\"time\", { let time = ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH).unwrap_or_default(); format!(\"{}.{:06}\", time.as_secs(), time.subsec_micros()) })); } else {  } }",
+            "#[cfg(feature = \"fingerprint-fn\")]",
+            "notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new(); *FUNCTION_HASH.get_or_init(|| { fn __nuhound_function() {} fn __nuhound_name<T>(_: T) -> &'static str { ::std::any::type_name::<T>() } let mut name = __nuhound_name(__nuhound_function).trim_end_matches(\"::__nuhound_function\"); while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\") { name = outer; } name.bytes().fold(0x811c_9dc5_u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)) & 0xff_ffff }) })));",
+            "#[cfg(feature = \"backtrace\")]",
+            "let inform = { let backtrace = match ::std::env::var(\"NUHOUND_BACKTRACE\") { ::std::result::Result::Ok(value) if value.is_empty() || value == \"0\" || value == \"false\" => ::std::backtrace::Backtrace::disabled(), ::std::result::Result::Ok(_) => ::std::backtrace::Backtrace::force_capture(), ::std::result::Result::Err(_) => ::std::backtrace::Backtrace::capture(), }; if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&backtrace.to_string())) } else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };",
+            "#[cfg(feature = \"span-fields\")]",
+            "let inform = { let mut spans: ::std::vec::Vec<(&str, ::std::string::String)> = ::std::vec::Vec::new(); ::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| { if !fields.is_empty() { spans.push((metadata.name(), fields.to_string())); } true }); if spans.is_empty() { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter() .map(|(name, fields)| format!(\"{{\\\"name\\\":{},\\\"fields\\\":{}}}\", (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(name), (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(fields))) .collect::<::std::vec::Vec<_>>().join(\",\")) } else { format!(\"{} [spans: {}]\", inform, spans.iter() .map(|(name, fields)| format!(\"{}{{{}}}\", name, fields)) .collect::<::std::vec::Vec<_>>().join(\", \")) } } };",
+            "#[cfg(feature = \"span-trace\")]",
+            "let inform = { let span_trace = ::tracing_error::SpanTrace::capture(); if span_trace.status() != ::tracing_error::SpanTraceStatus::CAPTURED { inform } else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str| { let mut json = ::std::string::String::from(\"\\\"\"); for c in text.chars() { match c { '\"' => json.push_str(\"\\\\\\\"\"), '\\\\' => json.push_str(\"\\\\\\\\\"), c if c.is_control() => json.push_str(&format!(\"\\\\u{:04x}\", c as u32)), c => json.push(c), } } json.push('\"'); json })(&span_trace.to_string())) } else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };",
+            "::std::result::Result::Err(notes.into_iter().fold(::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value)))",
+            "};",
+            "}",
+            "__nuhound_body",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+
+        assert_eq!(ensure_arguments("#[ensure(a, \"b\")]").unwrap(), "a, \"b\"");
+        assert!(ensure_arguments("#[nuhound :: context(\"b\")]").is_none());
+        assert!(ensure_arguments("#[doc = \" ensure(x)\"]").is_none());
+    }
+
+    #[test]
+    fn test_fallback_builder() {
+        const ITEM: &str = r##"fn warm() -> Report<usize> { __nuhound_body }"##;
+        let result = fallback_builder("0".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn warm() -> usize {",
+            "match (|| -> Report<usize> { __nuhound_body })() {",
+            "::std::result::Result::Ok(value) => value,",
+            "::std::result::Result::Err(error) => {",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let report = error.to_string();",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let report = error.trace();",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let report = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { error.trace() } else { error.to_string() } };",
+            "#[cfg(feature = \"tracing-events\")]",
+            "::tracing::warn!(\"{}::{} failed, using the fallback: {}\", module_path!(), \"warm\", report);",
+            "#[cfg(all(feature = \"log\", not(feature = \"tracing-events\")))]",
+            "::log::warn!(\"{}::{} failed, using the fallback: {}\", module_path!(), \"warm\", report);",
+            "#[cfg(not(any(feature = \"tracing-events\", feature = \"log\")))]",
+            "eprintln!(\"Warning: {}::{} failed, using the fallback: {}\", module_path!(), \"warm\", report);",
+            "0",
+            "}",
+            "}",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+
+        let result = fallback_builder("Vec::new(), sink = record".to_string(), ITEM.to_string());
+        assert!(result.contains("record(&error);"));
+        assert!(result.contains("Vec::new()"));
+    }
+
+    #[test]
+    fn test_should_err_builder() {
+        const ITEM: &str = r##"#[ignore] fn rejects() -> Report<()> { __nuhound_body }"##;
+        let result = should_err_builder("\"bad key\", \"invalid digit\"".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+        println!("{result_parts:#?}");
+
+        assert_eq!(result_parts[..4], ["#[test]", "#[ignore]", "fn rejects() {", "fn rejects() -> Report<()> {"]);
+        assert!(result_parts.contains(&"let ::std::result::Result::Err(error) = rejects() else {"));
+        assert!(result_parts.contains(&"panic!(\"Expected an error but 'rejects' succeeded\");"));
+        assert!(result_parts.contains(&"for expected in [\"bad key\", \"invalid digit\"] {"));
+    }
+
+    #[test]
+    fn test_trace_calls_builder() {
+        const ITEM: &str = r##"fn load(path: &str) -> Report<u32> { __nuhound_body }"##;
+        let result = trace_calls_builder("".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "fn load(path: &str) -> Report<u32> {",
+            "#[cfg(not(any(feature = \"tracing-events\", feature = \"log\")))]",
+            "::std::compile_error!(\"The trace_calls attribute requires either the 'tracing-events' or the 'log' feature\");",
+            "let __nuhound_call = format!(\"{}::{}\", module_path!(), \"load\");",
+            "#[cfg(feature = \"tracing-events\")]",
+            "::tracing::debug!(\"enter {}\", __nuhound_call);",
+            "#[cfg(all(feature = \"log\", not(feature = \"tracing-events\")))]",
+            "::log::debug!(\"enter {}\", __nuhound_call);",
+            "let outcome = (|| -> Report<u32> { __nuhound_body })();",
+            "match &outcome {",
+            "::std::result::Result::Ok(_) => {",
+            "#[cfg(feature = \"tracing-events\")]",
+            "::tracing::debug!(\"exit {}\", __nuhound_call);",
+            "#[cfg(all(feature = \"log\", not(feature = \"tracing-events\")))]",
+            "::log::debug!(\"exit {}\", __nuhound_call);",
+            "}",
+            "::std::result::Result::Err(error) => {",
+            "#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]",
+            "let report = error.to_string();",
+            "#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]",
+            "let report = error.trace();",
+            "#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))))]",
+            "let report = { static DISCLOSE: ::std::sync::OnceLock<bool> = ::std::sync::OnceLock::new(); let enabled = |value: ::std::string::String| !value.is_empty() && value != \"0\" && value != \"false\"; if *DISCLOSE.get_or_init(|| ::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)) { error.trace() } else { error.to_string() } };",
+            "#[cfg(feature = \"tracing-events\")]",
+            "::tracing::warn!(\"exit {} with error:\\n{}\", __nuhound_call, report);",
+            "#[cfg(all(feature = \"log\", not(feature = \"tracing-events\")))]",
+            "::log::warn!(\"exit {} with error:\\n{}\", __nuhound_call, report);",
+            "}",
+            "}",
+            "outcome",
+            "}",
+        ];
+        println!("{result_parts:#?}");
+        assert_eq!(result_parts, required);
+
+        let result = trace_calls_builder("args, level = \"info\"".to_string(), ITEM.to_string());
+        println!("{result}");
+        assert!(result.contains("format!(\"{}::{}(path={})\", module_path!(), \"load\", (&Probe(&path)).show())"));
+        assert!(result.contains("::log::info!(\"enter {}\", __nuhound_call);"));
+    }
+
+    #[test]
+    fn test_quiet_flag() {
+        let result = flags::scoped("@quiet text.parse::<u32>(), \"bad\"", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"bad\");"));
+        assert!(!result.contains("disclose"));
+
+        let result = flags::scoped("@quiet", |attribute| {
+            instrument_builder(attribute.to_string(), "fn login(user: &str) -> Report<()> { __nuhound_body }".to_string())
+        });
+        println!("{result}");
+        assert!(result.contains("let __nuhound_call = format!(\"{}::{}()\", module_path!(), \"login\");"));
+        assert!(!result.contains("disclose"));
+
+        let result = convert_builder("text.parse::<u32>(), \"bad\"".to_string());
+        assert!(result.contains("let inform = { let message = format!(\"bad\");"));
+        let result = flags::scoped("@quiet text.parse::<u32>(), \"bad\"", |item| convert_builder(item.to_string()));
+        assert!(!result.contains("let inform = { let message"));
+        assert_eq!(flags::Flags::split(" @quiet x, y").1, "x, y");
+    }
+
+    #[test]
+    fn test_disclose_flag() {
+        let result = flags::scoped("@disclose text.parse::<u32>(), \"bad\"", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let inform = { let message = format!(\"bad\");"));
+        assert!(!result.contains("let inform = format!(\"bad\");"));
+        assert!(!result.contains("NUHOUND_DISCLOSE"));
+        assert!(flags::Flags::split("@disclose @caller x").0.caller);
+    }
+
+    #[test]
+    fn test_scoped_flags() {
+        let (flags, rest) = flags::Flags::split("@scoped_quiet @scoped_disclose x");
+        assert!(flags.quiet && !flags.disclose);
+        assert_eq!(rest, "x");
+        let (flags, _) = flags::Flags::split("@scoped_disclose @scoped_quiet @caller x");
+        assert!(flags.disclose && !flags.quiet && flags.caller);
+        let (flags, _) = flags::Flags::split("@scoped_quiet @disclose x");
+        assert!(flags.disclose && !flags.quiet);
+    }
+
+    #[test]
+    fn test_sample_flag() {
+        let result = flags::scoped("@sample(100) text.parse::<u32>(), \"bad {}\", text", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let (inform, __nuhound_sampled) = {"));
+        assert!(result.contains("if occurrence % 100 == 0 {"));
+        assert!(result.contains("(format!(\"{} (occurrence {})\", format!(\"bad {}\", text), occurrence + 1), false)"));
+        assert!(result.contains("let inform = if __nuhound_sampled {"));
+        let result = flags::scoped("@sample(10) \"bad\", severity = warn", |item| custom_builder(item.to_string()));
+        assert!(result.contains("if __nuhound_sampled {\n            #[cfg(all(feature = \"tracing-events\", not(feature = \"obfuscate\")))]"));
+        assert!(result.contains("::log::warn!(\"{}\", inform);\n        #[cfg(all(feature = \"defmt\", not(feature = \"obfuscate\")))]"));
+        assert!(!flags::scoped("\"bad\", severity = warn", |item| custom_builder(item.to_string())).contains("if __nuhound_sampled {"));
+        let (flags, rest) = flags::Flags::split("@sample ( 5 ) @caller x");
+        assert_eq!(flags.sample, Some(5));
+        assert!(flags.caller);
+        assert_eq!(rest, "x");
+        assert!(!custom_builder("\"bad\"".to_string()).contains("__nuhound_sampled"));
+    }
+
+    #[test]
+    fn test_at_flag() {
+        let (flags, rest) = flags::Flags::split("@at(\"src/a (1).rs\", 5, 18) @quiet fail(2), \"inner\"");
+        assert!(flags.quiet);
+        assert_eq!(rest, "fail(2), \"inner\"");
+        assert_eq!(flags::Flags::split("@at (\"src/main.rs\", 1, 1) x").1, "x");
+    }
+
+    #[test]
+    fn test_depth_flag() {
+        let result = flags::scoped("@depth(3) text.parse::<u32>(), \"bad\"", |item| examine_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("notes.push((\"depth\", \"3\".to_string()));"));
+        assert_eq!(flags::Flags::split("@depth(2) x").0.depth, Some(2));
+        assert!(!custom_builder("\"bad\"".to_string()).contains("\"depth\""));
+    }
+
+    #[test]
+    fn test_caller_flag() {
+        let (flags, rest) = flags::Flags::split("@caller(1) x");
+        assert!(flags.caller && flags.shim);
+        assert_eq!(rest, "x");
+        let (flags, rest) = flags::Flags::split("@caller (x).unwrap()");
+        assert!(flags.caller && !flags.shim);
+        assert_eq!(rest, "(x).unwrap()");
+        let result = flags::scoped("@caller(1) text.parse::<u32>(), \"bad number\"", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.trim().starts_with("{\n        let __nuhound_caller = ::std::panic::Location::caller();\n        text.parse::<u32>().report(|reason| {"));
+        assert!(result.contains("__nuhound_caller.line(), __nuhound_caller.column()"));
+        assert!(!custom_builder("\"bad\"".to_string()).contains("::std::panic::Location::caller()"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The '@caller' flag can only report the immediate caller")]
+    fn test_caller_flag_depth() {
+        flags::Flags::split("@caller(2) x");
+    }
+
+    #[test]
+    fn test_severity_argument() {
+        let result = custom_builder("\"disk nearly full\", severity = Warn".to_string());
+        println!("{result}");
+        assert!(result.contains("::log::warn!(\"{}\", inform);"));
+        assert!(result.contains("notes.push((\"severity\", \"warn\".to_string()));"));
+        let result = convert_builder("text.parse::<u32>(), \"bad number\", severity = fatal".to_string());
+        assert!(result.contains("::log::error!(\"{}\", inform);"));
+        assert!(!result.contains("reason.__nuhound_severity(&inform)"));
+        let result = examine_builder("load(), \"load failed\", severity = \"info\"".to_string());
+        assert!(result.contains("notes.push((\"severity\", \"info\".to_string()));"));
+        let result = severity_of_builder("error".to_string());
+        assert!(result.contains(".find_map(|layer| noted(layer, \"severity\"))"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown severity 'loud'")]
+    fn test_severity_argument_unknown() {
+        custom_builder("\"bad\", severity = loud".to_string());
+    }
+
+    #[test]
+    fn test_tags_argument() {
+        let result = flags::scoped("\"disk nearly full\", tags = [\"io\", \"storage\"]", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"disclose-tags\")]\n        let inform = if inform.starts_with("));
+        assert!(result.contains("format!(\"{},\\\"tags\\\":[\\\"io\\\",\\\"storage\\\"]}}\", &inform[..inform.len() - 1])"));
+        assert!(result.contains("format!(\"{} [tags: io, storage]\", inform)"));
+        assert!(!flags::scoped("\"disk nearly full\"", |item| custom_builder(item.to_string())).contains("disclose-tags"));
+        let result = flags::scoped("\"unknown {tags}\", tags = names", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let inform = format!(\"unknown {tags}\", tags = names);"));
+        assert!(!result.contains("[tags: "));
+    }
+
+
+    #[test]
+    #[should_panic(expected = "The tag 'io' must be a string literal")]
+    fn test_tags_argument_literal() {
+        custom_builder("\"bad\", tags = [io]".to_string());
+    }
+
+    #[test]
+    fn test_fields_argument() {
+        let (positional, named) = split_named(analyse("\"bad {}\", x, fields : request_id = rid, code = \"E1\", shard = ?n".chars()), &NAMED);
+        assert_eq!(positional, ["\"bad {}\"", "x"]);
+        assert_eq!(named, [
+            (String::from("fields"), String::from("request_id = rid")),
+            (String::from("code"), String::from("\"E1\"")),
+            (String::from("fields"), String::from("shard = ?n")),
+        ]);
+        let result = flags::scoped("\"bad\", fields: request_id = rid, shard = ?n", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let fields: [(&str, ::std::string::String); 2] = [(\"request_id\", format!(\"{}\", rid)), (\"shard\", format!(\"{:?}\", n))];"));
+        assert!(result.contains("format!(\"{} ({})\", inform, fields.iter()"));
+        assert!(result.contains("let json = format!(\"{{{}}}\", fields.iter()"));
+        assert!(result.contains("notes.push((\"fields\", json));"));
+    }
+
+    #[test]
+    fn test_field_sigils() {
+        let result = flags::scoped("\"lookup failed\", ? key, % user . name, code = \"E3\"", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let fields: [(&str, ::std::string::String); 2] = [(\"key\", format!(\"{:?}\", key)), (\"user.name\", format!(\"{}\", user.name))];"));
+        assert!(!result.contains("\"lookup failed\", ? key"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The field '?map[0]' must name a variable or one of its fields, such as ?key or %user.name")]
+    fn test_field_sigils_expression() {
+        custom_builder("\"lookup failed\", ?map[0]".to_string());
+    }
+
+    #[test]
+    fn test_captured_variables() {
+        let (positional, named) = split_named(analyse("\"failed {}\", n; state, cfg.path, code = \"E5\"".chars()), &NAMED);
+        assert_eq!(positional, vec!["\"failed {}\"", "n"]);
+        assert_eq!(named, vec![
+            (String::from("code"), String::from("\"E5\"")),
+            (String::from("fields"), String::from("state = ?state")),
+            (String::from("fields"), String::from("cfg.path = ?cfg.path")),
+        ]);
+        let (positional, named) = split_named(analyse("debug = \"raw {:?}\", pkt; release = \"malformed\"; state".chars()), &NAMED);
+        assert_eq!(positional, vec!["debug = \"raw {:?}\"", "pkt; release = \"malformed\""]);
+        assert_eq!(named, vec![(String::from("fields"), String::from("state = ?state"))]);
+        let (positional, named) = split_named(analyse("debug = \"raw {:?}\", pkt; release = \"malformed\"".chars()), &NAMED);
+        assert_eq!(positional, vec!["debug = \"raw {:?}\"", "pkt; release = \"malformed\""]);
+        assert!(named.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "The captured variable 'state.len()' must name a variable or one of its fields")]
+    fn test_captured_variables_expression() {
+        custom_builder("\"failed\"; state.len()".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The field 'shard' must take the form 'key = value'")]
+    fn test_fields_argument_pair() {
+        custom_builder("\"bad\", fields: shard".to_string());
+    }
+
+    #[test]
+    fn test_help_argument() {
+        let result = flags::scoped("\"config missing\", help = \"run `myapp init`\"", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let advice = format!(\"{}\", \"run `myapp init`\");"));
+        assert!(result.contains("notes.push((\"help\", advice));"));
+        assert!(result.contains("format!(\"{}\\nhelp: {}\", inform, advice)"));
+        assert!(!flags::scoped("\"bad\"", |item| custom_builder(item.to_string())).contains("let advice = format!("));
+        let result = flags::scoped("\"missing {help}\", help = topic", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let inform = format!(\"missing {help}\", help = topic);"));
+        assert!(!result.contains("notes.push((\"help\""));
+    }
+
+    #[test]
+    fn test_url_argument() {
+        let result = flags::scoped("\"bad\", help = \"retry\", url = \"https://docs.example.com/E042\"", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let advice = format!(\"{}\", \"https://docs.example.com/E042\");"));
+        assert!(result.contains("notes.push((\"url\", advice));"));
+        assert!(result.contains("format!(\"{}\\nsee: {}\", inform, advice)"));
+        assert!(result.find("\\nhelp: ").unwrap() < result.find("\\nsee: ").unwrap());
+        let result = flags::scoped("\"unable to fetch {url:?}\", url = target", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let inform = format!(\"unable to fetch {url:?}\", url = target);"));
+        assert!(!result.contains("notes.push((\"url\""));
+    }
+
+    #[test]
+    fn test_exit_code_argument() {
+        let result = flags::scoped("text.parse::<u32>(), \"bad number\", exit_code = 69", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("notes.push((\"exit\", \"69\".to_string()));"));
+        assert!(!result.contains("reason.__nuhound_exit()"));
+        let result = flags::scoped("\"negative\", exit_code = - 3", |item| custom_builder(item.to_string()));
+        assert!(result.contains("notes.push((\"exit\", \"-3\".to_string()));"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The exit code 'code' must be an integer literal")]
+    fn test_exit_code_argument_literal() {
+        custom_builder("\"bad\", exit_code = code".to_string());
+    }
+
+    #[test]
+    fn test_retriable_argument() {
+        let result = flags::scoped("\"timeout\", retriable = true", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("notes.push((\"retriable\", \"true\".to_string()));"));
+        assert!(!result.contains("[retriable]"));
+        let result = flags::scoped("load(), \"bad request\", retriable = false", |item| examine_builder(item.to_string()));
+        assert!(result.contains("notes.push((\"retriable\", \"false\".to_string()));"));
+        let result = is_retriable_builder("error".to_string());
+        assert!(result.contains("impl<T: ?Sized> Retriable for &T {}"));
+        assert!(result.contains("match noted(current, \"retriable\").as_deref() {"));
+        assert!(result.contains("impl<T: ::std::error::Error + 'static> Classified for T {}"));
+        assert!(result.contains("(&(error)).__nuhound_retriable()"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The classification 'maybe' must be 'true' or 'false'")]
+    fn test_retriable_argument_bool() {
+        custom_builder("\"bad\", retriable = maybe".to_string());
+    }
+
+    #[test]
+    fn test_classification_argument() {
+        let result = flags::scoped("\"rejected\", classification = Permanent", |item| custom_builder(item.to_string()));
+        assert!(result.contains("notes.push((\"retriable\", \"false\".to_string()));"));
+        let result = flags::scoped("\"timeout\", classification = transient", |item| custom_builder(item.to_string()));
+        assert!(result.contains("notes.push((\"retriable\", \"true\".to_string()));"));
+        let result = flags::scoped("\"unclear\", classification = Unknown", |item| custom_builder(item.to_string()));
+        assert!(!result.contains("\"retriable\""));
+        let result = is_permanent_builder("error".to_string());
+        println!("{result}");
+        assert!(result.contains("impl<T: ?Sized> Permanent for &T {}"));
+        assert!(result.contains(".any(|layer| noted(layer, \"retriable\").as_deref() == ::std::option::Option::Some(\"false\"))"));
+        assert!(result.contains("(&(error)).__nuhound_permanent()"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The 'retriable' and 'classification' arguments cannot be given together")]
+    fn test_classification_argument_retriable() {
+        custom_builder("\"bad\", retriable = true, classification = Permanent".to_string());
+    }
+
+    #[test]
+    fn test_user_argument() {
+        let result = flags::scoped("\"internal: parse failed\", user = \"The file is corrupted\"", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("notes.push((\"user\", format!(\"{}\", \"The file is corrupted\")));"));
+        assert!(result.contains("::nuhound::Nuhound::new(inform), |error, (key, value)| error.note(key, value))"));
+        assert!(!result.contains("user: "));
+        let result = flags::scoped("\"no access for {user}\", user = name", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let inform = format!(\"no access for {user}\", user = name);"));
+        assert!(!result.contains("notes.push((\"user\""));
+        let result = user_message_builder("error".to_string());
+        assert!(result.contains("::std::iter::successors(::std::option::Option::Some(&(error) as &(dyn ::std::error::Error + 'static)), |layer| ::std::error::Error::source(*layer))"));
+        assert!(result.contains(".find_map(|layer| noted(layer, \"user\"))"));
+        assert!(result.contains("layer.downcast_ref::<::nuhound::Nuhound>()?.noted(key)"));
+    }
+
+    #[test]
+    fn test_report_json() {
+        let result = report_json_builder("error".to_string());
+        assert!(result.contains("::std::option::Option::Some(&(error) as &(dyn ::std::error::Error + 'static)), |layer| ::std::error::Error::source(*layer)"));
+        assert!(result.contains("let mut json = format!(\"{{\\\"msg\\\":{}\", escape(&layer.to_string()));"));
+        assert!(result.contains("if let ::std::option::Option::Some(value) = noted(layer, key) {"));
+        assert!(result.contains("(\"code\", true), (\"file\", true), (\"line\", false), (\"col\", false)"));
+        assert!(result.contains("format!(\"[{}]\", layers.join(\",\"))"));
+        let result = report_chain_builder("body".to_string());
+        assert!(result.contains("let json: &str = &(body);"));
+        assert!(result.contains("let message = member(layer, \"msg\").unwrap_or_default();"));
+        assert!(result.contains(".fold(::nuhound::Nuhound::new(message), |error, (key, value)| error.note(key, value)));"));
+        assert!(result.contains("layers.next().map(|innermost| layers.fold(innermost, |cause, layer| layer.caused_by(cause)))"));
+    }
+
+    #[test]
+    fn test_into_eyre() {
+        let result = into_eyre_builder("error".to_string());
+        assert!(result.contains("let error: ::nuhound::Nuhound = error;"));
+        assert!(result.contains("::eyre::Report::new(error)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires a single expression")]
+    fn test_into_eyre_expressions() {
+        into_eyre_builder("first, second".to_string());
+    }
+
+    #[test]
+    fn test_into_miette() {
+        let result = into_miette_builder("error".to_string());
+        assert!(result.contains("let error: ::nuhound::Nuhound = error;"));
+        assert!(result.contains("impl ::miette::Diagnostic for NuhoundDiagnostic {"));
+        assert!(result.contains("let (help, url) = (noted(&error, \"help\"), noted(&error, \"url\"));"));
+        assert!(!result.contains("read_to_string"));
+        assert!(result.contains("::miette::Report::new(NuhoundDiagnostic { error, message, code, help, url, source, label })"));
+        let result = main_builder(String::new(), "fn main() -> Report<()> { Ok(()) }".to_string());
+        assert!(result.contains(".unwrap_or(1);\n            #[cfg(feature = \"miette\")]\n            eprintln!(\"{:?}\", { let noted = |layer: &(dyn ::std::error::Error + 'static), key: &str|"));
+    }
+
+    #[test]
+    fn test_snippet_note() {
+        let result = flags::at(Some(("src/lib.rs".to_string(), 1, 5)), "\"bad\"", |item| custom_builder(item.to_string()));
+        assert!(result.contains("#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", \
+            feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]\n        {\n        #[cfg(all(feature = \"miette\", not(feature = \"obfuscate\")))]\n        notes.push((\"snippet\""));
+        let line = include_str!("lib.rs").lines().next().unwrap();
+        assert!(result.contains(&format!("notes.push((\"snippet\", {line:?}.to_string()));")));
+        assert!(result.contains(&format!("notes.push((\"label\", \"4..{0}\".to_string()));", line.trim_end().len())));
+    }
+
+    #[test]
+    fn test_localized_message() {
+        let result = flags::scoped("key = \"errors.config.missing\", args = [path, line]", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let key: &str = \"errors.config.missing\";"));
+        assert!(result.contains("let template = crate::__nuhound_catalog(key).unwrap_or_else(|| ::std::string::String::from(key));"));
+        assert!(result.contains("let args: [::std::string::String; 2] = [::std::format!(\"{}\", path), ::std::format!(\"{}\", line)];"));
+        let result = flags::scoped("load(path), key = \"errors.config.unreadable\", code = \"E7\"", |item| convert_builder(item.to_string()));
+        assert!(result.contains("let key: &str = \"errors.config.unreadable\";"));
+        assert!(result.contains("let args: [::std::string::String; 0] = [];"));
+        let result = flags::scoped("\"missing {key}\", key = name", |item| custom_builder(item.to_string()));
+        assert!(!result.contains("__nuhound_catalog"));
+    }
 
     #[test]
-    fn test_custom_builder() {
-        const ATTRIBUTES: &str = r##""Oh dear this failed because of {}", text"##;
-        let result = custom_builder(ATTRIBUTES.to_string());
+    #[should_panic(expected = "A message given as a key cannot also take format arguments")]
+    fn test_localized_message_format_arguments() {
+        custom_builder("key = \"errors.config.missing\", path".to_string());
+    }
+
+    #[test]
+    fn test_profiled_message() {
+        let result = flags::scoped("debug = \"raw packet was {:?}\", pkt; release = \"malformed packet received\"", |item| {
+            let result = custom_builder(item.to_string());
+            assert_eq!(flags::current().template.as_deref(), Some("malformed packet received"));
+            result
+        });
+        assert!(result.contains("#[cfg(debug_assertions)] let message = { let _ = || {  }; ::std::format!(\"raw packet was {:?}\", pkt) };"));
+        assert!(result.contains("#[cfg(not(debug_assertions))] let message = { let _ = || { let _ = &(pkt); }; ::std::format!(\"malformed packet received\") };"));
+        let result = flags::scoped("raw.parse::<u32>(), release = \"bad number\"; debug = \"bad number {raw:?}\", code = \"E1\"", |item| convert_builder(item.to_string()));
+        assert!(result.contains("::std::format!(\"bad number {raw:?}\")"));
+        assert!(result.contains("::std::format!(\"bad number\")"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The debug message must be followed by a message for the other profile")]
+    fn test_profiled_message_missing_profile() {
+        custom_builder("debug = \"raw packet was {:?}\", pkt".to_string());
+    }
+
+    #[test]
+    fn test_catalog_builder() {
+        const ITEM: &str = r##"fn translate(key: &str) -> Option<&'static str> { None }"##;
+        let result = catalog_builder("".to_string(), ITEM.to_string());
         let result_parts: Vec<&str> = result.split("\n")
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
             .collect();
 
         let required = vec![
-            "{",
-            "#[cfg(not(feature = \"disclose\"))]",
-            "let inform = format!(\"Oh dear this failed because of {}\", text);",
-            "#[cfg(feature = \"disclose\")]",
-            "let inform = format!(\"{0}:{1}:{2}: {3}\", file!(), line!(), column!(), format!(\"Oh dear this failed because of {}\", text));",
-            "::std::result::Result::Err(::nuhound::Nuhound::new(inform))",
+            "fn translate(key: &str) -> Option<&'static str> { None }",
+            "#[doc(hidden)]",
+            "#[allow(dead_code)]",
+            "pub(crate) fn __nuhound_catalog(key: &str) -> ::std::option::Option<::std::string::String> {",
+            "translate(key).map(::std::convert::Into::into)",
             "}",
         ];
 
+        assert_eq!(result_parts, required);
+    }
+
+    #[test]
+    fn test_log_argument() {
+        let result = flags::scoped("\"disk {} is full\", disk, log = warn", |item| custom_builder(item.to_string()));
+        assert!(result.contains("::log::warn!(\"{}\", inform);"));
+        assert!(!result.contains("[warn]"));
+        let result = flags::scoped("\"disk {} is full\", disk, severity = error, log = info", |item| custom_builder(item.to_string()));
+        assert!(result.contains("::log::info!(\"{}\", inform);"));
+        assert!(!result.contains("::log::error!"));
+        let result = flags::scoped("\"disk {} is full\", disk, severity = error, log = off", |item| custom_builder(item.to_string()));
+        assert!(!result.contains("::log::"));
+        let result = flags::scoped("\"unable to open {log}\", log = path", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let inform = format!(\"unable to open {log}\", log = path);"));
+        assert!(!result.contains("::log::"));
+    }
+
+    #[test]
+    fn test_tracing_event() {
+        let location = Some((String::from("src/net.rs"), 57, 9));
+        let result = flags::at(location, "\"port {} in use\", port, code = \"E9\", severity = warn", |item| custom_builder(item.to_string()));
+        assert!(result.contains("::tracing::event!(::tracing::Level::WARN, file = %\"src/net.rs\", line = 57u32, column = 9u32, code = \"E9\", severity = \"warn\", \"{}\", inform);"));
+        assert!(result.contains("#[cfg(all(feature = \"tracing-events\", feature = \"obfuscate\"))]\n        ::tracing::event!(::tracing::Level::WARN, code = \"E9\", severity = \"warn\", \"{}\", inform);"));
+        assert!(result.contains("::log::warn!(\"{}\", inform);"));
+        let result = flags::scoped("\"port {} in use\", port, log = off", |item| custom_builder(item.to_string()));
+        assert!(!result.contains("::tracing::event!"));
+        let result = flags::scoped("\"port {} in use\", port, code = \"E9\"", |item| custom_builder(item.to_string()));
+        assert!(!result.contains("::tracing::event!"));
+        let result = flags::scoped("\"port {} in use\", port, log = info", |item| custom_builder(item.to_string()));
+        assert!(result.contains("::tracing::event!(::tracing::Level::INFO, file = %file!(), line = line!(), column = column!(), \"{}\", inform);"));
+    }
+
+    #[test]
+    fn test_defmt_event() {
+        let location = Some((String::from("src/net.rs"), 57, 9));
+        let result = flags::at(location, "\"port {} in use by {owner:?}\", port, code = \"E9\", severity = warn", |item| custom_builder(item.to_string()));
+        assert!(result.contains("::defmt::warn!(\"src/net.rs:57:9: [E9] port {0} in use by {1:?}\", port, owner);"));
+        let result = flags::at(None, "\"port {} in use\", port, code = \"E9\"", |item| custom_builder(item.to_string()));
+        assert!(result.contains("]: [E9] port {0} in use\", port);"));
+        assert!(result.contains("#[cfg(all(feature = \"defmt\", feature = \"obfuscate\"))]\n        ::defmt::error!(\"[site "));
+        let result = flags::scoped("\"login failed for {redact:user.email} after {n} tries\", n = count", |item| custom_builder(item.to_string()));
+        assert!(result.contains("login failed for (redacted) after {0} tries\", count);"));
+        let result = flags::scoped("@caller \"bad number {}\", text", |item| custom_builder(item.to_string()));
+        assert!(result.contains("::defmt::error!(\"{1=str}:{2=u32}:{3=u32}: bad number {0}\", text, __nuhound_caller.file(), __nuhound_caller.line(), __nuhound_caller.column());"));
+        let result = flags::scoped("debug = \"state {:?}\", state; release = \"bad state\"", |item| custom_builder(item.to_string()));
+        assert!(!result.contains("::defmt::"));
+    }
+
+    #[test]
+    fn test_span_fields() {
+        let result = flags::scoped("\"connect failed\"", |item| custom_builder(item.to_string()));
+        assert!(result.contains("#[cfg(feature = \"span-fields\")]"));
+        assert!(result.contains("::tracing_error::SpanTrace::capture().with_spans(|metadata, fields| {"));
+        assert!(result.contains("format!(\"{} [spans: {}]\", inform, spans.iter()"));
+        assert!(result.contains("format!(\"{},\\\"spans\\\":[{}]}}\", &inform[..inform.len() - 1], spans.iter()"));
+        let result = flags::scoped("@sample(10) \"connect failed\"", |item| custom_builder(item.to_string()));
+        assert!(result.contains("let inform = if __nuhound_sampled {"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown log level 'fatal'. Expected 'trace', 'debug', 'info', 'warn', 'error' or 'off'")]
+    fn test_log_argument_level() {
+        custom_builder("\"disk {} is full\", disk, log = fatal".to_string());
+    }
+
+    #[test]
+    fn test_display_flag() {
+        let result = flags::scoped("@display plugin.run(), \"plugin failed\"", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("::nuhound::Nuhound::new(inform).caused_by(::nuhound::Nuhound::new(match code {"));
+        assert!(result.contains("::std::option::Option::None => ::std::string::ToString::to_string(&reason),"));
+        assert!(!result.contains("reason.__nuhound_link(inform, code)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The '@opaque' and '@display' flags cannot be given together")]
+    fn test_display_flag_opaque() {
+        flags::scoped("@opaque @display plugin.run(), \"plugin failed\"", |item| convert_builder(item.to_string()));
+    }
+
+    #[test]
+    fn test_report_link() {
+        let result = flags::scoped("plugin.load(), \"plugin failed\"", |item| convert_builder(item.to_string()));
+        assert!(result.contains("impl ReportLink for ::anyhow::Error {"));
+        assert!(result.contains("impl ReportLink for ::eyre::Report {"));
+        assert!(result.contains("__nuhound_report_link(::std::convert::AsRef::as_ref(&self), inform, code)"));
+        assert!(result.contains("::std::option::Option::None => ::nuhound::Nuhound::link(inform, error),"));
+    }
+
+    #[test]
+    fn test_opaque_flag() {
+        let result = flags::scoped("@opaque text.parse::<u32>(), \"bad\"", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("::nuhound::Nuhound::new(inform).caused_by(::nuhound::Nuhound::new(match code {"));
+        assert!(result.contains("format!(\"cause withheld: {}\", ::std::any::type_name_of_val(&reason))"));
+        assert!(!result.contains("reason.__nuhound_link(inform, code)"));
+        let result = flags::scoped("@opaque load(), \"bad\"", |item| examine_builder(item.to_string()));
+        assert!(result.contains("match ::std::option::Option::<&str>::None {"));
+        assert!(!result.contains(".caused_by(cause)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The '@quiet' and '@disclose' flags cannot be given together")]
+    fn test_quiet_disclose_flags() {
+        flags::Flags::split("@quiet @disclose x");
+    }
+
+    #[test]
+    fn test_track_caller_builder() {
+        const ITEM: &str = r##"#[inline] fn parse_number(text: &str) -> Report<u32> { __nuhound_body }"##;
+        let result = track_caller_builder("".to_string(), ITEM.to_string());
+        let result_parts: Vec<&str> = result.split("\n")
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let required = vec![
+            "#[inline]",
+            "#[track_caller]",
+            "fn parse_number(text: &str) -> Report<u32> {",
+            "let __nuhound_caller = ::std::panic::Location::caller();",
+            "__nuhound_body",
+            "}",
+        ];
         println!("{result_parts:#?}");
         assert_eq!(result_parts, required);
+
+        let result = flags::scoped("@caller \"not a number\"", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("format!(\"{}:{}:{}\", __nuhound_caller.file(), __nuhound_caller.line(), __nuhound_caller.column())"));
+        assert!(result.contains("let message = format!(\"not a number\");"));
     }
 
     #[test]
-    fn test_examine_builder() {
-        const ATTRIBUTES: &str = r##"text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text"##;
-        let result = examine_builder(ATTRIBUTES.to_string());
+    fn test_async_context_builder() {
+        let result = async_context_builder("".to_string(), "async fn sync() -> Report<u32> { __nuhound_body }".to_string());
+        assert!(result.contains("use ::nuhound::ResultExtension as _;"));
+
+        let result = await_generator("client.refresh(account).await").unwrap();
+        println!("{result}");
+        assert!(result.contains("format!(\"awaiting {}\", \"client.refresh(account)\")"));
+        assert!(await_generator("client.refresh(account)").is_none());
+        assert!(await_generator("client.refresh(account).awaited").is_none());
+        assert!(await_generator("lookup(await)").is_none());
+    }
+
+    #[test]
+    fn test_codes() {
+        let result = custom_builder("\"port {} is reserved\", port, code = \"E101\"".to_string());
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"[{}] {}\", \"E101\", format_args!(\"port {} is reserved\", port));"));
+
+        let result = convert_builder("text.parse::<u16>(), \"bad {x}\", x = 1, code = \"E100\"".to_string());
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"[{}] {}\", \"E100\", format_args!(\"bad {x}\", x = 1));"));
+
+        let result = convert_builder("save(), ctx = request, \"unable to save\", code = \"E102\"".to_string());
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"[{}] {}\", \"E102\", format_args!(\"{} ({})\", format_args!(\"unable to save\"), request.__nuhound_context()));"));
+
+        let result = examine_builder("load(), \"unable to load\", code = \"E103\"".to_string());
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"[{}] {}\", \"E103\", format_args!(\"unable to load\"));"));
+
+        let result = flags::scoped("\"bad code {code}\", code = c", |item| custom_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"bad code {code}\", code = c);"));
+        assert!(!result.contains("notes.push((\"code\""));
+
+        let result = flags::scoped("save(), \"unable to save {ctx:?}\", ctx = request", |item| convert_builder(item.to_string()));
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"unable to save {ctx:?}\", ctx = request);"));
+        assert!(!result.contains("__nuhound_context()"));
+
+        assert_eq!(public_api_code("@quiet \"reserved\", code = \"E101\"").unwrap(), "\"E101\"");
+        assert!(public_api_code("\"reserved\", x == 1").is_none());
+    }
+
+    #[test]
+    fn test_into_nuhound_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("StoreError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: vec![String::from("#[nuhound(code = \"E100\")]")],
+                    name: String::from("Missing"),
+                    fields: Fields::Tuple(vec![Field {
+                        attributes: Vec::new(),
+                        name: String::from("0"),
+                        ty: String::from("String"),
+                    }]),
+                },
+                Variant { attributes: Vec::new(), name: String::from("ReadOnly"), fields: Fields::Unit },
+            ],
+        };
+        let result = into_nuhound_builder(&data);
         let result_parts: Vec<&str> = result.split("\n")
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
             .collect();
 
         let required = vec![
-            "text.parse::<u32>().report(|cause| {",
-            "#[cfg(not(feature = \"disclose\"))]",
-            "let inform = format!(\"Oh dear - '{}' could not be converted to an integer\", text);",
-            "#[cfg(feature = \"disclose\")]",
-            "let inform = format!(\"{0}:{1}:{2}: {3}\", file!(), line!(), column!(), format!(\"Oh dear - '{}' could not be converted to an integer\", text));",
-            "::nuhound::Nuhound::new(inform).caused_by(cause)",
-            "})",
+            "impl ::std::convert::From<StoreError> for ::nuhound::Nuhound  {",
+            "fn from(error: StoreError) -> Self {",
+            "let code: ::std::option::Option<&'static str> = match &error {",
+            "StoreError::Missing(..) => ::std::option::Option::Some(\"E100\"),",
+            "StoreError::ReadOnly => ::std::option::Option::None,",
+            "};",
+            "match code {",
+            "::std::option::Option::Some(code) => (::nuhound::Nuhound::new(format!(\"[{}] {}\", code, error))).note(\"code\", code),",
+            "::std::option::Option::None => ::nuhound::Nuhound::new(error.to_string()),",
+            "}",
+            "}",
+            "}",
+            "impl StoreError  {",
+            "#[doc(hidden)]",
+            "pub fn __nuhound_link(self, inform: ::std::string::String, _code: ::std::option::Option<&'static str>) -> ::nuhound::Nuhound {",
+            "::nuhound::Nuhound::new(inform).caused_by(::nuhound::Nuhound::from(self))",
+            "}",
+            "}",
         ];
         println!("{result_parts:#?}");
         assert_eq!(result_parts, required);
     }
 
     #[test]
-    fn test_covert_builder() {
-        const ATTRIBUTES: &str = r##"text.parse::<u32>(), "Oh dear - '{}' could not be converted to an integer", text"##;
-        let result = convert_builder(ATTRIBUTES.to_string());
+    fn test_into_nuhound_chain() {
+        let data = Data {
+            attributes: vec![String::from("#[nuhound(chain)]")],
+            is_enum: true,
+            name: String::from("StoreError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: Vec::new(),
+                    name: String::from("Open"),
+                    fields: Fields::Tuple(vec![Field {
+                        attributes: vec![String::from("#[from]")],
+                        name: String::from("0"),
+                        ty: String::from("std::io::Error"),
+                    }]),
+                },
+            ],
+        };
+        let result = into_nuhound_builder(&data);
+        assert!(result.contains("match ::std::error::Error::source(&error) {"));
+        assert!(result.contains("::std::option::Option::Some(source) => ::nuhound::Nuhound::link(error.to_string(), source),"));
+        assert!(result.contains("::std::option::Option::None => ::nuhound::Nuhound::new(error.to_string()),"));
+    }
+
+    #[test]
+    fn test_error_code_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("StoreError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: vec![String::from("#[code = \"E1001\"]")],
+                    name: String::from("Missing"),
+                    fields: Fields::Tuple(vec![Field {
+                        attributes: Vec::new(),
+                        name: String::from("0"),
+                        ty: String::from("String"),
+                    }]),
+                },
+                Variant {
+                    attributes: vec![String::from("#[code = \"E1002\"]")],
+                    name: String::from("ReadOnly"),
+                    fields: Fields::Unit,
+                },
+            ],
+        };
+        let result = error_code_builder(&data);
+        println!("{result}");
+        assert!(result.contains("pub const CODES: &'static [&'static str] = &[\"E1001\", \"E1002\"];"));
+        assert!(result.contains("StoreError::Missing(..) => \"E1001\","));
+        assert!(result.contains("StoreError::ReadOnly => \"E1002\","));
+        assert!(result.contains("::std::option::Option::Some(self.code())"));
+        assert!(result.contains("impl ::std::str::FromStr for StoreError  {"));
+        assert!(result.contains("\"E1002\" => ::std::result::Result::Ok(StoreError::ReadOnly),"));
+        assert!(result.contains("\"E1001\" => ::std::result::Result::Err(::nuhound::Nuhound::new(format!(\"the error code '{}' belongs to a variant with fields\", code))),"));
+    }
+
+    #[test]
+    fn test_diagnostic_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: false,
+            name: String::from("Missing"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![Variant {
+                attributes: vec![String::from("#[nuhound(help = \"add the key\", url = \"https://example.com\")]")],
+                name: String::from("Missing"),
+                fields: Fields::Unit,
+            }],
+        };
+        let result = diagnostic_builder(&data);
+        println!("{result}");
+        assert!(result.contains("Missing => ::std::option::Option::Some(\"add the key\"),"));
+        assert!(result.contains("Missing => ::std::option::Option::Some(\"https://example.com\"),"));
+        assert!(result.contains("pub fn __nuhound_help(&self) -> ::std::option::Option<&'static str> {"));
+        assert!(result.contains("pub fn __nuhound_url(&self) -> ::std::option::Option<&'static str> {"));
+        let result = convert_builder("load(), \"unable to load\", help = \"add the key\"".to_string());
+        println!("{result}");
+        assert!(!result.contains("reason.__nuhound_help()"));
+        assert!(result.contains("let inform = match reason.__nuhound_url() {"));
+        assert_eq!(result.matches("\\nhelp: {}").count(), 1);
+    }
+
+    #[test]
+    fn test_context_fields_builder() {
+        let field = |attributes: &[&str], name: &str, ty: &str| Field {
+            attributes: attributes.iter().map(|attribute| attribute.to_string()).collect(),
+            name: name.to_string(),
+            ty: ty.to_string(),
+        };
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: false,
+            name: String::from("Request"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![Variant {
+                attributes: Vec::new(),
+                name: String::from("Request"),
+                fields: Fields::Named(vec![
+                    field(&[], "request_id", "u64"),
+                    field(&["#[context(redact)]"], "token", "String"),
+                    field(&["#[context(skip)]"], "body", "Vec<u8>"),
+                ]),
+            }],
+        };
+        let result = context_fields_builder(&data);
+        println!("{result}");
+        assert!(result.contains("(\"request_id\", format!(\"{:?}\", self.request_id)),"));
+        assert!(result.contains("(\"token\", ::std::string::String::from(\"[redacted]\")),"));
+        assert!(!result.contains("body"));
+    }
+
+    #[test]
+    fn test_severity_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("StoreError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: vec![String::from("#[severity(warn)]")],
+                    name: String::from("Missing"),
+                    fields: Fields::Unit,
+                },
+                Variant { attributes: Vec::new(), name: String::from("Corrupt"), fields: Fields::Unit },
+            ],
+        };
+        let result = severity_builder(&data);
+        println!("{result}");
+        assert!(result.contains("StoreError::Missing => \"warn\","));
+        assert!(result.contains("StoreError::Corrupt => \"error\","));
+        assert!(result.contains("\"warn\" => { #[cfg(feature = \"tracing-events\")]"));
+        assert!(result.contains("::log::error!(\"{}\", inform);"));
+    }
+
+    #[test]
+    fn test_from_nuhound_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("ApiError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: vec![String::from("#[code = \"E100\"]")],
+                    name: String::from("NotFound"),
+                    fields: Fields::Unit,
+                },
+                Variant {
+                    attributes: vec![String::from("#[nuhound(code = \"E200\")]")],
+                    name: String::from("Unavailable"),
+                    fields: Fields::Named(vec![Field {
+                        attributes: Vec::new(),
+                        name: String::from("retry_after"),
+                        ty: String::from("u64"),
+                    }]),
+                },
+                Variant { attributes: Vec::new(), name: String::from("Other"), fields: Fields::Unit },
+            ],
+        };
+        let result = from_nuhound_builder(&data);
+        println!("{result}");
+        assert!(result.contains("impl<'__nuhound> ::std::convert::TryFrom<&'__nuhound ::nuhound::Nuhound> for ApiError  {"));
+        assert!(result.contains("match noted(current, \"code\").as_deref() {"));
+        assert!(result.contains("::std::option::Option::Some(\"E100\") => return ::std::result::Result::Ok(ApiError::NotFound),"));
+        assert!(result.contains("::std::option::Option::Some(\"E200\") => return ::std::result::Result::Err(error),"));
+        assert!(!result.contains("::std::default::Default::default()"));
+        assert!(!result.contains("ApiError::Other"));
+    }
+
+    #[test]
+    fn test_retriable_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("FetchError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: vec![String::from("#[retriable]")],
+                    name: String::from("Refused"),
+                    fields: Fields::Unit,
+                },
+                Variant { attributes: Vec::new(), name: String::from("NotFound"), fields: Fields::Unit },
+            ],
+        };
+        let result = retriable_builder(&data);
+        println!("{result}");
+        assert!(result.contains("FetchError::Refused => true,"));
+        assert!(result.contains("FetchError::NotFound => false,"));
+        assert!(result.contains("self.is_retriable()"));
+    }
+
+    #[test]
+    fn test_wrap_error_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: false,
+            name: String::from("DbError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![Variant {
+                attributes: Vec::new(),
+                name: String::from("DbError"),
+                fields: Fields::Tuple(vec![Field {
+                    attributes: Vec::new(),
+                    name: String::from("0"),
+                    ty: String::from("sqlx::Error"),
+                }]),
+            }],
+        };
+        let result = wrap_error_builder(&data);
+        println!("{result}");
+        assert!(result.contains("::std::fmt::Display::fmt(&self.0, f)"));
+        assert!(result.contains("::std::error::Error::source(&self.0)"));
+        assert!(result.contains("impl ::std::convert::From<sqlx::Error> for DbError  {"));
+        assert!(result.contains("Self(inner)"));
+    }
+
+    #[test]
+    fn test_exit_code_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("CliError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant { attributes: vec![String::from("#[exit(2)]")], name: String::from("Usage"), fields: Fields::Unit },
+                Variant {
+                    attributes: vec![String::from("#[exit(66)]")],
+                    name: String::from("NoInput"),
+                    fields: Fields::Tuple(vec![Field {
+                        attributes: Vec::new(),
+                        name: String::from("0"),
+                        ty: String::from("String"),
+                    }]),
+                },
+            ],
+        };
+        let result = exit_code_builder(&data);
+        println!("{result}");
+        assert!(result.contains("CliError::Usage => 2,"));
+        assert!(result.contains("CliError::NoInput(..) => 66,"));
+        assert!(result.contains("::std::option::Option::Some(self.exit_code())"));
+    }
+
+    #[test]
+    fn test_nuhound_error_builder() {
+        let data = Data {
+            attributes: Vec::new(),
+            is_enum: true,
+            name: String::from("ConfigError"),
+            generics: String::new(),
+            where_clause: String::new(),
+            variants: vec![
+                Variant {
+                    attributes: vec![String::from("#[nuhound(display = \"unable to read '{0}'\", code = \"E200\")]")],
+                    name: String::from("Read"),
+                    fields: Fields::Tuple(vec![Field {
+                        attributes: vec![String::from("#[nuhound(from)]")],
+                        name: String::from("0"),
+                        ty: String::from("std::io::Error"),
+                    }]),
+                },
+                Variant {
+                    attributes: vec![String::from("#[nuhound(display = \"line {line}: {{unknown}}\")]")],
+                    name: String::from("Key"),
+                    fields: Fields::Named(vec![Field {
+                        attributes: Vec::new(),
+                        name: String::from("line"),
+                        ty: String::from("usize"),
+                    }]),
+                },
+            ],
+        };
+        let result = nuhound_error_builder(&data);
+        println!("{result}");
+        assert!(result.contains("ConfigError::Read(field0) => write!(f, \"unable to read '{field0}'\"),"));
+        assert!(result.contains("ConfigError::Key { line } => write!(f, \"line {line}: {{unknown}}\"),"));
+        assert!(result.contains("ConfigError::Read(field0) => ::std::option::Option::Some(field0 as &(dyn ::std::error::Error + 'static)),"));
+        assert!(result.contains("ConfigError::Key { .. } => ::std::option::Option::None,"));
+        assert!(holds_trait_object("std::boxed::Box<dyn std::error::Error + Send + Sync>"));
+        assert!(!holds_trait_object("Box<std::io::Error>"));
+        assert!(result.contains("impl ::std::convert::From<std::io::Error> for ConfigError  {"));
+        assert!(result.contains("ConfigError::Read(source)"));
+        assert!(result.contains("::std::option::Option::Some(source) => ::nuhound::Nuhound::link(error.to_string(), source),"));
+        assert!(result.contains("ConfigError::Read(..) => ::std::option::Option::Some(\"E200\"),"));
+    }
+
+    #[test]
+    fn test_disclose_json() {
+        let result = custom_builder("\"port {} is reserved\", port".to_string());
+        println!("{result}");
+        assert!(result.contains("let message = format!(\"port {} is reserved\", port);"));
+        assert!(result.contains("#[cfg(feature = \"disclose-json\")] let inform = { let escape = |text: &str|"));
+        assert!(result.contains("#[cfg(not(feature = \"disclose-json\"))] let inform = [&*"));
+        assert!(result.contains("format!(\"{{\\\"file\\\":{}{},\\\"msg\\\":{}}}\", { #[cfg(feature = \"obfuscate\")] let location = \"null\"; \
+            #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }, { #[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":"));
+        assert!(result.contains("let location = format!(\",\\\"line\\\":{},\\\"col\\\":{}\", line!(), column!());"));
+    }
+
+    #[test]
+    fn test_fingerprint_generator() {
+        let result = fingerprint_generator("\"port {}, {} is reserved\", host, port");
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"fingerprint\")]\n        let inform = {"));
+        assert!(result.contains("let bytes = concat!(file!(), \":\", \"\\\"port {}, {} is reserved\\\"\").as_bytes();"));
+        assert!(result.contains("} else { format!(\"[{:06x}] {}\", FINGERPRINT, inform) } };"));
+        assert!(result.contains("format!(\"{},\\\"fingerprint\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], FINGERPRINT)"));
+    }
+
+    #[test]
+    fn test_function_hash_generator() {
+        let result = function_hash_generator();
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"fingerprint-fn\")]\n        let inform = {"));
+        assert!(result.contains("static FUNCTION_HASH: ::std::sync::OnceLock<u32> = ::std::sync::OnceLock::new();"));
+        assert!(result.contains("} else { format!(\"[fn {:06x}] {}\", hash, inform) } };"));
+        assert!(result.contains("format!(\"{},\\\"function_hash\\\":\\\"{:06x}\\\"}}\", &inform[..inform.len() - 1], hash)"));
+
+        let result = function_hash_note_generator();
+        assert!(result.contains("#[cfg(feature = \"fingerprint-fn\")]\n        notes.push((\"function_hash\", format!(\"{:06x}\", { static FUNCTION_HASH:"));
+        let result = report_json_builder("error".to_string());
+        assert!(result.contains("(\"function_hash\", true)"));
+    }
+
+    #[test]
+    fn test_repeat_generator() {
+        let result = repeat_generator();
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"count-repeats\")]\n        let inform = {"));
+        assert!(result.contains("if LAST.swap(hash, ::std::sync::atomic::Ordering::Relaxed) != hash {"));
+        assert!(result.contains("} else { format!(\"{} (repeated {} times since last report)\", inform, repeats) } } };"));
+        assert!(result.contains("format!(\"{},\\\"repeated\\\":{}}}\", &inform[..inform.len() - 1], repeats)"));
+    }
+
+    #[test]
+    fn test_site_generator() {
+        let result = site_generator(12);
+        println!("{result}");
+        assert!(result.contains("format!(\"{},\\\"site\\\":12}}\", &inform[..inform.len() - 1])"));
+        assert!(result.contains("format!(\"[site 12] {}\", inform)"));
+        assert!(result.contains("#[cfg(all(feature = \"site-id\", not(feature = \"obfuscate\")))]\n        let inform = "));
+    }
+
+    #[test]
+    fn test_site_counters() {
+        let result = count_generator(12);
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"site-counts\")]\n        crate::__NUHOUND_SITE_COUNTS[11].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);"));
+        let result = flags::scoped("@sample(100) \"bad\"", |item| custom_builder(item.to_string()));
+        assert_eq!(result.matches("crate::__NUHOUND_SITE_COUNTS[").count(), 1);
+        assert!(result.find("crate::__NUHOUND_SITE_COUNTS[") < result.find("if occurrence % 100 == 0 {"));
+        let result = site_counters_builder();
+        assert!(result.contains("pub(crate) static __NUHOUND_SITE_COUNTS: [::std::sync::atomic::AtomicU64; "));
+        let result = nuhound_sites_builder();
+        assert!(result.contains("let sites: [(usize, &'static str, usize); "));
+        assert!(result.contains(", \"\", 0)"));
+        assert!(result.contains(".zip(crate::__NUHOUND_SITE_COUNTS.iter())"));
+        let result = flags::at(Some((String::from("src/net.rs"), 42, 5)), "\"bad\", code = \"E42\"", |item| custom_builder(item.to_string()));
+        assert!(result.contains("#[cfg(feature = \"metrics\")]\n        ::metrics::counter!(\"nuhound_errors_total\", \"site\" => { #[cfg(feature = \"obfuscate\")] let location = \""));
+        assert!(result.contains("#[cfg(not(feature = \"obfuscate\"))] let location = \"src/net.rs:42\"; location }, \"code\" => \"E42\").increment(1);"));
+    }
+
+    #[test]
+    fn test_build_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("match option_env!(\"NUHOUND_BUILD_ID\").unwrap_or_default() { \"\" => ::std::string::String::new(), build => format!(\"@{}\", build), }"));
+        let result = custom_builder("\"not a number\"".to_string());
+        assert!(result.contains("build => format!(\"{},\\\"build\\\":{}}}\", &inform[..inform.len() - 1], escape(build))"));
+    }
+
+    #[test]
+    fn test_version_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"disclose-version\")] let version = format!(\"[{} {}] \", env!(\"CARGO_PKG_VERSION\"), if cfg!(debug_assertions) { \"debug\" } else { \"release\" });"));
+        assert!(result.contains("#[cfg(not(feature = \"disclose-version\"))] let version = \"\"; version }"));
+        assert!(result.contains("escape(env!(\"CARGO_PKG_VERSION\")), escape(if cfg!(debug_assertions) { \"debug\" } else { \"release\" }))"));
+    }
+
+
+    #[test]
+    fn test_thread_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("&*{ #[cfg(feature = \"disclose-thread\")] let thread = format!(\"[{}] \", { let thread = ::std::thread::current();"));
+        assert!(result.contains("#[cfg(not(feature = \"disclose-thread\"))] let thread = \"\"; thread }"));
+        assert!(result.contains("#[cfg(feature = \"disclose-thread\")] let inform = format!(\"{},\\\"thread\\\":{}}}\", &inform[..inform.len() - 1], escape(&{ let thread"));
+    }
+
+
+    #[test]
+    fn test_time_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("&*{ #[cfg(feature = \"disclose-time\")] let time = format!(\"[{}] \", { let time = ::std::time::SystemTime::now()"));
+        assert!(result.contains("#[cfg(not(feature = \"disclose-time\"))] let time = \"\"; time }"));
+        assert!(result.contains("#[cfg(feature = \"disclose-time\")] let inform = { let time = ::std::time::SystemTime::now()"));
+        assert!(result.contains("format!(\"{},\\\"time\\\":{}.{:06}}}\", &inform[..inform.len() - 1], time.as_secs(), time.subsec_micros())"));
+        assert!(result.contains("#[cfg(feature = \"disclose-time\")]\n        notes.push((\"time\", { let time = ::std::time::SystemTime::now()"));
+        assert!(!result.contains("__nuhound_time"));
+
+        let result = examine_builder("load(path), \"unable to load\"".to_string());
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"disclose-time\")] let time = match { trait NoTime {"));
+        assert!(result.contains("(&cause).__nuhound_time()"));
+        assert!(result.contains("::std::option::Option::Some(elapsed) => format!(\"[{} +{:?}] \", { let time = ::std::time::SystemTime::now()"));
+        assert!(result.contains("::std::option::Option::Some(elapsed) => format!(\"{},\\\"elapsed\\\":{}.{:06}}}\", &inform[..inform.len() - 1], elapsed.as_secs(), elapsed.subsec_micros())"));
+        let result = convert_builder("text.parse::<u32>(), \"bad\"".to_string());
+        assert!(result.contains("(&reason).__nuhound_time()"));
+    }
+
+
+    #[test]
+    fn test_redact_generator() {
+        let message = vec!["\"login failed for {redact:user.email} after {} attempts {{{redact:auth::token():?}}}\"".to_string(), "count".to_string()];
+        let result = redact_generator(&message);
+        println!("{result}");
+        assert!(result.starts_with("\"login failed for {__nuhound_redact0} after {} attempts {{{__nuhound_redact1}}}\", count, "));
+        assert!(result.contains("__nuhound_redact0 = { #[cfg(feature = \"disclose-debug\")] let value = format!(\"{}\", user.email); \
+            #[cfg(not(feature = \"disclose-debug\"))] let value = { let _ = || { let _ = &(user.email); }; \"(redacted)\" }; value }"));
+        assert!(result.contains("let value = format!(\"{:?}\", auth::token());"));
+
+        let message = vec!["\"{} is {{redact:plain}}\"".to_string(), "name".to_string()];
+        assert_eq!(redact_generator(&message), "\"{} is {{redact:plain}}\", name");
+    }
+
+    #[test]
+    fn test_snippet_generator() {
+        let result = snippet_generator("db.fetch(\"users\", id)");
+        println!("{result}");
+        assert_eq!(result, "#[cfg(all(feature = \"disclose-snippet\", feature = \"disclose-json\"))] \
+            let inform = format!(\"{},\\\"snippet\\\":{}}}\", &inform[..inform.len() - 1], \"\\\"db.fetch(\\\\\\\"users\\\\\\\", id)\\\"\"); \
+            #[cfg(all(feature = \"disclose-snippet\", not(feature = \"disclose-json\")))] \
+            let inform = format!(\"{} in `{}`\", inform, \"db.fetch(\\\"users\\\", id)\"); ");
+
+        let result = convert_builder("db.fetch(id)".to_string());
+        assert!(result.contains("let inform = format!(\"{} in `{}`\", inform, \"db.fetch(id)\"); inform };"));
+        let result = custom_builder("\"not a number\"".to_string());
+        assert!(!result.contains("snippet"));
+    }
+
+
+    #[test]
+    fn test_backtrace_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"backtrace\")]\n        let inform = { let backtrace = match"));
+        assert!(result.contains("if backtrace.status() != ::std::backtrace::BacktraceStatus::Captured { inform } else { if"));
+        assert!(result.contains("} else { format!(\"{}\\nbacktrace:\\n{}\", inform, backtrace) } } };"));
+        assert!(result.contains("else { if inform.starts_with(\"{\\\"file\\\":\") && inform.ends_with('}') { \
+            format!(\"{},\\\"backtrace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str|"));
+    }
+
+    #[test]
+    fn test_span_trace_generator() {
+        let result = span_trace_generator();
+        println!("{result}");
+        assert!(result.contains("#[cfg(feature = \"span-trace\")]\n        let inform = {"));
+        assert!(result.contains("} else { format!(\"{}\\nspan trace:\\n{}\", inform, span_trace) } } };"));
+        assert!(result.contains("format!(\"{},\\\"span_trace\\\":{}}}\", &inform[..inform.len() - 1], (|text: &str|"));
+    }
+
+    #[test]
+    fn test_precision_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("#[cfg(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\"))))] let location = format!(\"{}\", file!());"));
+        assert!(result.contains("#[cfg(any(all(feature = \"disclose-line\", not(any(feature = \"disclose\", feature = \"disclose-full\"))), all(feature = \"no-column\", not(all(feature = \"disclose-file\", not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\")))))))] let location = format!(\"{}:{}\", file!(), line!());"));
+        assert!(result.contains("#[cfg(all(not(feature = \"no-column\"), any(feature = \"disclose\", feature = \"disclose-full\", \
+            not(any(feature = \"disclose-line\", feature = \"disclose-file\")))))] let location = format!(\"{}:{}:{}\", file!(), line!(), column!());"));
+        assert!(result.contains("let location = (file!().to_string(), ::std::option::Option::Some(line!().to_string()), ::std::option::Option::None);"));
+    }
+
+
+    #[test]
+    fn test_disclosed_concat() {
+        let result = custom_builder("\"bad {}\", text".to_string());
+        println!("{result}");
+        assert!(result.contains("let message = format!(\"bad {}\", text);"));
+        assert!(result.contains("&*message].concat();"));
+        assert!(!result.contains("{6}"));
+    }
+
+    #[test]
+    fn test_function_generator() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("while let ::std::option::Option::Some(outer) = name.strip_suffix(\"::{{closure}}\")"));
+        assert!(result.contains("{ #[cfg(feature = \"disclose-fn\")] let function = {"));
+        assert!(result.contains("format!(\"{}: \", name) }; #[cfg(not(feature = \"disclose-fn\"))] let function = \"\"; function }, &*message].concat()"));
+    }
+
+
+    #[test]
+    fn test_default_message() {
+        let result = convert_builder("text.parse::<u32>()".to_string());
+        println!("{result}");
+        assert!(result.contains("let inform = format!(\"{} failed\", stringify!(text.parse::<u32>()));"));
+        let result = examine_builder("load_config()".to_string());
+        assert!(result.contains("let inform = format!(\"{} failed\", stringify!(load_config()));"));
+        let result = convert_builder("text.parse::<u32>(), code = \"E100\"".to_string());
+        assert!(result.contains("stringify!(text.parse::<u32>())"));
+    }
+
+    #[test]
+    fn test_disclose_debug_only() {
+        let result = custom_builder("\"not a number\"".to_string());
+        println!("{result}");
+        assert!(result.contains("#[cfg(not(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", \
+            feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions), feature = \"disclose-env\")))]"));
+        assert!(result.contains("#[cfg(any(feature = \"disclose\", feature = \"disclose-full\", feature = \"disclose-line\", \
+            feature = \"disclose-file\", all(feature = \"disclose-debug-only\", debug_assertions)))]"));
+    }
+
+
+    #[test]
+    fn test_obfuscate_generator() {
+        let result = custom_builder("\"port {} is reserved\", port".to_string());
+        println!("{result}");
+        assert!(result.contains("{ #[cfg(feature = \"obfuscate\")] let location = \"[site "));
+        assert!(result.contains("#[cfg(feature = \"obfuscate\")] let location = \",\\\"site\\\":"));
+        assert!(result.contains("{ #[cfg(feature = \"obfuscate\")] let location = \"null\"; \
+            #[cfg(not(feature = \"obfuscate\"))] let location = escape(&file!()); location }"));
+        assert!(result.contains("#[cfg(feature = \"obfuscate\")] notes.push((\"site\", \""));
+        assert_eq!(registry::register_site("first"), registry::register_site("second"));
+    }
+
+    #[test]
+    fn test_message_catalog() {
+        flags::scoped("\"disk {} is full\", disk", |item| custom_builder(item.to_string()));
+        let result = registry::messages(&registry::crate_name());
+        println!("{result}");
+        assert!(result.starts_with("[\n  {\"site\": 1, \"file\": "));
+        assert!(result.contains(", \"file\": \"\", \"line\": 0, \"column\": 0, \"msg\": "));
+        assert!(result.ends_with("}\n]\n"));
+    }
+
+    #[test]
+    fn test_capacity_generator() {
+        let result = capacity_generator("\"[{}] {}\", \"E42\", format!(\"disk {} is full\", disk)", "128");
+        println!("{result}");
+        assert!(result.starts_with("{ struct Buffer(::heapless::String<128>, bool); "));
+        assert!(result.contains("::core::fmt::Write::write_fmt(&mut buffer, \
+            format_args!(\"[{}] {}\", \"E42\", format!(\"disk {} is full\", disk)));"));
+        assert!(result.contains("while buffer.0.len() + '…'.len_utf8() > 128 && buffer.0.pop().is_some() {}"));
+        assert!(result.ends_with("::std::string::String::from(buffer.0.as_str()) }"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The message capacity '2' must be a number of bytes of at least 16, such as 128")]
+    fn test_capacity_minimum() {
+        capacity_generator("\"disk full\"", "2");
+    }
+
+    #[test]
+    fn test_settings() {
+        const SETTINGS: &str = "
+            # The settings of the workspace
+            disclose = \"line\"
+            require = [\"code\", \"severity\"]
+            location_format = \"{file}:{line}: \\\"{msg}\\\"\"
+            max_message_length = 512
+            backtrace_threshold = \"fatal\"
+            no_std = true
+
+            [policy]
+            max_length = 80
+        ";
+        assert_eq!(config::lookup(SETTINGS, "disclose"), Some(String::from("line")));
+        assert_eq!(config::lookup(SETTINGS, "location_format"), Some(String::from("{file}:{line}: \"{msg}\"")));
+        assert_eq!(config::lookup(SETTINGS, "require"), Some(String::from("[\"code\", \"severity\"]")));
+        assert_eq!(config::lookup(SETTINGS, "max_message_length"), Some(String::from("512")));
+        assert_eq!(config::lookup(SETTINGS, "backtrace_threshold"), Some(String::from("fatal")));
+        assert_eq!(config::lookup(SETTINGS, "no_std"), Some(String::from("true")));
+        assert_eq!(config::lookup(SETTINGS, "sink"), None);
+        assert_eq!(config::section(SETTINGS, "policy"), vec!["max_length = 80"]);
+    }
+
+    #[test]
+    fn test_settings_registration() {
+        let directory = std::env::temp_dir().join(format!("nuhound-settings-{}", std::process::id()));
+        let manifest = directory.join("member");
+        std::fs::create_dir_all(&manifest).unwrap();
+        std::fs::write(directory.join("nuhound.toml"), "disclose = \"line\"\n").unwrap();
+        let path = directory.join("nuhound.toml").display().to_string();
+        let registration = config::registration_from(manifest.as_os_str()).unwrap();
+        assert!(registration.starts_with(&format!("const _: &str = ::core::include_str!({path:?}); ")));
+        assert!(registration.contains(" const _: ::core::option::Option<&str> = ::core::option_env!(\"NUHOUND_PATH_PREFIX\");"));
+        assert!(registration.contains(" const _: ::core::option::Option<&str> = ::core::option_env!(\"NUHOUND_LOCATION_FORMAT\");"));
+        assert_eq!(config::registration_from(manifest.as_os_str()), None);
+        std::fs::remove_dir_all(&directory).unwrap();
+        let registration = config::registration_from(directory.as_os_str()).unwrap();
+        assert!(registration.starts_with("const _: ::core::option::Option<&str> = ::core::option_env!(\"NUHOUND_PATH_PREFIX\");"));
+    }
+
+    const POLICY: &str = "
+        # The rules for the messages of the crate
+        max_length = 24
+        no_trailing_period = true
+        forbidden_words = [\"oops\", \"went wrong\"]
+        require_code = [\"src/api/\"]
+    ";
+
+    #[test]
+    fn test_message_policy() {
+        let policy = policy::Policy::parse(POLICY);
+        assert_eq!(policy.check("src/main.rs", "unable to read {}", false), Ok(()));
+        assert_eq!(policy.check("src/main.rs", "loading...", false), Ok(()));
+        assert_eq!(policy.check("src/main.rs", "whoops {}", false), Ok(()));
+        assert_eq!(policy.check("src/api/port.rs", "port {} is reserved", true), Ok(()));
+        assert_eq!(policy::Policy::parse("# nothing\n"), policy::Policy::default());
+    }
+
+    #[test]
+    fn test_message_policy_length() {
+        let result = policy::Policy::parse(POLICY).check("src/main.rs", "unable to read the configuration", false);
+        let required = Err(String::from("The message \"unable to read the configuration\" is longer than the 24 characters allowed by the message policy"));
+        assert_eq!(result, required);
+    }
+
+    #[test]
+    fn test_message_policy_words() {
+        let result = policy::Policy::parse(POLICY).check("src/main.rs", "Oops, {} failed", false);
+        let required = Err(String::from("The message \"Oops, {} failed\" contains 'oops', which is forbidden by the message policy"));
+        assert_eq!(result, required);
+    }
+
+    #[test]
+    fn test_message_policy_code() {
+        let result = policy::Policy::parse(POLICY).check("src/api/port.rs", "port {} is reserved", false);
+        let required = Err(String::from("Errors created within 'src/api/' must be given a code such as `code = \"E100\"` under the message policy"));
+        assert_eq!(result, required);
+    }
+
+    #[test]
+    fn test_manifest() {
+        custom_builder("\"unlocated\"".to_string());
+        let location = Some((String::from("src/manifest.rs"), 9, 5));
+        flags::at(location, "\"disk {} is full\", disk, code = \"E77\", severity = warn, tags = [\"io\", \"disk\"]", |item| custom_builder(item.to_string()));
+        let result = registry::manifest(&registry::crate_name());
+        println!("{result}");
+        assert!(result.starts_with("[\n  {\"site\": 1, "));
+        assert!(result.contains(", \"file\": \"src/manifest.rs\", \"line\": 9, \"column\": 5, \"msg\": \"disk {} is full\", \"code\": \"E77\", \"severity\": \"warn\", \"tags\": [\"io\", \"disk\"]}"));
+        assert!(result.contains(", \"file\": \"\", \"line\": 0, \"column\": 0, \"msg\": "));
+        assert!(result.ends_with("}\n]\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "The error code \"E9001\" at src/codes.rs:7:5 is already given to the error at src/codes.rs:3:5")]
+    fn test_duplicate_codes() {
+        let location = |line| Some((String::from("src/codes.rs"), line, 5));
+        // The sites without a location, which the other tests share, are kept the first of the crate
+        custom_builder("\"unlocated\"".to_string());
+        flags::at(location(3), "\"first\", code = \"E9001\"", |item| custom_builder(item.to_string()));
+        flags::at(location(3), "\"first\", code = \"E9001\"", |item| custom_builder(item.to_string()));
+        flags::at(location(5), "\"second\", code = \"E9002\"", |item| custom_builder(item.to_string()));
+        flags::at(location(7), "\"third\", code = \"E9001\"", |item| custom_builder(item.to_string()));
+    }
+
+    #[test]
+    fn test_template_generator() {
+        let result = template_generator("{file}#{line} [{module}] {msg} {{{line}}}");
+        println!("{result}");
+        assert_eq!(result, "format!(\"{0}#{1} [{2}] {3} {{{1}}}\", file!(), line!(), module_path!(), message)");
+
+        let result = template_generator("{function}: {msg}");
+        println!("{result}");
+        assert!(result.starts_with("format!(\"{0}: {1}\", { fn __nuhound_function() {}"));
+        assert!(result.ends_with("{ name = outer; } name }, message)"));
+    }
+
+    #[test]
+    fn test_file_generator() {
+        assert_eq!(file_generator("file!()", None), "file!()");
+        assert_eq!(
+            file_generator("file!()", Some("/home/build/")),
+            "{ let file: &str = file!(); file.strip_prefix(\"/home/build/\").unwrap_or(file) }",
+        );
+        assert_eq!(
+            file_generator("__nuhound_caller.file()", Some("/home/build/=src/")),
+            "{ let file: &str = __nuhound_caller.file(); match file.strip_prefix(\"/home/build/\") { \
+                ::std::option::Option::Some(rest) => format!(\"{}{}\", \"src/\", rest), \
+                ::std::option::Option::None => file.to_string(), } }",
+        );
+        let result = flags::at(Some((String::from("src/net.rs"), 42, 5)), "\"bad\"", |_| location_generator());
+        assert_eq!(result, "\"src/net.rs\", 42u32, 5u32");
+    }
+
+    #[test]
+    fn test_runtime_generator() {
+        let result = runtime_generator("let inform = format!(\"plain\");", "let inform = format!(\"disclosed\");");
+        println!("{result}");
+        assert!(result.starts_with("let inform = { static DISCLOSE: ::std::sync::OnceLock<bool>"));
+        assert!(result.contains("::std::env::var(\"NUHOUND_DISCLOSE\").is_ok_and(enabled)"));
+        assert!(result.ends_with("{ format!(\"disclosed\") } else { format!(\"plain\") } };"));
+
+        let result = runtime_generator("eprintln!(\"plain\");", "eprintln!(\"disclosed\");");
+        println!("{result}");
+        assert!(result.starts_with("{ static DISCLOSE"));
+        assert!(result.ends_with("{ eprintln!(\"disclosed\"); } else { eprintln!(\"plain\"); } }"));
+
+        let result = custom_builder("\"not a number\"".to_string());
+        assert!(result.contains("#[cfg(all(feature = \"disclose-env\", not(any(feature = \"disclose\""));
+        assert!(result.contains("::std::env::var(\"NUHOUND_DISCLOSE\")"));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration_generator("\"5s\""), "::std::time::Duration::from_nanos(5000000000)");
+        assert_eq!(duration_generator("\"1.5ms\""), "::std::time::Duration::from_nanos(1500000)");
+        assert_eq!(duration_generator("\"2 m\""), "::std::time::Duration::from_nanos(120000000000)");
+    }
+
+    #[test]
+    fn test_map_err_builder() {
+        const ITEM: &str = r##"pub fn parse_port(text: &str) -> nuhound::Report<Vec<u16>> { __nuhound_body }"##;
+        let result = map_err_builder("MyCrateError".to_string(), ITEM.to_string());
         let result_parts: Vec<&str> = result.split("\n")
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
             .collect();
 
         let required = vec![
-            "text.parse::<u32>().report(|reason| {",
-            "let cause: &dyn ::std::error::Error = &reason;",
-            "#[cfg(not(feature = \"disclose\"))]",
-            "let inform = format!(\"Oh dear - '{}' could not be converted to an integer\", text);",
-            "#[cfg(feature = \"disclose\")]",
-            "let inform = format!(\"{0}:{1}:{2}: {3}\", file!(), line!(), column!(), format!(\"Oh dear - '{}' could not be converted to an integer\", text));",
-            "::nuhound::Nuhound::link(inform, cause)",
-            "})",
+            "pub fn parse_port(text: &str) -> ::std::result::Result<Vec<u16>, MyCrateError> {",
+            "(|| -> ::nuhound::Report<Vec<u16>> { __nuhound_body })().map_err(<MyCrateError as ::std::convert::From<::nuhound::Nuhound>>::from)",
+            "}",
         ];
         println!("{result_parts:#?}");
         assert_eq!(result_parts, required);
+
+        const RESULT: &str = r##"async fn load() -> Result<(), Nuhound> { __nuhound_body }"##;
+        let result = map_err_builder("Error, with = Error::Internal".to_string(), RESULT.to_string());
+        println!("{result}");
+        assert!(result.starts_with("async fn load() -> ::std::result::Result<(), Error> {"));
+        assert!(result.contains("}.await.map_err(Error::Internal)"));
+    }
+
+    #[test]
+    fn function() {
+        const ITEM: &str = r##"#[doc = " A {test}"] pub async unsafe extern "C" fn f<'a, T: Fn() -> u8>(x: &'a str, mut y: T)
+            -> Result<Vec<u8>, std::io::Error> where T: Clone { if x.len() < 3 { y(); } Ok(vec![]) }"##;
+        let function = Function::parse(ITEM);
+        assert_eq!(function.attributes, vec!["#[doc = \" A {test}\"]"]);
+        assert_eq!(function.qualifiers, "pub async unsafe extern \"C\"");
+        assert_eq!(function.name, "f");
+        assert_eq!(function.generics, "<'a, T: Fn() -> u8>");
+        assert_eq!(function.inputs, "x: &'a str, mut y: T");
+        assert_eq!(function.output.as_deref(), Some("Result<Vec<u8>, std::io::Error>"));
+        assert_eq!(function.where_clause, "where T: Clone");
+        assert_eq!(function.body, "if x.len() < 3 { y(); } Ok(vec![])");
+        assert!(function.is_async());
+    }
+
+    #[test]
+    fn function_impl_output() {
+        const ITEM: &str = r##"fn f() -> Report<impl Fn(u8) -> Vec<u8> + Send, impl Debug> { __nuhound_body }"##;
+        let function = Function::parse(ITEM);
+        assert_eq!(function.invoke_body(), "(|| -> Report<_, _> { __nuhound_body })()");
+        const ASYNC: &str = r##"async fn f() -> Report<(impl Debug, u8)> { __nuhound_body }"##;
+        let result = Function::parse(ASYNC).invoke_body();
+        assert!(result.contains("let outcome: Report<(_, u8)> = { __nuhound_body };"));
     }
 
     #[test]
@@ -493,5 +9180,3 @@ mod tests {
         assert_eq!(result, required);
     }
 }
-
-
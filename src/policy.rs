@@ -0,0 +1,159 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that enforces the rules that a team sets for the messages of its errors
+
+use crate::config;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// The rules for the messages of a crate, read from the file named by the NUHOUND_POLICY environment
+// variable. Each line of the file takes the form 'rule = value' and lines starting with # are
+// comments, such as:
+//
+// max_length = 80
+// no_trailing_period = true
+// forbidden_words = ["oops", "something went wrong"]
+// require_code = ["src/api/"]
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Policy {
+    // The greatest number of characters in the format string of a message
+    max_length: Option<usize>,
+    // Whether a message must not end with a period
+    no_trailing_period: bool,
+    // The words and phrases that a message must not contain, which are matched ignoring case
+    forbidden_words: Vec<String>,
+    // The prefixes of the paths of the source files whose errors must be given a code
+    require_code: Vec<String>,
+}
+
+impl Policy {
+    // Parse the text of a policy file
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut policy = Self::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (rule, value) = crate::split_assignment(line)
+                .unwrap_or_else(|| panic!("The message policy rule '{line}' must take the form 'rule = value'"));
+            match rule.as_str() {
+                "max_length" => policy.max_length = Some(value.parse()
+                    .unwrap_or_else(|_| panic!("The message policy rule 'max_length' must be a number"))),
+                "no_trailing_period" => policy.no_trailing_period = value.parse()
+                    .unwrap_or_else(|_| panic!("The message policy rule 'no_trailing_period' must be 'true' or 'false'")),
+                "forbidden_words" => policy.forbidden_words = strings(&rule, &value).iter().map(|word| word.to_lowercase()).collect(),
+                "require_code" => policy.require_code = strings(&rule, &value),
+                _ => panic!("Unknown message policy rule '{rule}'. Expected 'max_length', 'no_trailing_period', \
+                    'forbidden_words' or 'require_code'"),
+            }
+        }
+        policy
+    }
+
+    // Check the format string of a message, created in the given source file, against the rules,
+    // returning the reason when a rule is broken
+    pub(crate) fn check(&self, file: &str, message: &str, code: bool) -> Result<(), String> {
+        if let Some(max_length) = self.max_length.filter(|&max_length| message.chars().count() > max_length) {
+            return Err(format!("The message \"{message}\" is longer than the {max_length} characters allowed by the message policy"));
+        }
+        if self.no_trailing_period && message.ends_with('.') && !message.ends_with("..") {
+            return Err(format!("The message \"{message}\" must not end with a period under the message policy"));
+        }
+        let lowercase = message.to_lowercase();
+        let words: Vec<&str> = lowercase.split(|c: char| !c.is_alphanumeric() && c != '\'').collect();
+        let forbidden = self.forbidden_words.iter().find(|word| match word.contains(char::is_whitespace) {
+            true => lowercase.contains(word.as_str()),
+            false => words.contains(&word.as_str()),
+        });
+        if let Some(word) = forbidden {
+            return Err(format!("The message \"{message}\" contains '{word}', which is forbidden by the message policy"));
+        }
+        let required = self.require_code.iter().find(|prefix| !code && file.starts_with(prefix.as_str()));
+        if let Some(prefix) = required {
+            return Err(format!("Errors created within '{prefix}' must be given a code such as `code = \"E100\"` under the message \
+                policy"));
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    // The reason that the message of the macro being expanded breaks the policy, together with the
+    // format string of the message as it is written, which is reported once the macro is expanded
+    static BREACH: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+}
+
+// Record that the message of the macro being expanded, written as the given format string, breaks
+// the policy for the given reason
+pub(crate) fn breach(reason: String, written: String) {
+    BREACH.with(|breach| breach.replace(Some((reason, written))));
+}
+
+// Take the breach of the policy recorded while expanding the macro, if any
+pub(crate) fn take_breach() -> Option<(String, String)> {
+    BREACH.with(|breach| breach.take())
+}
+
+// Return the string literals of a list given as the value of a rule, such as ["oops", "TODO"]
+fn strings(rule: &str, value: &str) -> Vec<String> {
+    let list = value.strip_prefix('[').and_then(|value| value.strip_suffix(']'))
+        .unwrap_or_else(|| panic!("The message policy rule '{rule}' must be a list of strings, such as [\"oops\"]"));
+    crate::analyse(list.chars()).into_iter()
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.strip_prefix('"').and_then(|item| item.strip_suffix('"')).map(str::to_string)
+                .unwrap_or_else(|| panic!("The message policy rule '{rule}' must be a list of strings, such as [\"oops\"]"))
+        })
+        .collect()
+}
+
+// Read the policy from the file named by the NUHOUND_POLICY environment variable, which is relative to
+// the directory of the manifest of the crate being compiled, or from the [policy] section of the
+// nuhound.toml settings, or return None when there is no policy
+pub(crate) fn load() -> Option<Policy> {
+    let Some(path) = env::var_os("NUHOUND_POLICY").filter(|path| !path.is_empty()) else {
+        return config::policy().map(|text| Policy::parse(&text));
+    };
+    let path = Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default()).join(path);
+    let text = fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("Unable to read the message policy '{}': {}", path.display(), error));
+    Some(Policy::parse(&text))
+}
@@ -0,0 +1,344 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that records information gathered from the macros of a crate as it is compiled
+
+use crate::flags;
+use crate::rewrite;
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+// The site of an error message, whose position among the sites of its crate gives its number
+struct Site {
+    krate: String,
+    file: String,
+    line: usize,
+    column: usize,
+    message: String,
+    // The key of a message that is looked up in the catalog, in place of its format string
+    key: Option<String>,
+    // The error code given to the error, which may only be given to one site of the crate
+    code: Option<String>,
+    // The severity and tags given to the error
+    severity: Option<&'static str>,
+    tags: Vec<String>,
+}
+
+// The sites of the error messages of each crate, which are held for the life of the compiler
+// process so that they may be gathered from many macro invocations
+static SITES: Mutex<Vec<Site>> = Mutex::new(Vec::new());
+
+// Return the name of the crate being compiled, which distinguishes the crates that are compiled by
+// the same process such as when the macros are used by an IDE
+pub(crate) fn crate_name() -> String {
+    env::var("CARGO_CRATE_NAME").unwrap_or_default()
+}
+
+// Return the error codes, as string literals, of the functions marked with the public_api attribute
+// within the module held in the given source file and the modules declared within it. The codes are
+// read from the source rather than gathered as the functions are compiled so that they don't depend
+// on the order in which the macros are expanded.
+pub(crate) fn codes(file: &Path) -> Vec<String> {
+    let mut codes = Vec::new();
+    let directory = match file.file_stem().and_then(|stem| stem.to_str()) {
+        Some("lib" | "main" | "mod") => file.parent().map(Path::to_path_buf),
+        Some(stem) => file.parent().map(|parent| parent.join(stem)),
+        None => None,
+    };
+    let stream = fs::read_to_string(file).ok().and_then(|source| source.parse::<TokenStream>().ok());
+    if let (Some(stream), Some(directory)) = (stream, directory) {
+        module_codes(stream, &directory, &mut codes);
+    }
+    codes
+}
+
+// Gather the error codes of the functions marked with the public_api attribute within the tokens of
+// a module, whose submodules are held in the given directory
+fn module_codes(stream: TokenStream, directory: &Path, codes: &mut Vec<String>) {
+    let trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut public_api = false;
+    let mut path = None;
+    let mut index = 0;
+    while index < trees.len() {
+        match (&trees[index], trees.get(index + 1), trees.get(index + 2)) {
+            (TokenTree::Punct(punct), Some(TokenTree::Group(group)), _)
+                if punct.as_char() == '#' && group.delimiter() == Delimiter::Bracket => {
+                let attribute: Vec<String> = group.stream().into_iter().map(|tree| tree.to_string()).collect();
+                public_api |= attribute.last().is_some_and(|name| name == "public_api");
+                if let [name, equals, file] = attribute.as_slice() && name == "path" && equals == "=" {
+                    path = Some(directory.join(file.trim_matches('"')));
+                }
+                index += 2;
+                continue;
+            }
+            (TokenTree::Ident(keyword), Some(TokenTree::Ident(name)), Some(next)) if keyword.to_string() == "mod" => {
+                let name = name.to_string();
+                match next {
+                    TokenTree::Group(group) => module_codes(group.stream(), &directory.join(&name), codes),
+                    _ => {
+                        let file = path.take()
+                            .or_else(|| Some(directory.join(format!("{name}.rs"))).filter(|file| file.exists()))
+                            .unwrap_or_else(|| directory.join(&name).join("mod.rs"));
+                        for code in self::codes(&file) {
+                            if !codes.contains(&code) {
+                                codes.push(code);
+                            }
+                        }
+                    }
+                }
+                (public_api, path) = (false, None);
+                index += 3;
+                continue;
+            }
+            (TokenTree::Group(group), _, _) if group.delimiter() == Delimiter::Brace => {
+                if public_api {
+                    for (_, input) in rewrite::find_macros(group.stream(), &["custom", "convert", "examine"]) {
+                        let code = crate::public_api_code(&input.to_string());
+                        if let Some(code) = code.filter(|code| !codes.contains(code)) {
+                            codes.push(code);
+                        }
+                    }
+                }
+                (public_api, path) = (false, None);
+            }
+            (TokenTree::Punct(punct), _, _) if punct.as_char() == ';' => (public_api, path) = (false, None),
+            _ => (),
+        }
+        index += 1;
+    }
+}
+
+// Return the file, line and column of the input of the macro being expanded, or of the macro itself
+// when that is not known
+pub(crate) fn location() -> (String, usize, usize) {
+    flags::location()
+        .or_else(|| proc_macro::is_available().then(|| {
+            let span = proc_macro::Span::call_site();
+            (span.file(), span.line(), span.column())
+        }))
+        .unwrap_or_default()
+}
+
+// Record the site of an error message for the crate being compiled, returning the number that
+// identifies it within the crate. The site is the location of the input of the macro being
+// expanded, or of the macro itself when that is not known. The format string of the message as it
+// is written is recorded in preference to the one given, which may have been extended with the
+// error code. The error code of the site, if any, must not already have been given to another site
+// of the crate.
+pub(crate) fn register_site(message: &str) -> usize {
+    let (file, line, column) = location();
+    let name = crate_name();
+    let flags = flags::current();
+    let mut sites = SITES.lock().unwrap();
+    let mut number = 0;
+    for site in sites.iter().filter(|site| site.krate == name) {
+        number += 1;
+        if (&site.file, site.line, site.column) == (&file, line, column) {
+            return number;
+        }
+    }
+    let shared = flags.code.as_ref()
+        .and_then(|code| sites.iter().find(|site| site.krate == name && site.code.as_ref() == Some(code)))
+        .map(|site| format!("{}:{}:{}", site.file, site.line, site.column));
+    if let Some(other) = shared {
+        // The lock is released first so that it isn't poisoned for the invocations that follow
+        drop(sites);
+        panic!("The error code \"{}\" at {file}:{line}:{column} is already given to the error at {other}",
+            flags.code.unwrap_or_default());
+    }
+    let tags = flags.tags.iter().map(|tag| tag.trim_matches('"').to_string()).collect();
+    sites.push(Site {
+        krate: name.clone(), file, line, column, message: flags.template.unwrap_or_else(|| message.to_string()),
+        key: flags.key, code: flags.code, severity: flags.severity, tags,
+    });
+    drop(sites);
+    OUTPUTS.with(|outputs| {
+        let mut crates = outputs.0.borrow_mut();
+        if !crates.contains(&name) {
+            crates.push(name);
+        }
+    });
+    number + 1
+}
+
+// The crates whose sites are to be written to the symbol map, message catalog and manifest. These
+// are written once the compiler has finished with the macros, when the thread that expanded them
+// exits, rather than as each site is recorded.
+struct Outputs(RefCell<Vec<String>>);
+
+impl Drop for Outputs {
+    fn drop(&mut self) {
+        for name in self.0.get_mut().drain(..) {
+            write_symbol_map(&name);
+            write_catalog(&name);
+            write_manifest(&name);
+        }
+    }
+}
+
+thread_local! {
+    static OUTPUTS: Outputs = const { Outputs(RefCell::new(Vec::new())) };
+}
+
+// Write the sites of the crate to a JSON file named after it within the directory named by the
+// NUHOUND_SYMBOL_MAP environment variable, if any, mapping the number of each site to its file,
+// line, column and message so that obfuscated traces may be read
+fn write_symbol_map(name: &str) {
+    let Some(directory) = env::var_os("NUHOUND_SYMBOL_MAP").filter(|directory| !directory.is_empty()) else {
+        return;
+    };
+    let entries: Vec<String> = SITES.lock().unwrap().iter()
+        .filter(|site| site.krate == name)
+        .enumerate()
+        .map(|(index, site)| {
+            format!("  \"{}\": {{\"file\": {}, \"line\": {}, \"column\": {}, \"msg\": {}}}",
+                index + 1, quote(&site.file), site.line, site.column, quote(&site.message))
+        })
+        .collect();
+    let path = Path::new(&directory).join(format!("{name}.json"));
+    fs::create_dir_all(&directory)
+        .and_then(|_| fs::write(&path, format!("{{\n{}\n}}\n", entries.join(",\n"))))
+        .unwrap_or_else(|error| panic!("Unable to write the symbol map '{}': {}", path.display(), error));
+}
+
+// Write the catalog of the messages of the crate to a file named after it within the directory
+// named by the NUHOUND_MESSAGES environment variable, if any
+fn write_catalog(name: &str) {
+    let Some(directory) = env::var_os("NUHOUND_MESSAGES").filter(|directory| !directory.is_empty()) else {
+        return;
+    };
+    let path = Path::new(&directory).join(format!("{name}-messages.json"));
+    fs::create_dir_all(&directory)
+        .and_then(|_| fs::write(&path, messages(name)))
+        .unwrap_or_else(|error| panic!("Unable to write the message catalog '{}': {}", path.display(), error));
+}
+
+// Write the manifest of the sites of the crate to the file named by the NUHOUND_MANIFEST environment
+// variable, if any, in which {crate} stands for the name of the crate
+fn write_manifest(name: &str) {
+    let Some(path) = env::var("NUHOUND_MANIFEST").ok().filter(|path| !path.is_empty()) else {
+        return;
+    };
+    let path = path.replace("{crate}", name);
+    let path = Path::new(&path);
+    path.parent()
+        .filter(|directory| !directory.as_os_str().is_empty())
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, manifest(name)))
+        .unwrap_or_else(|error| panic!("Unable to write the manifest '{}': {}", path.display(), error));
+}
+
+// Return the manifest of the sites recorded so far for the named crate as a JSON array
+// holding the number, file, line, column and format string of the message of each site together
+// with the error code, severity and tags given to it, which are null or empty when not given, so
+// that every error that the crate can emit may be inventoried
+pub(crate) fn manifest(name: &str) -> String {
+    let optional = |value: Option<&str>| value.map(quote).unwrap_or_else(|| String::from("null"));
+    let entries: Vec<String> = SITES.lock().unwrap().iter()
+        .filter(|site| site.krate == name)
+        .enumerate()
+        .map(|(index, site)| {
+            let tags: Vec<String> = site.tags.iter().map(|tag| quote(tag)).collect();
+            format!("  {{\"site\": {}, \"file\": {}, \"line\": {}, \"column\": {}, \"msg\": {}, \"code\": {}, \"severity\": {}, \"tags\": [{}]}}",
+                index + 1, quote(&site.file), site.line, site.column, quote(&site.message),
+                optional(site.code.as_deref()), optional(site.severity), tags.join(", "))
+        })
+        .collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+// Return the catalog of the messages recorded so far for the named crate as a JSON array
+// holding the number, file, line, column and format string of the message of each site, together
+// with the key of a message that is looked up in the catalog of templates, for translation
+// workflows and message audits
+pub(crate) fn messages(name: &str) -> String {
+    let entries: Vec<String> = SITES.lock().unwrap().iter()
+        .filter(|site| site.krate == name)
+        .enumerate()
+        .map(|(index, site)| {
+            let key = site.key.as_deref().map(|key| format!(", \"key\": {}", quote(key))).unwrap_or_default();
+            format!("  {{\"site\": {}, \"file\": {}, \"line\": {}, \"column\": {}, \"msg\": {}{key}}}",
+                index + 1, quote(&site.file), site.line, site.column, quote(&site.message))
+        })
+        .collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+// Return the sites that have been recorded so far for the crate being compiled, each written as a
+// tuple of its number, file, line, column and message
+pub(crate) fn sites() -> Vec<String> {
+    let name = crate_name();
+    SITES.lock().unwrap().iter()
+        .filter(|site| site.krate == name)
+        .enumerate()
+        .map(|(index, site)| format!("({}, {:?}, {}, {}, {:?})", index + 1, site.file, site.line, site.column, site.message))
+        .collect()
+}
+
+// Return the sites that have been recorded so far for the crate being compiled, each written as a
+// tuple of its number, file and line
+pub(crate) fn locations() -> Vec<String> {
+    let name = crate_name();
+    SITES.lock().unwrap().iter()
+        .filter(|site| site.krate == name)
+        .enumerate()
+        .map(|(index, site)| format!("({}, {:?}, {})", index + 1, site.file, site.line))
+        .collect()
+}
+
+// Quote a string as a JSON string
+fn quote(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
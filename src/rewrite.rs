@@ -0,0 +1,736 @@
+// This project is licensed under either:
+//
+// - Apache License, Version 2.0, https://www.apache.org/licenses/LICENSE-2.0)
+// - MIT license https://opensource.org/licenses/MIT)
+//
+// Copyright 2025 Porter
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// MIT License
+// 
+// Copyright (c) 2025 Porter
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+//
+//! A module that rewrites the tokens of a function body
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+// Keywords that may not begin or continue the expression to which a question mark operator applies
+const KEYWORDS: [&str; 36] = [
+    "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "yield",
+];
+
+// The modules of the standard library that are re-exported from the alloc crate and from the core
+// crate, whose paths are rewritten for crates built without the standard library
+const ALLOC: [&str; 5] = ["borrow", "boxed", "format", "string", "vec"];
+const CORE: [&str; 16] = [
+    "any", "char", "cmp", "convert", "error", "fmt", "hash", "iter", "marker", "mem", "num", "ops",
+    "option", "panic", "result", "str",
+];
+
+// The macros that only the standard library provides, as they print to the standard streams
+const STD_MACROS: [&str; 5] = ["dbg", "eprint", "eprintln", "print", "println"];
+
+// The names in the prelude of the standard library that are not in the prelude of the core crate,
+// together with their paths in the alloc crate
+const PRELUDE: [(&str, &[&str]); 7] = [
+    ("String", &["alloc", "string", "String"]),
+    ("Vec", &["alloc", "vec", "Vec"]),
+    ("Box", &["alloc", "boxed", "Box"]),
+    ("ToString", &["alloc", "string", "ToString"]),
+    ("ToOwned", &["alloc", "borrow", "ToOwned"]),
+    ("format", &["alloc", "format"]),
+    ("vec", &["alloc", "vec"]),
+];
+
+// The methods of the traits that are not in the prelude of the core crate, together with their
+// paths in the alloc crate
+const METHODS: [(&str, &[&str]); 2] = [
+    ("to_string", &["alloc", "string", "ToString", "to_string"]),
+    ("to_owned", &["alloc", "borrow", "ToOwned", "to_owned"]),
+];
+
+// Replace each occurrence of the placeholder identifier in the generated tokens with the replacement
+pub(crate) fn splice(generated: TokenStream, placeholder: &str, replacement: &TokenTree) -> TokenStream {
+    generated.into_iter().map(|tree| match tree {
+        TokenTree::Ident(ident) if ident.to_string() == placeholder => replacement.clone(),
+        TokenTree::Group(group) => {
+            let mut spliced = Group::new(group.delimiter(), splice(group.stream(), placeholder, replacement));
+            spliced.set_span(group.span());
+            TokenTree::Group(spliced)
+        }
+        tree => tree,
+    }).collect()
+}
+
+// The keywords that may qualify a function, and those that begin the other items that a macro may
+// generate
+const QUALIFIERS: [&str; 5] = ["async", "const", "default", "extern", "unsafe"];
+const ITEMS: [&str; 10] = ["enum", "impl", "macro_rules", "mod", "static", "struct", "trait", "type", "union", "use"];
+
+// Add an item to the tokens generated by a macro, placing it where an item is allowed. It begins the
+// body of a generated function, precedes a generated statement, follows other generated items and
+// is wrapped in a block together with a generated expression.
+pub(crate) fn register(stream: TokenStream, item: TokenStream) -> TokenStream {
+    let mut trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut index = 0;
+    let mut qualified = false;
+    let keyword = loop {
+        match (trees.get(index), trees.get(index + 1)) {
+            (Some(TokenTree::Punct(punct)), Some(TokenTree::Group(group)))
+                if punct.as_char() == '#' && group.delimiter() == Delimiter::Bracket => index += 2,
+            (Some(TokenTree::Ident(ident)), Some(TokenTree::Group(group)))
+                if ident.to_string() == "pub" && group.delimiter() == Delimiter::Parenthesis => index += 2,
+            (Some(TokenTree::Ident(ident)), _) if ident.to_string() == "pub" => index += 1,
+            (Some(TokenTree::Ident(ident)), _) if QUALIFIERS.contains(&ident.to_string().as_str()) => {
+                qualified = true;
+                index += 1;
+            }
+            (Some(TokenTree::Literal(_)), _) if qualified => index += 1,
+            (Some(TokenTree::Ident(ident)), _) => break ident.to_string(),
+            _ => break String::new(),
+        }
+    };
+    match keyword.as_str() {
+        "fn" => {
+            let body = trees.iter_mut().rev().find_map(|tree| match tree {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => Some(group),
+                _ => None,
+            });
+            if let Some(body) = body {
+                let span = body.span();
+                *body = Group::new(Delimiter::Brace, item.into_iter().chain(body.stream()).collect());
+                body.set_span(span);
+            }
+            TokenStream::from_iter(trees)
+        }
+        "let" => item.into_iter().chain(trees).collect(),
+        _ if qualified || ITEMS.contains(&keyword.as_str()) => trees.into_iter().chain(item).collect(),
+        _ => TokenStream::from(TokenTree::Group(Group::new(Delimiter::Brace, item.into_iter().chain(trees).collect()))),
+    }
+}
+
+// Rewrite every method within the body of an impl block. The items of the block are separated and
+// each function, together with the span of its name, is passed to the given function so that it
+// may be replaced. All other items are left untouched.
+pub(crate) fn rewrite_methods(stream: TokenStream, rewrite: &dyn Fn(TokenStream, Span) -> TokenStream) -> TokenStream {
+    let mut output = Vec::new();
+    let mut item: Vec<TokenTree> = Vec::new();
+    for tree in stream {
+        match &tree {
+            TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                output.append(&mut item);
+                output.push(tree);
+            }
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                item.push(tree);
+                match function_name(&item) {
+                    Some(span) => output.extend(rewrite(TokenStream::from_iter(item.drain(..)), span)),
+                    None if is_item(&item) => output.append(&mut item),
+                    None => {}
+                }
+            }
+            _ => item.push(tree),
+        }
+    }
+    output.extend(item);
+    TokenStream::from_iter(output)
+}
+
+// Return the span of the name of a function item. The fn keyword of a function pointer type is not
+// followed by a name, which distinguishes a constant of that type from a function.
+fn function_name(item: &[TokenTree]) -> Option<Span> {
+    item.windows(2).find_map(|pair| match pair {
+        [TokenTree::Ident(keyword), TokenTree::Ident(name)] if keyword.to_string() == "fn" => Some(name.span()),
+        _ => None,
+    })
+}
+
+// Determine whether the braces at the end of the tokens complete an item that is not a function,
+// such as a macro invocation, rather than forming part of an expression within an item.
+fn is_item(item: &[TokenTree]) -> bool {
+    matches!(item.iter().rev().nth(1), Some(TokenTree::Punct(punct)) if punct.as_char() == '!')
+}
+
+// Insert the flag at the start of the input of every nuhound macro, and of every nuhound attribute
+// with one of the given names, within the tokens of an item including those in nested groups. The
+// names of the function like macros are given and they are recognised when they are qualified by
+// the nuhound crate or, unqualified, when the item doesn't import or define others of that name.
+pub(crate) fn flag_macros(stream: TokenStream, flag: &str, macros: &[&str], attributes: &[&str]) -> TokenStream {
+    let names = Names::new(&stream, macros);
+    flag_invocations(stream, flag, &names, attributes)
+}
+
+// Insert the flag into the invocations of the nuhound macros and attributes within the tokens, as
+// flag_macros does, given the names by which the macros may be invoked
+fn flag_invocations(stream: TokenStream, flag: &str, names: &Names, attributes: &[&str]) -> TokenStream {
+    let trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut output = Vec::new();
+    for (index, tree) in trees.iter().enumerate() {
+        let TokenTree::Group(group) = tree else {
+            output.push(tree.clone());
+            continue;
+        };
+        let previous = index.checked_sub(1).map(|index| &trees[index]);
+        let stream = match previous {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' && group.delimiter() == Delimiter::Bracket => {
+                flag_attribute(group.stream(), flag, attributes)
+            }
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '!' && names.invoked(&trees[..index - 1]) => {
+                let mut stream: TokenStream = flag.parse().unwrap();
+                stream.extend(flag_invocations(group.stream(), flag, names, attributes));
+                stream
+            }
+            _ => flag_invocations(group.stream(), flag, names, attributes),
+        };
+        let mut flagged = Group::new(group.delimiter(), stream);
+        flagged.set_span(group.span());
+        output.push(TokenTree::Group(flagged));
+    }
+    TokenStream::from_iter(output)
+}
+
+// Insert the flag given for the input of every nuhound macro, if any, at the start of that input
+// within the tokens, including those in nested groups, as flag_macros does with a single flag
+pub(crate) fn flag_each_macro(stream: TokenStream, macros: &[&str], flag: &dyn Fn(&TokenStream) -> Option<String>) -> TokenStream {
+    let names = Names::new(&stream, macros);
+    flag_each_invocation(stream, &names, flag)
+}
+
+// Insert the flag given for the input of every invocation of the nuhound macros within the tokens,
+// as flag_each_macro does, given the names by which the macros may be invoked
+fn flag_each_invocation(stream: TokenStream, names: &Names, flag: &dyn Fn(&TokenStream) -> Option<String>) -> TokenStream {
+    let trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut output = Vec::new();
+    for (index, tree) in trees.iter().enumerate() {
+        let TokenTree::Group(group) = tree else {
+            output.push(tree.clone());
+            continue;
+        };
+        let mut stream = flag_each_invocation(group.stream(), names, flag);
+        let invoked = index > 1 && is_punct(trees.get(index - 1), '!') && names.invoked(&trees[..index - 1]);
+        if let Some(flag) = invoked.then(|| flag(&group.stream())).flatten() {
+            let mut flagged: TokenStream = flag.parse().unwrap();
+            flagged.extend(stream);
+            stream = flagged;
+        }
+        let mut flagged = Group::new(group.delimiter(), stream);
+        flagged.set_span(group.span());
+        output.push(TokenTree::Group(flagged));
+    }
+    TokenStream::from_iter(output)
+}
+
+// Find every invocation of one of the macros within the tokens, including those in nested groups,
+// returning the span of the name of each macro together with its input
+pub(crate) fn find_macros(stream: TokenStream, macros: &[&str]) -> Vec<(Span, TokenStream)> {
+    let names = Names::new(&stream, macros);
+    find_invocations(stream, &names)
+}
+
+// Find every invocation of the nuhound macros within the tokens, as find_macros does, given the
+// names by which the macros may be invoked
+fn find_invocations(stream: TokenStream, names: &Names) -> Vec<(Span, TokenStream)> {
+    let trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut found = Vec::new();
+    for (index, tree) in trees.iter().enumerate() {
+        let TokenTree::Group(group) = tree else {
+            continue;
+        };
+        match index.checked_sub(1).map(|index| &trees[index]) {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '!' && names.invoked(&trees[..index - 1]) => {
+                found.push((trees[index - 2].span(), group.stream()));
+            }
+            _ => (),
+        }
+        found.extend(find_invocations(group.stream(), names));
+    }
+    found
+}
+
+// The names by which the nuhound macros may be invoked within an item. A macro qualified by the
+// nuhound crate is always one of them, while an unqualified name is taken to be a nuhound macro
+// unless the item imports or defines another macro of that name. A nuhound macro that the item
+// imports under another name may be invoked by that name.
+struct Names<'a> {
+    macros: &'a [&'a str],
+    unqualified: Vec<String>,
+}
+
+impl<'a> Names<'a> {
+    // Find the names by which the macros may be invoked from the imports and definitions of the item
+    fn new(stream: &TokenStream, macros: &'a [&'a str]) -> Self {
+        let mut imports = Vec::new();
+        let mut defined = Vec::new();
+        find_imports(stream.clone(), &mut imports, &mut defined);
+        let mut aliases = Vec::new();
+        for (path, alias) in imports {
+            let name = path.last().map(String::as_str).unwrap_or_default();
+            if is_nuhound(&path[0]) && macros.contains(&name) {
+                aliases.push(alias);
+            } else {
+                defined.push(alias);
+            }
+        }
+        let mut unqualified: Vec<String> = macros.iter()
+            .map(|name| name.to_string())
+            .filter(|name| !defined.contains(name))
+            .collect();
+        unqualified.extend(aliases);
+        Self { macros, unqualified }
+    }
+
+    // Determine whether the path at the end of the tokens names one of the macros, either on its own
+    // or qualified by the nuhound crate
+    fn invoked(&self, path: &[TokenTree]) -> bool {
+        let Some((TokenTree::Ident(name), rest)) = path.split_last() else {
+            return false;
+        };
+        let name = name.to_string();
+        match rest {
+            [.., TokenTree::Ident(krate), TokenTree::Punct(first), TokenTree::Punct(second)]
+                if first.as_char() == ':' && second.as_char() == ':' => {
+                is_nuhound(&krate.to_string()) && self.macros.contains(&name.as_str())
+            }
+            [.., TokenTree::Punct(punct)] if punct.as_char() == ':' => false,
+            _ => self.unqualified.contains(&name),
+        }
+    }
+}
+
+// Determine whether the name is that of the nuhound crate, or of this crate that it re-exports
+fn is_nuhound(name: &str) -> bool {
+    name == "nuhound" || name == "proc_nuhound"
+}
+
+// Gather the path and the name given to each item imported by the use declarations within the
+// tokens, including those in nested groups, along with the names of the macros that they define
+fn find_imports(stream: TokenStream, imports: &mut Vec<(Vec<String>, String)>, defined: &mut Vec<String>) {
+    let trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut index = 0;
+    while index < trees.len() {
+        match &trees[index] {
+            TokenTree::Ident(ident) if ident.to_string() == "use" => {
+                let end = (index..trees.len()).find(|&end| is_punct(trees.get(end), ';')).unwrap_or(trees.len());
+                use_tree(&trees[index + 1..end], Vec::new(), imports);
+                index = end;
+            }
+            TokenTree::Ident(ident) if ident.to_string() == "macro_rules" && is_punct(trees.get(index + 1), '!') => {
+                if let Some(TokenTree::Ident(name)) = trees.get(index + 2) {
+                    defined.push(name.to_string());
+                }
+            }
+            TokenTree::Group(group) => find_imports(group.stream(), imports, defined),
+            _ => (),
+        }
+        index += 1;
+    }
+}
+
+// Gather the path and the name given to each item imported by a use tree, whose path begins with
+// the given prefix
+fn use_tree(trees: &[TokenTree], mut prefix: Vec<String>, imports: &mut Vec<(Vec<String>, String)>) {
+    let mut index = 0;
+    while let Some(tree) = trees.get(index) {
+        match tree {
+            TokenTree::Ident(ident) if is_punct(trees.get(index + 1), ':') => {
+                prefix.push(ident.to_string());
+                index += 3;
+            }
+            TokenTree::Ident(ident) => {
+                let alias = match (trees.get(index + 1), trees.get(index + 2)) {
+                    (Some(TokenTree::Ident(keyword)), Some(alias)) if keyword.to_string() == "as" => alias.to_string(),
+                    _ => ident.to_string(),
+                };
+                prefix.push(ident.to_string());
+                imports.push((prefix, alias));
+                return;
+            }
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                let nested: Vec<TokenTree> = group.stream().into_iter().collect();
+                for branch in nested.split(|tree| is_punct(Some(tree), ',')) {
+                    use_tree(branch, prefix.clone(), imports);
+                }
+                return;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == ':' => index += 1,
+            _ => return,
+        }
+    }
+}
+
+// Insert the flag at the start of the arguments of an attribute qualified by the nuhound crate with
+// one of the given names, adding the arguments when there are none
+fn flag_attribute(stream: TokenStream, flag: &str, attributes: &[&str]) -> TokenStream {
+    let mut trees: Vec<TokenTree> = stream.into_iter().collect();
+    let path: Vec<String> = trees.iter()
+        .take_while(|tree| !matches!(tree, TokenTree::Group(_)))
+        .filter(|tree| !is_punct(Some(tree), ':'))
+        .map(|tree| tree.to_string())
+        .collect();
+    if !matches!(path.as_slice(), [krate, name] if is_nuhound(krate) && attributes.contains(&name.as_str())) {
+        return TokenStream::from_iter(trees);
+    }
+    match trees.last_mut() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            let mut stream: TokenStream = flag.parse().unwrap();
+            stream.extend(group.stream());
+            let mut flagged = Group::new(Delimiter::Parenthesis, stream);
+            flagged.set_span(group.span());
+            *group = flagged;
+        }
+        _ => trees.push(TokenTree::Group(Group::new(Delimiter::Parenthesis, flag.parse().unwrap()))),
+    }
+    TokenStream::from_iter(trees)
+}
+
+// Give every token the same span. Generated tokens are given the span of the user's code that
+// they replace so that file!(), line!() and column!() report the location of that code.
+pub(crate) fn respan(stream: TokenStream, span: Span) -> TokenStream {
+    stream.into_iter().map(|tree| match tree {
+        TokenTree::Group(group) => {
+            let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+            respanned.set_span(span);
+            TokenTree::Group(respanned)
+        }
+        mut tree => {
+            tree.set_span(span);
+            tree
+        }
+    }).collect()
+}
+
+// Find the span of the first literal within the tokens, including those in nested groups, that
+// is written as the given text
+pub(crate) fn find_literal(stream: TokenStream, text: &str) -> Option<Span> {
+    stream.into_iter().find_map(|tree| match tree {
+        TokenTree::Group(group) => find_literal(group.stream(), text),
+        TokenTree::Literal(literal) if literal.to_string() == text => Some(literal.span()),
+        _ => None,
+    })
+}
+
+// Rewrite every question mark operator within the tokens, including those in nested groups, so that
+// the expression it applies to is replaced by generated code. The generator is given the text of
+// the expression and returns code in which the placeholder stands for the expression, or None to
+// leave the expression unchanged. The generated code takes the location of the question mark whilst
+// the expression keeps its own.
+pub(crate) fn rewrite_try(stream: TokenStream, placeholder: &str, generate: &dyn Fn(&str) -> Option<String>) -> TokenStream {
+    // The original tokens are kept alongside the output so that the text of an expression does not
+    // include the code generated for any expressions nested within it. Rewritten expressions are
+    // held in the original tokens as undelimited groups so that both lists have the same length.
+    let mut output: Vec<TokenTree> = Vec::new();
+    let mut original: Vec<TokenTree> = Vec::new();
+    for tree in stream {
+        match tree {
+            TokenTree::Punct(punct) if punct.as_char() == '?' => {
+                let start = expression_start(&output);
+                let generated = if start < output.len() { generate(&source(&original[start..])) } else { None };
+                if let Some(generated) = generated {
+                    let expression = TokenStream::from_iter(output.drain(start..));
+                    let text: Vec<TokenTree> = original.drain(start..).collect();
+                    let replacement = TokenTree::Group(Group::new(Delimiter::Parenthesis, expression));
+                    let generated = generated.parse().unwrap();
+                    let generated = splice(respan(generated, punct.span()), placeholder, &replacement);
+                    let mut group = Group::new(Delimiter::Parenthesis, generated);
+                    group.set_span(punct.span());
+                    output.push(TokenTree::Group(group));
+                    original.push(TokenTree::Group(Group::new(Delimiter::None, TokenStream::from_iter(text))));
+                }
+                output.push(TokenTree::Punct(punct.clone()));
+                original.push(TokenTree::Punct(punct));
+            }
+            TokenTree::Group(group) => {
+                let mut rewritten = Group::new(group.delimiter(), rewrite_try(group.stream(), placeholder, generate));
+                rewritten.set_span(group.span());
+                output.push(TokenTree::Group(rewritten));
+                original.push(TokenTree::Group(group));
+            }
+            tree => {
+                output.push(tree.clone());
+                original.push(tree);
+            }
+        }
+    }
+    output.into_iter().collect()
+}
+
+// Return the source text of the tokens of an expression. Where the source is not available, such
+// as for tokens produced by another macro, the tokens are converted to text instead.
+fn source(trees: &[TokenTree]) -> String {
+    let mut text = String::new();
+    for tree in trees {
+        match tree {
+            TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+                text.push_str(&source(&group.stream().into_iter().collect::<Vec<_>>()));
+            }
+            tree => {
+                let token = tree.span().source_text().unwrap_or_else(|| tree.to_string());
+                if text.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                    && token.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    text.push(' ');
+                }
+                text.push_str(&token);
+                if token == "," {
+                    text.push(' ');
+                }
+            }
+        }
+    }
+    text
+}
+
+// Determine whether the identifier is a keyword
+fn is_keyword(ident: &Ident) -> bool {
+    KEYWORDS.contains(&ident.to_string().as_str())
+}
+
+// Determine whether the token is the given punctuation character
+fn is_punct(tree: Option<&TokenTree>, character: char) -> bool {
+    matches!(tree, Some(TokenTree::Punct(punct)) if punct.as_char() == character)
+}
+
+// Return the number of tokens that connect the tokens before the index to a member, method or path
+// segment at the index. Member access uses '.', which must not be part of a range, and paths use
+// '::'. Zero is returned when there is no connection.
+fn connector(trees: &[TokenTree], index: usize) -> usize {
+    let before = |offset: usize| index.checked_sub(offset).and_then(|position| trees.get(position));
+    if is_punct(before(1), '.') && !is_punct(before(2), '.') {
+        1
+    } else if is_punct(before(1), ':') && matches!(before(2), Some(TokenTree::Punct(punct))
+        if punct.as_char() == ':' && punct.spacing() == Spacing::Joint) {
+        2
+    } else {
+        0
+    }
+}
+
+// Return the index of the first token of the postfix expression that ends the tokens. This is a
+// chain of paths, method calls, field accesses, indexing and awaits that the question mark
+// operator binds to. The length of the tokens is returned when there is no such expression, such as
+// for the '?' in '?Sized'.
+fn expression_start(trees: &[TokenTree]) -> usize {
+    let mut index = trees.len();
+    while index > 0 {
+        match &trees[index - 1] {
+            TokenTree::Group(group) if group.delimiter() != Delimiter::Brace => {
+                index -= 1;
+                continue;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '?' => {
+                index -= 1;
+                continue;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '!' && index > 1 => {
+                // A macro invocation, the arguments of which have already been included
+                if !matches!(&trees[index - 2], TokenTree::Ident(ident) if !is_keyword(ident)) {
+                    break;
+                }
+                index -= 2;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '>' => {
+                // A turbofish such as ::<u32> which must be preceded by a path separator
+                let mut depth = 0;
+                let mut open = index - 1;
+                loop {
+                    match &trees[open] {
+                        TokenTree::Punct(punct) if punct.as_char() == '>' => depth += 1,
+                        TokenTree::Punct(punct) if punct.as_char() == '<' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 || open == 0 {
+                        break;
+                    }
+                    open -= 1;
+                }
+                if depth != 0 || connector(trees, open) != 2 {
+                    break;
+                }
+                index = open - 2;
+                continue;
+            }
+            TokenTree::Ident(ident) if !is_keyword(ident)
+                || (ident.to_string() == "await" && connector(trees, index - 1) == 1) => {
+                index -= 1;
+            }
+            TokenTree::Literal(_) => {
+                index -= 1;
+            }
+            _ => break,
+        }
+        // A path or member may be connected to the expression that precedes it
+        match connector(trees, index) {
+            0 => break,
+            length => index -= length,
+        }
+    }
+    index
+}
+
+// Rewrite the generated tokens, including those in nested groups, for a crate that is built without
+// the standard library but with the alloc crate. Paths into the standard library are replaced by
+// those of the core or alloc crates that re-export the same items, while the types, macros and
+// methods of the standard prelude that the core prelude lacks, such as String, format! and
+// to_string, are given their full paths. Paths to items that only the standard library provides,
+// such as std::env, are left as they are.
+pub(crate) fn no_std(stream: TokenStream) -> TokenStream {
+    let trees: Vec<TokenTree> = stream.into_iter().collect();
+    let mut output: Vec<TokenTree> = Vec::new();
+    let mut index = 0;
+    while index < trees.len() {
+        let segment = |offset: usize| match trees.get(index + offset) {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => String::new(),
+        };
+        match &trees[index] {
+            TokenTree::Group(group) => {
+                let mut rewritten = Group::new(group.delimiter(), no_std(group.stream()));
+                rewritten.set_span(group.span());
+                output.push(TokenTree::Group(rewritten));
+            }
+            TokenTree::Ident(ident) if ident.to_string() == "std" && connector(&output, output.len()) == 2 => {
+                let module = segment(3);
+                let is_macro = is_punct(trees.get(index + 4), '!');
+                let krate = if ALLOC.contains(&module.as_str()) {
+                    "alloc"
+                } else if CORE.contains(&module.as_str())
+                    || (is_macro && !STD_MACROS.contains(&module.as_str()))
+                    || (module == "sync" && segment(6) == "atomic")
+                    || (module == "time" && segment(6) == "Duration") {
+                    "core"
+                } else {
+                    "std"
+                };
+                output.push(TokenTree::Ident(Ident::new(krate, ident.span())));
+            }
+            TokenTree::Ident(ident) if connector(&output, output.len()) == 0 => {
+                let name = ident.to_string();
+                let is_macro = is_punct(trees.get(index + 1), '!');
+                let prelude = PRELUDE.iter().find(|(known, segments)| *known == name && (segments.len() == 2) == is_macro);
+                match prelude {
+                    Some((_, segments)) => output.extend(path(segments, ident.span())),
+                    None if name == "str" && connector(&trees, index + 3) == 2 && segment(3) == "to_string" => {
+                        output.extend(path(METHODS[0].1, ident.span()));
+                        index += 4;
+                        continue;
+                    }
+                    None => output.push(TokenTree::Ident(ident.clone())),
+                }
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '.' && !is_punct(output.last(), '.') => {
+                let method = METHODS.iter().find(|(name, _)| *name == segment(1));
+                let called = matches!(trees.get(index + 2), Some(TokenTree::Group(group))
+                    if group.delimiter() == Delimiter::Parenthesis && group.stream().is_empty());
+                let start = expression_start(&output);
+                match method {
+                    Some((_, segments)) if called && start < output.len() => {
+                        let mut receiver = TokenStream::from(TokenTree::Punct(Punct::new('&', Spacing::Alone)));
+                        receiver.extend(output.drain(start..));
+                        output.extend(path(segments, punct.span()));
+                        output.push(TokenTree::Group(Group::new(Delimiter::Parenthesis, receiver)));
+                        index += 3;
+                        continue;
+                    }
+                    _ => output.push(TokenTree::Punct(punct.clone())),
+                }
+            }
+            tree => output.push(tree.clone()),
+        }
+        index += 1;
+    }
+    TokenStream::from_iter(output)
+}
+
+// The prefix of the placeholders that stand in for the identifiers of the input of a macro that the
+// no_std rewrite would replace, such as String or to_string, while the output of the macro is built
+const SHIELD: &str = "__nuhound_shield_";
+
+// Replace each identifier in the input of a macro that the no_std rewrite would replace with a
+// numbered placeholder of the same span, adding the identifier to those already shielded, so that
+// the rewrite leaves the code written by the user as it is
+pub(crate) fn shield(stream: TokenStream, shielded: &mut Vec<Ident>) -> TokenStream {
+    stream.into_iter().map(|tree| match tree {
+        TokenTree::Group(group) => {
+            let mut rewritten = Group::new(group.delimiter(), shield(group.stream(), shielded));
+            rewritten.set_span(group.span());
+            TokenTree::Group(rewritten)
+        }
+        TokenTree::Ident(ident) if is_rewritten(&ident.to_string()) => {
+            shielded.push(ident.clone());
+            TokenTree::Ident(Ident::new(&format!("{SHIELD}{}_", shielded.len() - 1), ident.span()))
+        }
+        tree => tree,
+    }).collect()
+}
+
+// Restore the identifiers held by the placeholders in the output of a macro, including those that
+// were written into its string literals, such as the text of an expression that is disclosed
+pub(crate) fn unshield(stream: TokenStream, shielded: &[Ident]) -> TokenStream {
+    let restore = |text: &str| (0..shielded.len())
+        .fold(text.to_string(), |text, index| text.replace(&format!("{SHIELD}{index}_"), &shielded[index].to_string()));
+    stream.into_iter().map(|tree| match tree {
+        TokenTree::Group(group) => {
+            let mut restored = Group::new(group.delimiter(), unshield(group.stream(), shielded));
+            restored.set_span(group.span());
+            TokenTree::Group(restored)
+        }
+        TokenTree::Ident(ident) => match ident.to_string().strip_prefix(SHIELD)
+            .and_then(|index| index.strip_suffix('_')?.parse::<usize>().ok()) {
+            Some(index) => TokenTree::Ident(shielded[index].clone()),
+            None => TokenTree::Ident(ident),
+        },
+        TokenTree::Literal(literal) if literal.to_string().contains(SHIELD) => {
+            let mut restored: Literal = restore(&literal.to_string()).parse().unwrap();
+            restored.set_span(literal.span());
+            TokenTree::Literal(restored)
+        }
+        tree => tree,
+    }).collect()
+}
+
+// Return whether an identifier is one that the no_std rewrite may replace
+fn is_rewritten(name: &str) -> bool {
+    name == "std" || PRELUDE.iter().any(|(known, _)| *known == name) || METHODS.iter().any(|(known, _)| *known == name)
+}
+
+// Return the tokens of a path from the root, such as ::alloc::string::String, with the given span
+fn path(segments: &[&str], span: Span) -> Vec<TokenTree> {
+    segments.iter().flat_map(|segment| {
+        let mut first = Punct::new(':', Spacing::Joint);
+        let mut second = Punct::new(':', Spacing::Alone);
+        first.set_span(span);
+        second.set_span(span);
+        [TokenTree::Punct(first), TokenTree::Punct(second), TokenTree::Ident(Ident::new(segment, span))]
+    }).collect()
+}
@@ -104,7 +104,7 @@ impl Scanner {
     pub(crate) fn get_string_attributes(&self) -> Vec<String> {
         let mut output = Vec::new();
         for attribute in &self.attributes {
-            let attr: String = self.char_string[attribute.start..attribute.end].into_iter().collect();
+            let attr: String = self.char_string[attribute.start..attribute.end].iter().collect();
             output.push(attr.trim().to_string())
         }
         output
@@ -123,13 +123,39 @@ impl Scanner {
         false
     }
 
-    // Determine whether a quote character has been escaped
-    pub(crate) fn is_escaped(&self) -> bool {
-        if self.index == 0 {
-            false
+    // Return the cursor position which is the index of the character following the current one
+    pub(crate) fn position(&self) -> usize {
+        self.index
+    }
+
+    // Return the character after the cursor position without moving the cursor
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.char_string.get(self.index).copied()
+    }
+
+    // Return the character before the current one if there is one otherwise return None
+    pub(crate) fn previous(&self) -> Option<char> {
+        if self.index < 2 {
+            None
         } else {
-            self.char_string[self.index - 1] == '\\'
+            Some(self.char_string[self.index - 2])
         }
     }
+
+    // Determine whether a single quote character at the cursor position starts a lifetime or loop
+    // label such as 'outer rather than a character literal such as 'o'
+    pub(crate) fn is_lifetime(&self) -> bool {
+        match self.char_string.get(self.index) {
+            Some(next) if next.is_alphanumeric() || *next == '_' => {
+                self.char_string.get(self.index + 1) != Some(&'\'')
+            }
+            _ => false,
+        }
+    }
+
+    // Determine whether a quote character has been escaped
+    pub(crate) fn is_escaped(&self) -> bool {
+        self.previous() == Some('\\')
+    }
 }
 
@@ -0,0 +1,102 @@
+// Behaviour tests that compile and run the expansions of the macros that note metadata on an error
+// and of those that read it back. The generated code refers to the nuhound crate, which is stood in
+// for by this crate. The expansions also carry the cfg attributes of the features of the crate that
+// uses the macros, which this crate doesn't declare.
+
+#![allow(unexpected_cfgs)]
+
+extern crate self as nuhound;
+
+use proc_nuhound::{custom, examine, is_permanent, report_json, severity_of, user_message};
+use std::error::Error;
+use std::fmt;
+
+// A stand in for the Nuhound error of the nuhound crate, holding just enough of it for the
+// expansions of the macros to be compiled and run, including the notes held apart from its message
+#[derive(Debug)]
+pub struct Nuhound {
+    message: String,
+    source: Option<Box<Nuhound>>,
+    notes: Vec<(String, String)>,
+}
+
+pub type Report<T> = Result<T, Nuhound>;
+
+impl Nuhound {
+    pub fn new<S: ToString>(message: S) -> Self {
+        Self { message: message.to_string(), source: None, notes: Vec::new() }
+    }
+
+    pub fn caused_by(mut self, cause: Nuhound) -> Self {
+        self.source = Some(Box::new(cause));
+        self
+    }
+
+    pub fn note<S: ToString>(mut self, key: &str, value: S) -> Self {
+        self.notes.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn noted(&self, key: &str) -> Option<&str> {
+        self.notes.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for Nuhound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for Nuhound {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+pub trait ResultExtension<T, E> {
+    fn report<O: FnOnce(E) -> Nuhound>(self, op: O) -> Report<T>;
+}
+
+impl<T, E> ResultExtension<T, E> for Result<T, E> {
+    fn report<O: FnOnce(E) -> Nuhound>(self, op: O) -> Report<T> {
+        self.map_err(op)
+    }
+}
+
+fn disk() -> Report<u32> {
+    custom!("disk nearly full", severity = warn)
+}
+
+fn flag() -> Report<u32> {
+    custom!("unsupported region 🇬🇧", code = "E3", retriable = false, user = "Try another region")
+}
+
+fn outer() -> Report<u32> {
+    examine!(flag(), "unable to load the map")
+}
+
+#[test]
+fn test_noted_severity() {
+    let error = disk().unwrap_err();
+    assert_eq!(error.to_string(), "disk nearly full");
+    assert_eq!(error.noted("severity"), Some("warn"));
+    assert_eq!(severity_of!(error).as_deref(), Some("warn"));
+}
+
+#[test]
+fn test_noted_chain() {
+    let error = outer().unwrap_err();
+    assert_eq!(error.to_string(), "unable to load the map");
+    assert_eq!(error.source().unwrap().to_string(), "[E3] unsupported region 🇬🇧");
+    assert_eq!(user_message!(error).as_deref(), Some("Try another region"));
+    assert!(is_permanent!(error));
+    assert!(!is_permanent!(disk().unwrap_err()));
+}
+
+#[test]
+fn test_noted_function_hash() {
+    let error = Nuhound::new("unable to load the map").note("function_hash", "4b0e2d");
+    assert_eq!(error.noted("function_hash"), Some("4b0e2d"));
+    assert_eq!(report_json!(error), "[{\"msg\":\"unable to load the map\",\"function_hash\":\"4b0e2d\"}]");
+}
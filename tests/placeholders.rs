@@ -0,0 +1,101 @@
+// Behaviour tests that compile and run the expansions of the todo_report and unimplemented_report
+// macros. The generated code refers to the nuhound crate, which is stood in for by this crate. The
+// expansions also carry the cfg attributes of the features of the crate that uses the macros, which
+// this crate doesn't declare.
+
+#![allow(unexpected_cfgs)]
+
+extern crate self as nuhound;
+
+use proc_nuhound::{todo_report, unimplemented_report};
+use std::error::Error;
+use std::fmt;
+
+// A stand in for the Nuhound error of the nuhound crate, holding just enough of it for the
+// expansions of the macros to be compiled and run, including the notes held apart from its message
+#[derive(Debug)]
+pub struct Nuhound {
+    message: String,
+    source: Option<Box<Nuhound>>,
+    notes: Vec<(String, String)>,
+}
+
+pub type Report<T> = Result<T, Nuhound>;
+
+impl Nuhound {
+    pub fn new<S: ToString>(message: S) -> Self {
+        Self { message: message.to_string(), source: None, notes: Vec::new() }
+    }
+
+    pub fn caused_by(mut self, cause: Nuhound) -> Self {
+        self.source = Some(Box::new(cause));
+        self
+    }
+
+    pub fn note<S: ToString>(mut self, key: &str, value: S) -> Self {
+        self.notes.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    // Return the messages of the error and its causes, one line for each layer
+    pub fn trace(&self) -> String {
+        let layers = std::iter::successors(Some(self), |layer| layer.source.as_deref());
+        layers.enumerate().map(|(index, layer)| format!("{index}: {}\n", layer.message)).collect()
+    }
+}
+
+impl fmt::Display for Nuhound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for Nuhound {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|source| source as &(dyn Error + 'static))
+    }
+}
+
+fn export(format: &str) -> Report<String> {
+    match format {
+        "csv" => Ok(String::from("a,b")),
+        "xml" => todo_report!("{} export", format),
+        "pdf" => unimplemented_report!("{} export is not supported", format),
+        _ => todo_report!(),
+    }
+}
+
+fn report(format: &str) -> Report<String> {
+    export(format).map_err(|error| Nuhound::new(format!("unable to export the {format} report")).caused_by(error))
+}
+
+#[test]
+fn test_todo_report() {
+    let result = export("xml").unwrap_err().to_string();
+    assert_eq!(result, "not yet implemented: xml export");
+    let result = export("json").unwrap_err().to_string();
+    assert_eq!(result, "not yet implemented");
+    assert_eq!(export("csv").unwrap(), "a,b");
+}
+
+#[test]
+fn test_unimplemented_report() {
+    let result = export("pdf").unwrap_err().to_string();
+    assert_eq!(result, "not implemented: pdf export is not supported");
+}
+
+#[test]
+fn test_placeholder_trace() {
+    let trace = report("xml").unwrap_err().trace();
+    let result_parts: Vec<&str> = trace.split("\n")
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    let required = vec![
+        "0: unable to export the xml report",
+        "1: not yet implemented: xml export",
+    ];
+    println!("{result_parts:#?}");
+    assert_eq!(result_parts, required);
+}